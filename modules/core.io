@@ -3,23 +3,22 @@ module core;
 // Never
 extern Never;
 
-// prints message and halts
-extern panic : (message: Utf8) -> Never;
+// prints message with the calls in progress and halts
+pub extern panic : (message: Utf8) -> Never;
+
+// runs f, a panic or runtime error inside it becomes Error(message) instead of halting
+pub extern catch_panic : (f: () -> T) -> Result<T, Utf8>;
 
 type Debug : trait{
   format: (self) -> Utf8;
 }
 
-panic<E: Debug> : (error: E) -> Never = {
-  panic(e.format());
-}
-
-type Result<R, E>: enum{
+pub type Result<R, E>: enum{
   Ok: R,
   Error: E,
 }
 
-type Maybe<T> = enum{
+pub type Maybe<T> = enum{
   Some: T;
   None;
 };
@@ -1,3 +1,6 @@
 module math;
 
-extern sqrt: (v:f64) -> f64;
\ No newline at end of file
+pub extern sqrt: (v:f64) -> f64;
+pub extern pow: (base: f64, exp: f64) -> f64;
+pub extern parse: (text: Utf8) -> Result<f64, Utf8>;
+pub extern random: () -> f64;
@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+use rune::Engine;
+use rune::io::runtime::Backend;
+
+/// a deep call tree, every call evaluates the body of [fib]
+const FIB: &str = "
+fib: (n) {
+  if n < 2 { return n }
+  fib(n - 1) + fib(n - 2)
+}
+fib(22);
+";
+
+/// the same few statements over and over
+const LOOP: &str = "
+total = 0;
+i = 0;
+while i < 300000 {
+  total = total + i % 7;
+  i += 1;
+}
+";
+
+/// the best of [runs] evaluations of [source] on a fresh engine
+fn measure(source: &str, backend: Backend, runs: usize) -> Duration {
+  (0..runs)
+    .map(|_| {
+      let mut engine = Engine::default();
+      engine.set_backend(backend);
+      let start = Instant::now();
+      engine.eval_str(source).expect("the benchmark failed");
+      start.elapsed()
+    })
+    .min()
+    .unwrap()
+}
+
+fn main() {
+  // the tree walker recurses on the host stack
+  let bench = std::thread::Builder::new().stack_size(256 * 1024 * 1024).spawn(|| {
+    for (name, source) in [("fib", FIB), ("loop", LOOP)] {
+      for backend in [Backend::TreeWalker, Backend::Vm] {
+        println!("{:<6} {:<12} {:>10.2?}", name, format!("{:?}", backend), measure(source, backend, 5));
+      }
+    }
+  });
+  bench.unwrap().join().unwrap();
+}
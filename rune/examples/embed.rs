@@ -0,0 +1,66 @@
+use std::io::Write;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use rune::{Engine, RuntimeError};
+use rune::io::runtime::{ExternFn, RuntimeValue};
+use rune::io::shared::SharedValue;
+
+/// collects what a script prints, the way a service would capture it
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Capture {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    self.0.lock().unwrap().write(buf)
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    Ok(())
+  }
+}
+
+fn main() -> Result<(), RuntimeError> {
+  let stdout = Capture::default();
+  let mut engine = Engine::default();
+  engine.set_stdout(stdout.clone());
+  engine.set_global("limit", 3i64);
+
+  engine.eval_str("
+    fn greet(name) { println(\"hello \" + name); }
+    let total = 0;
+    for i in 0..limit { total = total + i; }
+  ")?;
+
+  engine.call_function("greet", vec![RuntimeValue::String("host".to_string())])?;
+  println!("total = {:?}", engine.get_global("total"));
+
+  if let Err(e) = engine.call_function("missing", vec![]) {
+    println!("error: {}", e);
+  }
+
+  // a native that panics fails the script, not the service
+  engine.register_global("explode", ExternFn::new(|_, _| panic!("native bug")));
+  if let Err(e) = engine.eval_str("explode();") {
+    println!("contained: {}", e);
+  }
+  print!("captured: {}", String::from_utf8_lossy(&stdout.0.lock().unwrap()));
+
+  // an engine stays on its thread, another thread gets copies of its values
+  let (sender, receiver) = mpsc::channel();
+  engine.register_global("send", ExternFn::new(move |args, _| {
+    for value in &args {
+      sender.send(SharedValue::new(value)?).map_err(|e| RuntimeError::host(e.to_string()))?;
+    }
+    Ok(RuntimeValue::Never)
+  }));
+  engine.eval_str("send([1, 2, 3], total);")?;
+
+  let worker = thread::spawn(move || -> Result<SharedValue, RuntimeError> {
+    let mut engine = Engine::default();
+    let received: Vec<SharedValue> = receiver.try_iter().collect();
+    engine.set_global("received", received);
+    SharedValue::new(&engine.eval_str("received.len()")?)
+  });
+  println!("worker: {:?}", worker.join().unwrap()?);
+  Ok(())
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::cell::RefCell;
+use crate::io::builtins::{core_natives, prelude};
+use crate::io::error::RuntimeError;
+use crate::io::eval::{call_function, contain_panic, run};
+use crate::io::gc::{self, HeapStats};
+use crate::io::lexer::tokenize;
+use crate::io::native::{IntoNative, IntoValue};
+use crate::io::parser::parse;
+use crate::io::stack::StackBase;
+use crate::io::runtime::{Backend, Context, ExternFn, Output, RefContext, RefRuntime, Runtime, RuntimeValue};
+
+pub type Value = RuntimeValue;
+
+/// an interpreter a host application runs scripts in
+///
+/// a failing script, or a native panicking under it, returns an error and never takes the host down
+///
+/// the evaluator recurses on the calling thread, a script stops with a "stack overflow" error once it takes
+/// [crate::io::runtime::Runtime::max_stack] bytes of its stack, see [Engine::set_max_stack]
+///
+/// an engine and its values stay on the thread that created it, threads exchange [crate::io::shared::SharedValue]s
+pub struct Engine {
+  /// the builtins, modules are evaluated next to the program and only see these
+  root: RefContext,
+  /// the globals of the programs evaluated so far
+  ctx: RefContext,
+}
+
+impl Default for Engine {
+  fn default() -> Self {
+    Engine::new(Runtime::default())
+  }
+}
+
+impl Engine {
+  pub fn new(runtime: Runtime) -> Self {
+    let mut runtime = runtime;
+    core_natives(&mut runtime.natives);
+    let mut context = Context::new(runtime);
+    prelude(&mut context);
+    let root = gc::alloc(RefCell::new(context));
+    let ctx = gc::alloc(RefCell::new(Context::with_parent(root.clone())));
+    Engine { root, ctx }
+  }
+
+  pub fn runtime(&self) -> RefRuntime {
+    self.root.borrow().runtime.clone()
+  }
+
+  /// the context programs run in
+  pub fn context(&self) -> RefContext {
+    self.ctx.clone()
+  }
+
+  /// registers a native `extern` declarations of [module] can bind to, see [crate::io::native::Registry::register]
+  pub fn register<Args>(&mut self, module: &str, name: &str, signature: &str, function: impl IntoNative<Args>) -> Result<(), RuntimeError> {
+    let runtime = self.runtime();
+    let mut runtime = runtime.borrow_mut();
+    runtime.natives.register(module, name, signature, function)
+  }
+
+  /// a builtin every program and module sees, without declaring it
+  pub fn register_global(&mut self, name: &str, function: ExternFn) {
+    self.root.borrow_mut().let_variable(name, RuntimeValue::ExternFn(function));
+  }
+
+  /// module roots, searched in order
+  ///
+  /// `panic` and `catch_panic` are natives of `core`, scripts reach them through modules/core.io,
+  /// without a root holding it `import core { panic }` is an import error
+  pub fn set_module_roots(&mut self, roots: Vec<PathBuf>) {
+    self.runtime().borrow_mut().loader.roots = roots;
+  }
+
+  /// runs the programs evaluated from now on with [backend], functions keep the backend that declared them
+  pub fn set_backend(&mut self, backend: Backend) {
+    self.runtime().borrow_mut().backend = backend;
+  }
+
+  pub fn set_max_call_depth(&mut self, depth: usize) {
+    self.runtime().borrow_mut().max_call_depth = depth;
+  }
+
+  /// bytes of the calling thread's stack scripts may take, leave the rest of it to the host and natives
+  pub fn set_max_stack(&mut self, bytes: usize) {
+    self.runtime().borrow_mut().max_stack = bytes;
+  }
+
+  /// where `println` writes
+  pub fn set_stdout(&mut self, sink: impl Write + Send + 'static) {
+    self.runtime().borrow_mut().stdout = Output::new(sink);
+  }
+
+  /// where `eprintln` writes
+  pub fn set_stderr(&mut self, sink: impl Write + Send + 'static) {
+    self.runtime().borrow_mut().stderr = Output::new(sink);
+  }
+
+  pub fn set_global(&mut self, name: &str, value: impl IntoValue) {
+    self.ctx.borrow_mut().let_variable(name, value.into_value());
+  }
+
+  /// a global of the evaluated programs or a builtin
+  pub fn get_global(&self, name: &str) -> Option<Value> {
+    self.ctx.borrow().get_variable(name)
+  }
+
+  /// evaluates [source] as a program, its declarations stay visible to later calls
+  pub fn eval_str(&mut self, source: &str) -> Result<Value, RuntimeError> {
+    // parsing takes the stack the program may take
+    let _base = StackBase::enter(self.runtime().borrow().max_stack);
+    contain_panic(&self.ctx, || run(parse(tokenize(source))?, self.ctx.clone()))
+  }
+
+  pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Value, RuntimeError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)
+      .map_err(|e| RuntimeError::host(format!("unable to read {}: {}", path.display(), e)))?;
+    self.eval_str(&source)
+  }
+
+  /// calls the function global [name] with [args]
+  pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match self.get_global(name) {
+      Some(f) => contain_panic(&self.ctx, || call_function(f, args, self.ctx.clone())),
+      None => Err(RuntimeError::name(format!("undefined {:?}", name))),
+    }
+  }
+
+  /// frees the unreachable cycles of every engine of this thread, returns how many objects were freed
+  ///
+  /// collections also run on their own as programs allocate, the cycles of a dropped engine go with the next one
+  pub fn collect_garbage(&mut self) -> usize {
+    gc::collect()
+  }
+
+  /// statistics of the heap of this thread, shared by its engines
+  pub fn heap(&self) -> HeapStats {
+    gc::stats()
+  }
+}
@@ -0,0 +1,680 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
+use crate::io::collections::{Key, Table};
+use crate::io::error::{ErrorKind, EvalResult, RuntimeError};
+use crate::io::eval::{call_function, contain_panic};
+use crate::io::expr::{Symbol, Type, TypeDef, Variant};
+use crate::io::gc::{self, HeapStats};
+use crate::io::lexer::Span;
+use crate::io::native::Registry;
+use crate::io::runtime::{Context, ExternFn, Output, RefContext, RefTable, RefVec, Runtime, RuntimeValue, TypeInfo};
+
+/// globals every program starts with
+pub fn prelude(context: &mut Context) {
+  context.let_variable("Vec", RuntimeValue::ExternFn(ExternFn::new(|args, _| Ok(new_vec(args)))));
+  context.let_variable("Map", RuntimeValue::ExternFn(ExternFn::new(|args, _| {
+    expect_args("Map", &args, 0)?;
+    Ok(RuntimeValue::Map(gc::alloc(RefCell::new(Table::default()))))
+  })));
+  context.let_variable("Set", RuntimeValue::ExternFn(ExternFn::new(|args, _| new_set(args))));
+  context.let_variable("pi", RuntimeValue::Float(std::f64::consts::PI));
+  context.let_variable("true", RuntimeValue::Bool(true));
+  context.let_variable("false", RuntimeValue::Bool(false));
+  context.let_variable("println", RuntimeValue::ExternFn(ExternFn::new(|args, ctx| {
+    output(&ctx, |e| &e.stdout).write_line(&format!("[extern::print] > {:?}", args));
+    Ok(RuntimeValue::Never)
+  })));
+  context.let_variable("Result", RuntimeValue::Type(result_type()));
+  context.let_variable("Maybe", RuntimeValue::Type(maybe_type()));
+  context.let_variable("Ok", RuntimeValue::ExternFn(ExternFn::new(|args, _| {
+    expect_args("Ok", &args, 1)?;
+    Ok(ok(args[0].clone()))
+  })));
+  context.let_variable("Error", RuntimeValue::ExternFn(ExternFn::new(|args, _| {
+    expect_args("Error", &args, 1)?;
+    Ok(error(args[0].clone()))
+  })));
+  context.let_variable("Some", RuntimeValue::ExternFn(ExternFn::new(|args, _| {
+    expect_args("Some", &args, 1)?;
+    Ok(some(args[0].clone()))
+  })));
+  context.let_variable("None", none());
+  context.let_variable("eprintln", RuntimeValue::ExternFn(ExternFn::new(|args, ctx| {
+    let line: Vec<String> = args.iter().map(|e| e.to_string()).collect();
+    output(&ctx, |e| &e.stderr).write_line(&line.join(" "));
+    Ok(RuntimeValue::Never)
+  })));
+  // frees the cycles of the program that are unreachable, returns how many objects it freed
+  context.let_variable("gc", RuntimeValue::ExternFn(ExternFn::new(|args, _| {
+    expect_args("gc", &args, 0)?;
+    Ok(RuntimeValue::Int(gc::collect() as i64))
+  })));
+  context.let_variable("heap", RuntimeValue::ExternFn(ExternFn::new(|args, _| {
+    expect_args("heap", &args, 0)?;
+    Ok(heap_stats(gc::stats()))
+  })));
+}
+
+/// [stats] as an object of the language, like `heap().live`
+fn heap_stats(stats: HeapStats) -> RuntimeValue {
+  let fields = [("live", stats.live), ("allocated", stats.allocated), ("freed", stats.freed), ("collections", stats.collections)];
+  RuntimeValue::Object(fields.into_iter().map(|(k, v)| (k.to_string(), RuntimeValue::Int(v as i64))).collect())
+}
+
+/// an enum the language itself relies on, [variants] carry at most one value of the named generic
+fn builtin_enum(name: &str, variants: &[(&str, Option<&str>)]) -> Arc<TypeInfo> {
+  let variants = variants.iter().map(|(variant, generic)| Variant {
+    name: variant.to_string(),
+    fields: generic.iter().map(|e| Type::Named { name: Symbol::new(e.to_string(), Span::default()), args: Vec::new() }).collect(),
+  }).collect();
+  Arc::new(TypeInfo { name: name.to_string(), def: TypeDef::Enum { variants }, module: None })
+}
+
+/// `Result<R, E>`, the outcome of an operation that can fail
+pub fn result_type() -> Arc<TypeInfo> {
+  static RESULT: OnceLock<Arc<TypeInfo>> = OnceLock::new();
+  RESULT.get_or_init(|| builtin_enum("Result", &[("Ok", Some("R")), ("Error", Some("E"))])).clone()
+}
+
+/// `Maybe<T>`, a value that may be absent
+pub fn maybe_type() -> Arc<TypeInfo> {
+  static MAYBE: OnceLock<Arc<TypeInfo>> = OnceLock::new();
+  MAYBE.get_or_init(|| builtin_enum("Maybe", &[("Some", Some("T")), ("None", None)])).clone()
+}
+
+/// the builtin type modules/core declares as [name], its values are made by the interpreter itself
+pub fn core_type(name: &str) -> Option<Arc<TypeInfo>> {
+  match name {
+    "Result" => Some(result_type()),
+    "Maybe" => Some(maybe_type()),
+    _ => None,
+  }
+}
+
+pub fn ok(value: RuntimeValue) -> RuntimeValue {
+  RuntimeValue::Enum { ty: result_type(), variant: "Ok".to_string(), values: vec![value] }
+}
+
+pub fn error(value: RuntimeValue) -> RuntimeValue {
+  RuntimeValue::Enum { ty: result_type(), variant: "Error".to_string(), values: vec![value] }
+}
+
+pub fn some(value: RuntimeValue) -> RuntimeValue {
+  RuntimeValue::Enum { ty: maybe_type(), variant: "Some".to_string(), values: vec![value] }
+}
+
+pub fn none() -> RuntimeValue {
+  RuntimeValue::Enum { ty: maybe_type(), variant: "None".to_string(), values: Vec::new() }
+}
+
+/// `Some(value)`, or `None` when there is no value
+pub fn maybe(value: Option<RuntimeValue>) -> RuntimeValue {
+  value.map_or_else(none, some)
+}
+
+/// the value of an `Ok` or `Some`, [Err] holds an `Error` or `None` unchanged
+/// [None] when [value] is neither a [result_type] nor a [maybe_type]
+pub fn unwrap(value: RuntimeValue) -> Option<Result<RuntimeValue, RuntimeValue>> {
+  match value {
+    RuntimeValue::Enum { ty, variant, mut values } if Arc::ptr_eq(&ty, &result_type()) || Arc::ptr_eq(&ty, &maybe_type()) => {
+      match variant.as_str() {
+        "Ok" | "Some" => Some(Ok(values.remove(0))),
+        _ => Some(Err(RuntimeValue::Enum { ty, variant, values })),
+      }
+    }
+    _ => None,
+  }
+}
+
+/// natives of modules/core, every runtime has them
+pub fn core_natives(natives: &mut Registry) {
+  // what a function that doesn't return gives back, the unit value
+  natives.register_type("core", "Never", TypeDef::Alias(Type::Unit));
+
+  // halts the program, the error records the calls in progress
+  let panic = ExternFn::new(|args, ctx| {
+    expect_args("panic", &args, 1)?;
+    let runtime = ctx.borrow().runtime.clone();
+    let stack = runtime.borrow().stack.clone();
+    Err(RuntimeError::new(ErrorKind::Panic, text(&args[0])).with_trace(&stack))
+  });
+  natives.register_raw("core", "panic", "(Utf8) -> Never", panic).expect("invalid core signature");
+
+  // Ok(f()), or Error(message) when f fails, the program carries on either way
+  let catch_panic = ExternFn::new(|args, ctx| {
+    expect_args("catch_panic", &args, 1)?;
+    match contain_panic(&ctx, || call_function(args[0].clone(), Vec::new(), ctx.clone())) {
+      Ok(e) => Ok(ok(e)),
+      Err(e) if e.kind == ErrorKind::Panic => Ok(error(RuntimeValue::String(e.message))),
+      Err(e) => Ok(error(RuntimeValue::String(format!("{}: {}", e.kind, e.message)))),
+    }
+  });
+  natives.register_raw("core", "catch_panic", "(() -> T) -> Result<T, Utf8>", catch_panic).expect("invalid core signature");
+}
+
+/// the [stream] of the runtime [ctx] belongs to
+fn output(ctx: &RefContext, stream: fn(&Runtime) -> &Output) -> Output {
+  let runtime = ctx.borrow().runtime.clone();
+  let runtime = runtime.borrow();
+  stream(&runtime).clone()
+}
+
+pub fn new_vec(items: Vec<RuntimeValue>) -> RuntimeValue {
+  RuntimeValue::Vec(gc::alloc(RefCell::new(items)))
+}
+
+pub fn new_set(items: Vec<RuntimeValue>) -> EvalResult {
+  let mut table = Table::default();
+  for item in items {
+    table.insert(Key::try_from(&item)?, RuntimeValue::Never);
+  }
+  Ok(RuntimeValue::Set(gc::alloc(RefCell::new(table))))
+}
+
+/// the values a `for in` loop walks over
+pub fn iterate(value: RuntimeValue) -> Result<Box<dyn Iterator<Item=RuntimeValue>>, RuntimeError> {
+  match value {
+    RuntimeValue::String(e) => Ok(Box::new(
+      e.chars().map(|e| RuntimeValue::String(e.to_string())).collect::<Vec<_>>().into_iter()
+    )),
+    RuntimeValue::Range { start, end } => Ok(Box::new((start..end).map(RuntimeValue::Int))),
+    // iterates over a snapshot, the body is free to mutate the vec
+    RuntimeValue::Vec(e) => Ok(Box::new(e.borrow().clone().into_iter())),
+    RuntimeValue::Map(e) | RuntimeValue::Set(e) => {
+      let keys: Vec<RuntimeValue> = e.borrow().keys().cloned().map(RuntimeValue::from).collect();
+      Ok(Box::new(keys.into_iter()))
+    }
+    RuntimeValue::Object(e) => {
+      let mut keys: Vec<String> = e.into_keys().collect();
+      keys.sort();
+      Ok(Box::new(keys.into_iter().map(RuntimeValue::String)))
+    }
+    e => Err(RuntimeError::type_error(format!("{} is not iterable", e))),
+  }
+}
+
+/// structural equality, [None] when the values can't be compared
+pub fn equals(lhs: &RuntimeValue, rhs: &RuntimeValue) -> Option<bool> {
+  equals_in(lhs, rhs, &mut Vec::new())
+}
+
+/// the collections of [lhs] and [rhs] being compared, a pair met again while comparing it is taken as equal
+type Pairs = Vec<(*const (), *const ())>;
+
+/// runs [f] unless the collections [l] and [r] are already being compared, [revisit] is the result then
+fn enter_pair<T>(l: *const (), r: *const (), pairs: &mut Pairs, revisit: T, f: impl FnOnce(&mut Pairs) -> T) -> T {
+  if pairs.contains(&(l, r)) {
+    return revisit;
+  }
+  pairs.push((l, r));
+  let result = f(pairs);
+  pairs.pop();
+  result
+}
+
+fn equals_in(lhs: &RuntimeValue, rhs: &RuntimeValue, pairs: &mut Pairs) -> Option<bool> {
+  match (lhs, rhs) {
+    (RuntimeValue::Never, RuntimeValue::Never) => Some(true),
+    (RuntimeValue::Bool(l), RuntimeValue::Bool(r)) => Some(l == r),
+    (RuntimeValue::Int(l), RuntimeValue::Int(r)) => Some(l == r),
+    (RuntimeValue::Float(l), RuntimeValue::Float(r)) => Some(l == r),
+    (RuntimeValue::String(l), RuntimeValue::String(r)) => Some(l == r),
+    (RuntimeValue::Range { start: ls, end: le }, RuntimeValue::Range { start: rs, end: re }) => Some(ls == rs && le == re),
+    (RuntimeValue::Tuple(l), RuntimeValue::Tuple(r)) => Some(all_equal(l, r, pairs)),
+    (RuntimeValue::Type(l), RuntimeValue::Type(r)) => Some(Arc::ptr_eq(l, r)),
+    (RuntimeValue::Module(l), RuntimeValue::Module(r)) => Some(Rc::ptr_eq(l, r)),
+    (RuntimeValue::Struct { ty: lt, fields: l }, RuntimeValue::Struct { ty: rt, fields: r }) => {
+      Some(Arc::ptr_eq(lt, rt) && l.iter().all(|(k, v)| r.get(k).is_some_and(|e| equals_in(v, e, pairs) == Some(true))))
+    }
+    (RuntimeValue::Enum { ty: lt, variant: lv, values: l }, RuntimeValue::Enum { ty: rt, variant: rv, values: r }) => {
+      Some(Arc::ptr_eq(lt, rt) && lv == rv && all_equal(l, r, pairs))
+    }
+    (RuntimeValue::Vec(l), RuntimeValue::Vec(r)) => {
+      if Rc::ptr_eq(l, r) {
+        return Some(true);
+      }
+      enter_pair(Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const (), pairs, Some(true), |pairs| {
+        let l = l.borrow().clone();
+        let r = r.borrow().clone();
+        Some(all_equal(&l, &r, pairs))
+      })
+    }
+    // order doesn't matter
+    (RuntimeValue::Map(l), RuntimeValue::Map(r)) | (RuntimeValue::Set(l), RuntimeValue::Set(r)) => {
+      if Rc::ptr_eq(l, r) {
+        return Some(true);
+      }
+      enter_pair(Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const (), pairs, Some(true), |pairs| {
+        let l = l.borrow().clone();
+        let r = r.borrow().clone();
+        Some(l.len() == r.len() && l.entries().all(|(k, v)| r.get(k).is_some_and(|e| equals_in(v, e, pairs) == Some(true))))
+      })
+    }
+    _ => None,
+  }
+}
+
+fn all_equal(lhs: &[RuntimeValue], rhs: &[RuntimeValue], pairs: &mut Pairs) -> bool {
+  lhs.len() == rhs.len() && lhs.iter().zip(rhs.iter()).all(|(l, r)| equals_in(l, r, pairs) == Some(true))
+}
+
+/// ordering used by `<` and `sort`, [None] for NaN
+pub fn compare(lhs: &RuntimeValue, rhs: &RuntimeValue) -> Result<Option<Ordering>, RuntimeError> {
+  compare_in(lhs, rhs, &mut Vec::new())
+}
+
+/// like [equals_in], vectors met again while comparing them are taken as equal
+fn compare_in(lhs: &RuntimeValue, rhs: &RuntimeValue, pairs: &mut Pairs) -> Result<Option<Ordering>, RuntimeError> {
+  match (lhs, rhs) {
+    (RuntimeValue::Int(l), RuntimeValue::Int(r)) => Ok(l.partial_cmp(r)),
+    (RuntimeValue::Int(l), RuntimeValue::Float(r)) => Ok((*l as f64).partial_cmp(r)),
+    (RuntimeValue::Float(l), RuntimeValue::Int(r)) => Ok(l.partial_cmp(&(*r as f64))),
+    (RuntimeValue::Float(l), RuntimeValue::Float(r)) => Ok(l.partial_cmp(r)),
+    (RuntimeValue::String(l), RuntimeValue::String(r)) => Ok(l.partial_cmp(r)),
+    (RuntimeValue::Bool(l), RuntimeValue::Bool(r)) => Ok(l.partial_cmp(r)),
+    (RuntimeValue::Vec(l), RuntimeValue::Vec(r)) => {
+      enter_pair(Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const (), pairs, Ok(Some(Ordering::Equal)), |pairs| {
+        let l = l.borrow().clone();
+        let r = r.borrow().clone();
+        compare_all(&l, &r, pairs)
+      })
+    }
+    (RuntimeValue::Tuple(l), RuntimeValue::Tuple(r)) => compare_all(l, r, pairs),
+    (l, r) => Err(RuntimeError::type_error(format!("can't compare {} with {}", l, r))),
+  }
+}
+
+/// lexicographic ordering of two sequences
+fn compare_all(lhs: &[RuntimeValue], rhs: &[RuntimeValue], pairs: &mut Pairs) -> Result<Option<Ordering>, RuntimeError> {
+  for (l, r) in lhs.iter().zip(rhs.iter()) {
+    match compare_in(l, r, pairs)? {
+      Some(Ordering::Equal) => {}
+      e => return Ok(e),
+    }
+  }
+  Ok(lhs.len().partial_cmp(&rhs.len()))
+}
+
+/// the text of a value, strings aren't quoted
+pub fn text(value: &RuntimeValue) -> String {
+  match value {
+    RuntimeValue::String(e) => e.clone(),
+    e => e.to_string(),
+  }
+}
+
+/// `value[index]`
+pub fn index(value: RuntimeValue, index: RuntimeValue) -> EvalResult {
+  match (value, index) {
+    (RuntimeValue::Object(props), RuntimeValue::String(key)) => props
+      .get(&key)
+      .cloned()
+      .ok_or_else(|| RuntimeError::name(format!("undefined property {:?}", key))),
+    (RuntimeValue::String(e), RuntimeValue::Int(i)) => {
+      match usize::try_from(i).ok().and_then(|i| e.chars().nth(i)) {
+        Some(c) => Ok(RuntimeValue::String(c.to_string())),
+        None => Err(out_of_bounds(i, e.chars().count())),
+      }
+    }
+    (RuntimeValue::String(e), RuntimeValue::Range { start, end }) => {
+      let (start, end) = slice_bounds(start, end, e.chars().count())?;
+      Ok(RuntimeValue::String(e.chars().skip(start).take(end - start).collect()))
+    }
+    (RuntimeValue::Vec(e), RuntimeValue::Int(i)) => {
+      let items = e.borrow();
+      match usize::try_from(i).ok().and_then(|i| items.get(i)) {
+        Some(e) => Ok(e.clone()),
+        None => Err(out_of_bounds(i, items.len())),
+      }
+    }
+    (RuntimeValue::Vec(e), RuntimeValue::Range { start, end }) => {
+      let items = e.borrow();
+      let (start, end) = slice_bounds(start, end, items.len())?;
+      Ok(new_vec(items[start..end].to_vec()))
+    }
+    (RuntimeValue::Tuple(e), RuntimeValue::Int(i)) => match usize::try_from(i).ok().and_then(|i| e.get(i)) {
+      Some(e) => Ok(e.clone()),
+      None => Err(out_of_bounds(i, e.len())),
+    },
+    (RuntimeValue::Struct { fields, .. }, RuntimeValue::String(key)) => fields
+      .get(&key)
+      .cloned()
+      .ok_or_else(|| RuntimeError::name(format!("undefined property {:?}", key))),
+    (RuntimeValue::Map(e), key) => {
+      let k = Key::try_from(&key)?;
+      let value = e.borrow().get(&k).cloned();
+      value.ok_or_else(|| RuntimeError::new(ErrorKind::KeyError, format!("key {} not found", k)))
+    }
+    (value, index) => Err(RuntimeError::type_error(format!("{} can't be indexed by {}", value, index))),
+  }
+}
+
+/// `value[index] = item`
+pub fn set_index(value: RuntimeValue, index: RuntimeValue, item: RuntimeValue) -> EvalResult {
+  match (value, index, item) {
+    (RuntimeValue::Vec(e), RuntimeValue::Int(i), item) => {
+      let mut items = e.borrow_mut();
+      let len = items.len();
+      match usize::try_from(i).ok().and_then(|i| items.get_mut(i)) {
+        Some(e) => {
+          *e = item.clone();
+          Ok(item)
+        }
+        None => Err(out_of_bounds(i, len)),
+      }
+    }
+    (RuntimeValue::Map(e), key, item) => {
+      let k = Key::try_from(&key)?;
+      e.borrow_mut().insert(k, item.clone());
+      Ok(item)
+    }
+    (value, index, _) => Err(RuntimeError::type_error(format!("{} can't be assigned at {}", value, index))),
+  }
+}
+
+/// copy of the value type [value] with [index] replaced, the caller writes it back: `point.x = 1`
+pub fn with_index(value: RuntimeValue, index: RuntimeValue, item: RuntimeValue) -> EvalResult {
+  match (value, index) {
+    (RuntimeValue::Tuple(mut items), RuntimeValue::Int(i)) => {
+      let len = items.len();
+      match usize::try_from(i).ok().and_then(|i| items.get_mut(i)) {
+        Some(e) => *e = item,
+        None => return Err(out_of_bounds(i, len)),
+      }
+      Ok(RuntimeValue::Tuple(items))
+    }
+    (RuntimeValue::Struct { ty, mut fields }, RuntimeValue::String(key)) => {
+      if !fields.contains_key(&key) {
+        return Err(RuntimeError::name(format!("{} has no field {:?}", ty.name, key)));
+      }
+      fields.insert(key, item);
+      Ok(RuntimeValue::Struct { ty, fields })
+    }
+    (RuntimeValue::Object(mut props), RuntimeValue::String(key)) => {
+      props.insert(key, item);
+      Ok(RuntimeValue::Object(props))
+    }
+    (value, index) => Err(RuntimeError::type_error(format!("{} can't be assigned at {}", value, index))),
+  }
+}
+
+fn out_of_bounds(index: impl Display, len: usize) -> RuntimeError {
+  RuntimeError::index(format!("index {} out of bounds for length {}", index, len))
+}
+
+/// checks [start]..<[end] against a sequence of [len] items
+fn slice_bounds(start: i64, end: i64, len: usize) -> Result<(usize, usize), RuntimeError> {
+  match (usize::try_from(start), usize::try_from(end)) {
+    (Ok(s), Ok(e)) if s <= e && e <= len => Ok((s, e)),
+    _ => Err(RuntimeError::index(format!("range {}..<{} out of bounds for length {}", start, end, len))),
+  }
+}
+
+fn expect_args(method: &str, args: &[RuntimeValue], count: usize) -> Result<(), RuntimeError> {
+  if args.len() != count {
+    return Err(RuntimeError::arity(method, count, args.len()));
+  }
+  Ok(())
+}
+
+/// built-in methods of values, eg: `(0..10).contains(5)`
+pub fn call_method(receiver: RuntimeValue, method: &str, args: Vec<RuntimeValue>, ctx: RefContext) -> EvalResult {
+  match (receiver, method) {
+    (receiver, "to_string") => expect_args(method, &args, 0).map(|_| RuntimeValue::String(text(&receiver))),
+    (RuntimeValue::Range { start, end }, "contains") => expect_args(method, &args, 1).and_then(|_| match &args[0] {
+      RuntimeValue::Int(e) => Ok(RuntimeValue::Bool(start <= *e && *e < end)),
+      RuntimeValue::Float(e) => Ok(RuntimeValue::Bool(start as f64 <= *e && *e < end as f64)),
+      e => Err(RuntimeError::type_error(format!("range can't contain {}", e))),
+    }),
+    (RuntimeValue::Range { start, end }, "len") => expect_args(method, &args, 0).and_then(|_| match end.checked_sub(start) {
+      Some(len) => Ok(RuntimeValue::Int(len.max(0))),
+      None => Err(RuntimeError::new(ErrorKind::Overflow, format!("length of {}..<{} overflows", start, end))),
+    }),
+    (RuntimeValue::String(e), "len") => expect_args(method, &args, 0).map(|_| RuntimeValue::Int(e.chars().count() as i64)),
+    (RuntimeValue::Vec(e), method) => vec_method(e, method, args, ctx),
+    (RuntimeValue::Map(e), method) => map_method(e, method, args),
+    (RuntimeValue::Set(e), method) => set_method(e, method, args),
+    (receiver @ RuntimeValue::Enum { .. }, method) if unwrap(receiver.clone()).is_some() => option_method(receiver, method, args, ctx),
+    // Things.Paint(color)
+    (RuntimeValue::Type(ty), variant) => match ty.variant_arity(variant) {
+      Some(n) => expect_args(variant, &args, n).map(|_| RuntimeValue::Enum { ty: ty.clone(), variant: variant.to_string(), values: args }),
+      None => Err(RuntimeError::name(format!("{} has no variant {:?}", ty.name, variant))),
+    },
+    (receiver, method) => Err(RuntimeError::name(format!("{} has no method {:?}", receiver, method))),
+  }
+}
+
+fn vec_method(vec: RefVec, method: &str, args: Vec<RuntimeValue>, ctx: RefContext) -> EvalResult {
+  // closures may access the vec, never call them while holding the lock
+  let snapshot = || vec.borrow().clone();
+
+  match method {
+    "len" => {
+      expect_args(method, &args, 0)?;
+      Ok(RuntimeValue::Int(vec.borrow().len() as i64))
+    }
+    "push" => {
+      expect_args(method, &args, 1)?;
+      vec.borrow_mut().push(args[0].clone());
+      Ok(RuntimeValue::Never)
+    }
+    "pop" => {
+      expect_args(method, &args, 0)?;
+      let last = vec.borrow_mut().pop();
+      Ok(maybe(last))
+    }
+    "insert" => {
+      expect_args(method, &args, 2)?;
+      let mut items = vec.borrow_mut();
+      match &args[0] {
+        RuntimeValue::Int(i) if *i >= 0 && *i as usize <= items.len() => {
+          items.insert(*i as usize, args[1].clone());
+          Ok(RuntimeValue::Never)
+        }
+        e => Err(out_of_bounds(e, items.len())),
+      }
+    }
+    "remove" => {
+      expect_args(method, &args, 1)?;
+      let mut items = vec.borrow_mut();
+      match &args[0] {
+        RuntimeValue::Int(i) if *i >= 0 && (*i as usize) < items.len() => Ok(items.remove(*i as usize)),
+        e => Err(out_of_bounds(e, items.len())),
+      }
+    }
+    "contains" => {
+      expect_args(method, &args, 1)?;
+      Ok(RuntimeValue::Bool(snapshot().iter().any(|e| equals(e, &args[0]) == Some(true))))
+    }
+    "reverse" => {
+      expect_args(method, &args, 0)?;
+      vec.borrow_mut().reverse();
+      Ok(RuntimeValue::Never)
+    }
+    "sort" => {
+      expect_args(method, &args, 0)?;
+      let mut items = snapshot();
+      let mut error = None;
+      items.sort_by(|l, r| match compare(l, r) {
+        Ok(e) => e.unwrap_or(Ordering::Equal),
+        Err(e) => {
+          error.get_or_insert(e);
+          Ordering::Equal
+        }
+      });
+      if let Some(e) = error {
+        return Err(e);
+      }
+      *vec.borrow_mut() = items;
+      Ok(RuntimeValue::Never)
+    }
+    "join" => {
+      expect_args(method, &args, 1)?;
+      let separator = text(&args[0]);
+      let items: Vec<String> = snapshot().iter().map(text).collect();
+      Ok(RuntimeValue::String(items.join(separator.as_str())))
+    }
+    "map" => {
+      expect_args(method, &args, 1)?;
+      let mut items = Vec::new();
+      for item in snapshot() {
+        items.push(call_function(args[0].clone(), vec![item], ctx.clone())?);
+      }
+      Ok(new_vec(items))
+    }
+    "filter" => {
+      expect_args(method, &args, 1)?;
+      let mut items = Vec::new();
+      for item in snapshot() {
+        match call_function(args[0].clone(), vec![item.clone()], ctx.clone())? {
+          RuntimeValue::Bool(true) => items.push(item),
+          RuntimeValue::Bool(false) => {}
+          e => return Err(RuntimeError::type_error(format!("filter expects a bool, found {}", e))),
+        }
+      }
+      Ok(new_vec(items))
+    }
+    "fold" => {
+      expect_args(method, &args, 2)?;
+      let mut acc = args[0].clone();
+      for item in snapshot() {
+        acc = call_function(args[1].clone(), vec![acc, item], ctx.clone())?;
+      }
+      Ok(acc)
+    }
+    _ => Err(RuntimeError::name(format!("vec has no method {:?}", method))),
+  }
+}
+
+/// combinators shared by `Result` and `Maybe`, an `Error` or `None` passes through untouched
+fn option_method(receiver: RuntimeValue, method: &str, args: Vec<RuntimeValue>, ctx: RefContext) -> EvalResult {
+  let value = unwrap(receiver.clone()).expect("not a Result or Maybe");
+  let is_result = matches!(&receiver, RuntimeValue::Enum { ty, .. } if Arc::ptr_eq(ty, &result_type()));
+
+  match method {
+    "is_ok" if is_result => {
+      expect_args(method, &args, 0)?;
+      Ok(RuntimeValue::Bool(value.is_ok()))
+    }
+    "is_some" if !is_result => {
+      expect_args(method, &args, 0)?;
+      Ok(RuntimeValue::Bool(value.is_ok()))
+    }
+    "is_error" if is_result => {
+      expect_args(method, &args, 0)?;
+      Ok(RuntimeValue::Bool(value.is_err()))
+    }
+    "is_none" if !is_result => {
+      expect_args(method, &args, 0)?;
+      Ok(RuntimeValue::Bool(value.is_err()))
+    }
+    "map" => {
+      expect_args(method, &args, 1)?;
+      match value {
+        Ok(e) => {
+          let mapped = call_function(args[0].clone(), vec![e], ctx)?;
+          Ok(if is_result { ok(mapped) } else { some(mapped) })
+        }
+        Err(_) => Ok(receiver),
+      }
+    }
+    "and_then" => {
+      expect_args(method, &args, 1)?;
+      match value {
+        Ok(e) => call_function(args[0].clone(), vec![e], ctx),
+        Err(_) => Ok(receiver),
+      }
+    }
+    "unwrap_or" => {
+      expect_args(method, &args, 1)?;
+      Ok(value.unwrap_or_else(|_| args[0].clone()))
+    }
+    // Some(x) is Ok(x), None is Error(args[0])
+    "ok_or" if !is_result => {
+      expect_args(method, &args, 1)?;
+      match value {
+        Ok(e) => Ok(ok(e)),
+        Err(_) => Ok(error(args[0].clone())),
+      }
+    }
+    _ => Err(RuntimeError::name(format!("{} has no method {:?}", receiver, method))),
+  }
+}
+
+fn map_method(map: RefTable, method: &str, args: Vec<RuntimeValue>) -> EvalResult {
+  match method {
+    "len" => {
+      expect_args(method, &args, 0)?;
+      Ok(RuntimeValue::Int(map.borrow().len() as i64))
+    }
+    "get" => {
+      expect_args(method, &args, 1)?;
+      let key = Key::try_from(&args[0])?;
+      let value = map.borrow().get(&key).cloned();
+      Ok(maybe(value))
+    }
+    "insert" => {
+      expect_args(method, &args, 2)?;
+      let key = Key::try_from(&args[0])?;
+      Ok(map.borrow_mut().insert(key, args[1].clone()).unwrap_or(RuntimeValue::Never))
+    }
+    "remove" => {
+      expect_args(method, &args, 1)?;
+      let key = Key::try_from(&args[0])?;
+      Ok(map.borrow_mut().remove(&key).unwrap_or(RuntimeValue::Never))
+    }
+    "contains" => {
+      expect_args(method, &args, 1)?;
+      let key = Key::try_from(&args[0])?;
+      Ok(RuntimeValue::Bool(map.borrow().contains(&key)))
+    }
+    "keys" => {
+      expect_args(method, &args, 0)?;
+      Ok(new_vec(map.borrow().keys().cloned().map(RuntimeValue::from).collect()))
+    }
+    "values" => {
+      expect_args(method, &args, 0)?;
+      Ok(new_vec(map.borrow().values().cloned().collect()))
+    }
+    "entries" => {
+      expect_args(method, &args, 0)?;
+      let entries = map.borrow().entries()
+        .map(|(k, v)| RuntimeValue::Tuple(vec![k.clone().into(), v.clone()]))
+        .collect();
+      Ok(new_vec(entries))
+    }
+    _ => Err(RuntimeError::name(format!("map has no method {:?}", method))),
+  }
+}
+
+fn set_method(set: RefTable, method: &str, args: Vec<RuntimeValue>) -> EvalResult {
+  match method {
+    "len" => {
+      expect_args(method, &args, 0)?;
+      Ok(RuntimeValue::Int(set.borrow().len() as i64))
+    }
+    "insert" => {
+      expect_args(method, &args, 1)?;
+      let key = Key::try_from(&args[0])?;
+      Ok(RuntimeValue::Bool(set.borrow_mut().insert(key, RuntimeValue::Never).is_none()))
+    }
+    "remove" => {
+      expect_args(method, &args, 1)?;
+      let key = Key::try_from(&args[0])?;
+      Ok(RuntimeValue::Bool(set.borrow_mut().remove(&key).is_some()))
+    }
+    "contains" => {
+      expect_args(method, &args, 1)?;
+      let key = Key::try_from(&args[0])?;
+      Ok(RuntimeValue::Bool(set.borrow().contains(&key)))
+    }
+    "values" => {
+      expect_args(method, &args, 0)?;
+      Ok(new_vec(set.borrow().keys().cloned().map(RuntimeValue::from).collect()))
+    }
+    _ => Err(RuntimeError::name(format!("set has no method {:?}", method))),
+  }
+}
@@ -0,0 +1,208 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::rc::Rc;
+use crate::io::error::RuntimeError;
+use crate::io::intern::Name;
+use crate::io::expr::{Capture, ImportItem, Pattern, Symbol, Type, TypeDef};
+use crate::io::lexer::Span;
+use crate::io::runtime::{Cell, RefContext, RuntimeValue};
+
+/// an instruction of the [crate::io::vm], operands index into the tables of its [Chunk]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+  /// pushes [Chunk::constants]
+  Constant(usize),
+  Never,
+  Pop,
+  Dup,
+  /// the two values on top of the stack, eg: a container and its key
+  Dup2,
+  /// pops a value and puts it back under the [n] values below it
+  Sink(usize),
+  /// `Set*` and `Define*` store the top of the stack and leave it there, assignments are expressions
+  GetLocal(usize),
+  SetLocal(usize),
+  /// a local captured by a closure, it outlives the frame
+  GetCell(usize),
+  SetCell(usize),
+  /// a cell of an enclosing function, captured when the closure was created
+  GetUpvalue(usize),
+  SetUpvalue(usize),
+  /// by name, [Chunk::names], in the context of the program or module
+  GetGlobal(usize),
+  /// updates the closest declaration, declares it in the program if there is none
+  SetGlobal(usize),
+  DefineGlobal(usize),
+  Binary(BinaryOp),
+  Eq,
+  NotEq,
+  Range { inclusive: bool },
+  Jump(usize),
+  /// pops a bool
+  JumpIfFalse(usize),
+  /// pops an iterable, the frame keeps its iterator until [Op::IterEnd]
+  IterStart,
+  /// pushes the next item, jumps when there is none
+  IterNext(usize),
+  IterEnd,
+  /// callee, then arguments
+  /// a [tail] call of a closure replaces the frame of the caller, the [Op::Return] after it only runs for other callees
+  Call { argc: usize, tail: bool },
+  /// receiver, then arguments, [method] indexes [Chunk::symbols], [tail] like [Op::Call]
+  Invoke { method: usize, argc: usize, tail: bool },
+  Return,
+  /// unwraps an `Ok` or `Some`, returns anything else from the function
+  Try,
+  Vec(usize),
+  Tuple(usize),
+  /// the values of the keys in [Chunk::shapes]
+  Object(usize),
+  /// type, then the values of [Chunk::structs]
+  Struct(usize),
+  GetProperty(usize),
+  GetIndex,
+  /// value, container, key. pushes the value and jumps when the container is shared,
+  /// otherwise pushes the value and the updated copy for the following instructions to write back
+  SetIndex(usize),
+  /// pops a tuple of [n] items, pushes them last to first
+  Unpack(usize),
+  Closure(usize),
+  /// pops a value, pushes the names of [Chunk::sites] last to first and true when it matches, false otherwise
+  Match(usize),
+  /// like [Op::Match], a mismatch is an error
+  Bind(usize, BindKind),
+  /// pushes the names of the import last to first
+  Import(usize),
+  Extern(usize),
+  Type(usize),
+  /// fails with [Chunk::errors]
+  Raise(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+  Add,
+  Sub,
+  Mul,
+  Div,
+  Rem,
+  Lt,
+  LtEq,
+  Gt,
+  GtEq,
+}
+
+impl BinaryOp {
+  pub fn parse(op: &str) -> Option<BinaryOp> {
+    match op {
+      "+" => Some(BinaryOp::Add),
+      "-" => Some(BinaryOp::Sub),
+      "*" => Some(BinaryOp::Mul),
+      "/" => Some(BinaryOp::Div),
+      "%" => Some(BinaryOp::Rem),
+      "<" => Some(BinaryOp::Lt),
+      "<=" => Some(BinaryOp::LtEq),
+      ">" => Some(BinaryOp::Gt),
+      ">=" => Some(BinaryOp::GtEq),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      BinaryOp::Add => "+",
+      BinaryOp::Sub => "-",
+      BinaryOp::Mul => "*",
+      BinaryOp::Div => "/",
+      BinaryOp::Rem => "%",
+      BinaryOp::Lt => "<",
+      BinaryOp::LtEq => "<=",
+      BinaryOp::Gt => ">",
+      BinaryOp::GtEq => ">=",
+    }
+  }
+}
+
+/// what a pattern that doesn't match was binding, decides the error
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BindKind {
+  Let,
+  Param,
+  Assign,
+}
+
+/// a pattern and the names it binds, in the order [crate::io::pattern::destructure] binds them
+#[derive(Debug, Clone)]
+pub struct Site {
+  pub pattern: Pattern,
+  pub names: Vec<Name>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+  pub path: Vec<Symbol>,
+  pub alias: Option<Symbol>,
+  pub items: Option<Vec<ImportItem>>,
+}
+
+/// the code of a function and the tables its instructions refer to
+#[derive(Debug, Default)]
+pub struct Chunk {
+  pub code: Vec<Op>,
+  /// where the expression each instruction was compiled from starts, errors it raises are located there
+  pub spans: Vec<Option<Span>>,
+  pub constants: Vec<RuntimeValue>,
+  pub names: Vec<Name>,
+  pub symbols: Vec<Symbol>,
+  pub shapes: Vec<Vec<String>>,
+  pub structs: Vec<(Symbol, Vec<Symbol>)>,
+  pub sites: Vec<Site>,
+  pub imports: Vec<Import>,
+  pub externs: Vec<(Symbol, Option<Type>)>,
+  pub types: Vec<(Symbol, TypeDef)>,
+  pub protos: Vec<Rc<Proto>>,
+  pub errors: Vec<RuntimeError>,
+}
+
+impl Chunk {
+  pub fn emit(&mut self, op: Op, span: Option<Span>) -> usize {
+    self.code.push(op);
+    self.spans.push(span);
+    self.code.len() - 1
+  }
+}
+
+/// a compiled function, or the program itself
+#[derive(Debug, Default)]
+pub struct Proto {
+  /// [None] for closures and programs
+  pub name: Option<Symbol>,
+  /// arguments are the first locals
+  pub arity: usize,
+  /// names of the locals, to report the ones read before they're assigned, [None] for the compiler's own
+  pub locals: Vec<Option<Name>>,
+  pub cells: Vec<Name>,
+  pub captures: Vec<Capture>,
+  /// names of the [captures]
+  pub upvalues: Vec<Name>,
+  /// declaring module, [None] for the program itself
+  pub module: Option<String>,
+  pub chunk: Chunk,
+}
+
+/// a function value of the vm, the counterpart of [RuntimeValue::Fn]
+pub struct Closure {
+  pub proto: Rc<Proto>,
+  pub upvalues: Vec<Cell>,
+  /// the program or module the function was declared in
+  pub globals: RefContext,
+}
+
+impl fmt::Debug for Closure {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match &self.proto.name {
+      Some(name) => write!(f, "Closure({})", name.name),
+      None => write!(f, "Closure"),
+    }
+  }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+use crate::io::error::RuntimeError;
+use crate::io::runtime::RuntimeValue;
+
+/// hashable subset of [RuntimeValue], used to key [Table]s
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+  Never,
+  Bool(bool),
+  Int(i64),
+  String(String),
+  Tuple(Vec<Key>),
+}
+
+impl TryFrom<&RuntimeValue> for Key {
+  type Error = RuntimeError;
+
+  fn try_from(value: &RuntimeValue) -> Result<Self, Self::Error> {
+    match value {
+      RuntimeValue::Never => Ok(Key::Never),
+      RuntimeValue::Bool(e) => Ok(Key::Bool(*e)),
+      RuntimeValue::Int(e) => Ok(Key::Int(*e)),
+      RuntimeValue::String(e) => Ok(Key::String(e.clone())),
+      RuntimeValue::Tuple(e) => e.iter().map(Key::try_from).collect::<Result<_, _>>().map(Key::Tuple),
+      e => Err(RuntimeError::type_error(format!("{} is not hashable", e))),
+    }
+  }
+}
+
+impl From<Key> for RuntimeValue {
+  fn from(value: Key) -> Self {
+    match value {
+      Key::Never => RuntimeValue::Never,
+      Key::Bool(e) => RuntimeValue::Bool(e),
+      Key::Int(e) => RuntimeValue::Int(e),
+      Key::String(e) => RuntimeValue::String(e),
+      Key::Tuple(e) => RuntimeValue::Tuple(e.into_iter().map(RuntimeValue::from).collect()),
+    }
+  }
+}
+
+impl fmt::Display for Key {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", RuntimeValue::from(self.clone()))
+  }
+}
+
+/// hash table that iterates in insertion order, backs both `Map` and `Set`
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+  index: HashMap<Key, usize>,
+  entries: Vec<(Key, RuntimeValue)>,
+}
+
+impl Table {
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn get(&self, key: &Key) -> Option<&RuntimeValue> {
+    self.index.get(key).map(|i| &self.entries[*i].1)
+  }
+
+  pub fn contains(&self, key: &Key) -> bool {
+    self.index.contains_key(key)
+  }
+
+  /// returns the replaced value, an existing key keeps its position
+  pub fn insert(&mut self, key: Key, value: RuntimeValue) -> Option<RuntimeValue> {
+    match self.index.get(&key) {
+      Some(i) => Some(std::mem::replace(&mut self.entries[*i].1, value)),
+      None => {
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+      }
+    }
+  }
+
+  pub fn remove(&mut self, key: &Key) -> Option<RuntimeValue> {
+    let i = self.index.remove(key)?;
+    let (_, value) = self.entries.remove(i);
+    for (_, e) in self.index.iter_mut() {
+      if *e > i {
+        *e -= 1;
+      }
+    }
+    Some(value)
+  }
+
+  pub fn keys(&self) -> impl Iterator<Item=&Key> {
+    self.entries.iter().map(|(k, _)| k)
+  }
+
+  pub fn values(&self) -> impl Iterator<Item=&RuntimeValue> {
+    self.entries.iter().map(|(_, v)| v)
+  }
+
+  pub fn entries(&self) -> impl Iterator<Item=&(Key, RuntimeValue)> {
+    self.entries.iter()
+  }
+}
@@ -0,0 +1,932 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use crate::io::bytecode::{BinaryOp, BindKind, Chunk, Import, Op, Proto, Site};
+use crate::io::error::{ErrorKind, RuntimeError};
+use crate::io::eval::{eval_number, import_names};
+use crate::io::intern::Name;
+use crate::io::expr::{Capture, Expr, NodeId, Parameter, Pattern, Property, Symbol};
+use crate::io::lexer::Span;
+use crate::io::resolver::{declarations, for_each_child, pattern_names, visible_names};
+use crate::io::runtime::{RefContext, RuntimeValue};
+use crate::io::stack;
+
+/// where a name lives at runtime
+#[derive(Debug, Clone, Copy)]
+enum Var {
+  Local(usize),
+  Cell(usize),
+  Upvalue(usize),
+  /// in the context of the program, by name
+  Global,
+}
+
+struct Loop {
+  label: Option<Symbol>,
+  /// for-in loops hold an iterator, jumping out of them drops it
+  iterates: bool,
+  breaks: Vec<usize>,
+  continues: Vec<usize>,
+}
+
+/// a function being compiled
+struct Function {
+  proto: Proto,
+  scope: HashMap<Name, Var>,
+  upvalues: HashMap<Name, usize>,
+  /// locals nested functions refer to, they live in cells
+  captured: HashSet<Name>,
+  /// everything the function declares, nested functions resolve these here instead of globally
+  declared: HashSet<Name>,
+  loops: Vec<Loop>,
+  /// innermost enclosing expressions with a position, an error raised without one is located there
+  spans: Vec<Span>,
+  /// function and type declarations to the table entry hoisting created
+  hoisted: HashMap<NodeId, usize>,
+}
+
+impl Function {
+  fn new(name: Option<Symbol>, module: Option<String>, analysis: &Analysis) -> Self {
+    Function {
+      proto: Proto { name, module, ..Proto::default() },
+      scope: HashMap::new(),
+      upvalues: HashMap::new(),
+      captured: analysis.captured.clone(),
+      declared: analysis.declared.iter().chain(&analysis.branch).cloned().collect(),
+      loops: Vec::new(),
+      spans: Vec::new(),
+      hoisted: HashMap::new(),
+    }
+  }
+}
+
+/// lowers a program to the [Proto] the [crate::io::vm] runs
+/// names the program doesn't declare are looked up in the context it runs in
+pub fn compile(program: &Expr, ctx: RefContext) -> Proto {
+  let body = match program {
+    Expr::Program(e) => e.as_slice(),
+    e => std::slice::from_ref(e),
+  };
+  let module = ctx.borrow().module.clone();
+  let mut globals = visible_names(&ctx);
+  let analysis = analyze(&[], body, &globals);
+  globals.extend(analysis.declared.iter().cloned());
+
+  let mut compiler = Compiler { functions: vec![Function::new(None, module, &analysis)], globals, ctx };
+  if let Some(e) = analysis.error {
+    compiler.raise(e);
+  }
+  compiler.program(body);
+  compiler.functions.pop().unwrap().proto
+}
+
+struct Compiler {
+  /// innermost last, the program first
+  functions: Vec<Function>,
+  /// the names of the context and the ones the program declares
+  globals: HashSet<Name>,
+  ctx: RefContext,
+}
+
+impl Compiler {
+  fn function(&mut self) -> &mut Function {
+    self.functions.last_mut().unwrap()
+  }
+
+  fn chunk(&mut self) -> &mut Chunk {
+    &mut self.function().proto.chunk
+  }
+
+  fn emit(&mut self, op: Op) -> usize {
+    let span = self.function().spans.last().copied();
+    self.chunk().emit(op, span)
+  }
+
+  fn emit_at(&mut self, op: Op, span: Span) -> usize {
+    self.chunk().emit(op, Some(span))
+  }
+
+  fn here(&mut self) -> usize {
+    self.chunk().code.len()
+  }
+
+  /// points the jump at [at] to the next instruction
+  fn patch(&mut self, at: usize) {
+    let target = self.here();
+    let code = &mut self.chunk().code;
+    code[at] = match code[at] {
+      Op::Jump(_) => Op::Jump(target),
+      Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+      Op::IterNext(_) => Op::IterNext(target),
+      Op::SetIndex(_) => Op::SetIndex(target),
+      op => unreachable!("{:?} doesn't jump", op),
+    };
+  }
+
+  fn constant(&mut self, value: RuntimeValue) {
+    let constants = &mut self.chunk().constants;
+    constants.push(value);
+    let index = constants.len() - 1;
+    self.emit(Op::Constant(index));
+  }
+
+  fn name(&mut self, name: Name) -> usize {
+    let names = &mut self.chunk().names;
+    match names.iter().position(|e| *e == name) {
+      Some(e) => e,
+      None => {
+        names.push(name);
+        names.len() - 1
+      }
+    }
+  }
+
+  fn raise(&mut self, error: RuntimeError) {
+    let errors = &mut self.chunk().errors;
+    errors.push(error);
+    let index = errors.len() - 1;
+    self.emit(Op::Raise(index));
+  }
+
+  fn spanned(&mut self, span: Span, f: impl FnOnce(&mut Self)) {
+    self.function().spans.push(span);
+    f(self);
+    self.function().spans.pop();
+  }
+
+  /// a slot of the current function no name refers to
+  fn hidden(&mut self) -> usize {
+    let proto = &mut self.function().proto;
+    proto.locals.push(None);
+    proto.locals.len() - 1
+  }
+
+  /// a new variable [name] of the current function, shadowing any previous one
+  fn declare(&mut self, name: Name) -> Var {
+    let var = if self.function().captured.contains(&name) {
+      let proto = &mut self.function().proto;
+      proto.cells.push(name);
+      Var::Cell(proto.cells.len() - 1)
+    } else {
+      let index = self.hidden();
+      self.function().proto.locals[index] = Some(name);
+      Var::Local(index)
+    };
+    self.function().scope.insert(name, var);
+    var
+  }
+
+  fn resolve(&mut self, level: usize, name: Name) -> Var {
+    let function = &self.functions[level];
+    if let Some(var) = function.scope.get(&name) {
+      return *var;
+    }
+    if let Some(index) = function.upvalues.get(&name) {
+      return Var::Upvalue(*index);
+    }
+    if level == 0 || !self.functions[..level].iter().any(|e| e.declared.contains(&name) || e.scope.contains_key(&name)) {
+      return Var::Global;
+    }
+    let capture = match self.resolve(level - 1, name) {
+      Var::Cell(e) => Capture::Cell(e),
+      Var::Upvalue(e) => Capture::Upvalue(e),
+      Var::Global => return Var::Global,
+      Var::Local(_) => unreachable!("{:?} is captured without a cell", name),
+    };
+    let function = &mut self.functions[level];
+    function.proto.captures.push(capture);
+    function.proto.upvalues.push(name);
+    let index = function.proto.captures.len() - 1;
+    function.upvalues.insert(name, index);
+    Var::Upvalue(index)
+  }
+
+  fn load(&mut self, name: &Symbol) {
+    match self.resolve(self.functions.len() - 1, name.name) {
+      Var::Local(e) => self.emit_at(Op::GetLocal(e), name.span),
+      Var::Cell(e) => self.emit_at(Op::GetCell(e), name.span),
+      Var::Upvalue(e) => self.emit_at(Op::GetUpvalue(e), name.span),
+      Var::Global => {
+        let name_index = self.name(name.name);
+        self.emit_at(Op::GetGlobal(name_index), name.span)
+      }
+    };
+  }
+
+  /// stores the top of the stack into [name], a definition declares it in the program if it isn't a local
+  fn store(&mut self, name: Name, define: bool) {
+    let level = self.functions.len() - 1;
+    let var = match self.resolve(level, name) {
+      // parameters are declared as they are bound
+      Var::Global if define && level > 0 => self.declare(name),
+      e => e,
+    };
+    match var {
+      Var::Local(e) => self.emit(Op::SetLocal(e)),
+      Var::Cell(e) => self.emit(Op::SetCell(e)),
+      Var::Upvalue(e) => self.emit(Op::SetUpvalue(e)),
+      Var::Global if define => {
+        let name = self.name(name);
+        self.emit(Op::DefineGlobal(name))
+      }
+      Var::Global => {
+        let name = self.name(name);
+        self.emit(Op::SetGlobal(name))
+      }
+    };
+  }
+
+  /// `None` in a pattern matches the unit variant it names instead of binding it
+  fn binds(&self, name: Name) -> bool {
+    if !name.starts_with(char::is_uppercase) {
+      return true;
+    }
+    let value = self.ctx.borrow().get_variable(name);
+    !matches!(value, Some(RuntimeValue::Enum { variant, values, .. }) if variant == name && values.is_empty())
+  }
+
+  /// the names [pattern] binds, in the order they are bound
+  fn bound_names(&self, pattern: &Pattern) -> Vec<Name> {
+    match pattern {
+      Pattern::Identifier(e) if self.binds(e.name) => vec![e.name],
+      Pattern::Tuple(patterns) | Pattern::Variant { fields: patterns, .. } => patterns.iter().flat_map(|e| self.bound_names(e)).collect(),
+      Pattern::Struct { fields, .. } => fields.iter().flat_map(|(_, e)| self.bound_names(e)).collect(),
+      _ => Vec::new(),
+    }
+  }
+
+  fn site(&mut self, pattern: &Pattern) -> (usize, Vec<Name>) {
+    let names = self.bound_names(pattern);
+    let sites = &mut self.chunk().sites;
+    sites.push(Site { pattern: pattern.clone(), names: names.clone() });
+    (sites.len() - 1, names)
+  }
+
+  /// binds the value on top of the stack to [pattern], leaving it there
+  fn bind(&mut self, pattern: &Pattern, kind: BindKind, define: bool) {
+    match pattern {
+      Pattern::Wildcard => {}
+      Pattern::Identifier(e) if self.binds(e.name) => self.store(e.name, define),
+      pattern => {
+        let (site, names) = self.site(pattern);
+        self.emit(Op::Dup);
+        self.emit(Op::Bind(site, kind));
+        for name in names {
+          self.store(name, define);
+          self.emit(Op::Pop);
+        }
+      }
+    }
+  }
+
+  fn program(&mut self, body: &[Expr]) {
+    // externs are bound before anything runs, an unresolved one fails the whole program
+    for expr in body {
+      if let Expr::ExternDecl { .. } = expr {
+        self.expr(expr);
+        self.emit(Op::Pop);
+      }
+    }
+    self.block(body);
+    self.emit(Op::Return);
+  }
+
+  /// declares every function and type of a block up front, so they can reference each other regardless of order
+  fn hoist(&mut self, body: &[Expr]) {
+    for expr in body {
+      match expr {
+        Expr::FnDecl { id, identifier, params, body, .. } => {
+          let proto = self.compile_function(Some(identifier), params, body);
+          self.function().hoisted.insert(*id, proto);
+          self.emit(Op::Closure(proto));
+          self.store(identifier.name, true);
+          self.emit(Op::Pop);
+        }
+        Expr::TypeDecl { id, identifier, def, .. } => {
+          let types = &mut self.chunk().types;
+          types.push((identifier.clone(), def.clone()));
+          let ty = types.len() - 1;
+          self.function().hoisted.insert(*id, ty);
+          self.emit(Op::Type(ty));
+          self.store(identifier.name, true);
+          self.emit(Op::Pop);
+        }
+        _ => {}
+      }
+    }
+  }
+
+  fn block(&mut self, body: &[Expr]) {
+    self.hoist(body);
+    self.statements(body);
+  }
+
+  /// pushes the value of the last statement
+  fn statements(&mut self, body: &[Expr]) {
+    if body.is_empty() {
+      self.emit(Op::Never);
+    }
+    for (i, expr) in body.iter().enumerate() {
+      if i > 0 {
+        self.emit(Op::Pop);
+      }
+      self.expr(expr);
+    }
+  }
+
+  /// compiles a nested function, the index of its [Proto] in the current chunk
+  fn compile_function(&mut self, name: Option<&Symbol>, params: &[Parameter], body: &Expr) -> usize {
+    let mut visible = self.globals.clone();
+    for function in &self.functions {
+      visible.extend(function.declared.iter().cloned());
+      visible.extend(function.scope.keys().cloned());
+    }
+    let analysis = analyze(params, std::slice::from_ref(body), &visible);
+    let module = self.function().proto.module.clone();
+    let mut function = Function::new(name.cloned(), module, &analysis);
+    function.declared.extend(params.iter().flat_map(|e| pattern_names(&e.pattern)));
+    function.proto.arity = params.len();
+    function.proto.locals = params.iter().map(|e| match &e.pattern {
+      Pattern::Identifier(e) => Some(e.name),
+      _ => None,
+    }).collect();
+    self.functions.push(function);
+
+    // arguments arrive in the first locals, anything but a plain name is bound from there
+    for (i, param) in params.iter().enumerate() {
+      match &param.pattern {
+        Pattern::Identifier(e) if self.binds(e.name) && !self.function().captured.contains(&e.name) => {
+          self.function().scope.insert(e.name, Var::Local(i));
+        }
+        pattern => {
+          // parameters shadow the names of enclosing functions
+          for name in self.bound_names(pattern) {
+            self.declare(name);
+          }
+          self.emit(Op::GetLocal(i));
+          self.bind(pattern, BindKind::Param, true);
+          self.emit(Op::Pop);
+        }
+      }
+    }
+    for name in &analysis.declared {
+      if !self.function().scope.contains_key(name) {
+        self.declare(*name);
+      }
+    }
+
+    if let Some(e) = analysis.error {
+      self.raise(e);
+    }
+    self.expr(body);
+    self.emit(Op::Return);
+
+    let proto = self.functions.pop().unwrap().proto;
+    let protos = &mut self.chunk().protos;
+    protos.push(Rc::new(proto));
+    protos.len() - 1
+  }
+
+  fn expr(&mut self, expr: &Expr) {
+    if let Err(e) = stack::check_nesting() {
+      self.raise(e);
+      self.emit(Op::Never);
+      return;
+    }
+    match expr {
+      Expr::Program(body) => self.block(body),
+      Expr::VarDecl { pattern, value, .. } => {
+        self.expr(value);
+        self.bind(pattern, BindKind::Let, true);
+      }
+      Expr::FnDecl { id, identifier, params, body, .. } => {
+        let proto = match self.function().hoisted.get(id) {
+          Some(e) => *e,
+          None => self.compile_function(Some(identifier), params, body),
+        };
+        self.emit(Op::Closure(proto));
+        self.store(identifier.name, true);
+      }
+      Expr::ExternDecl { identifier, ty, .. } => {
+        let externs = &mut self.chunk().externs;
+        externs.push((identifier.clone(), ty.clone()));
+        let index = externs.len() - 1;
+        self.emit(Op::Extern(index));
+        self.store(identifier.name, true);
+      }
+      Expr::TypeDecl { id, identifier, def, .. } => {
+        let ty = match self.function().hoisted.get(id) {
+          Some(e) => *e,
+          None => {
+            let types = &mut self.chunk().types;
+            types.push((identifier.clone(), def.clone()));
+            types.len() - 1
+          }
+        };
+        self.emit(Op::Type(ty));
+        self.store(identifier.name, true);
+      }
+      Expr::Module { .. } => {
+        self.emit(Op::Never);
+      }
+      Expr::Import { path, alias, items } => {
+        let imports = &mut self.chunk().imports;
+        imports.push(Import { path: path.clone(), alias: alias.clone(), items: items.clone() });
+        let index = imports.len() - 1;
+        self.emit(Op::Import(index));
+        for name in import_names(path, alias, items) {
+          self.store(name.name, true);
+          self.emit(Op::Pop);
+        }
+        self.emit(Op::Never);
+      }
+      Expr::Closure { params, body, .. } => {
+        let proto = self.compile_function(None, params, body);
+        self.emit(Op::Closure(proto));
+      }
+      Expr::BinaryExpr { left, right, op, span } => self.spanned(*span, |c| {
+        c.expr(left);
+        c.expr(right);
+        c.binary(op);
+      }),
+      Expr::AssignExpr { target, value } => {
+        // the whole right hand side is evaluated first, so `a, b = b, a` swaps
+        self.expr(value);
+        self.assign(target);
+      }
+      Expr::CompoundAssign { target, value, op, span } => {
+        let result = self.hidden();
+        self.update(target, &mut |c| {
+          c.expr(value);
+          c.spanned(*span, |c| c.binary(op));
+          c.emit(Op::SetLocal(result));
+        });
+        self.emit(Op::Pop);
+        self.emit(Op::GetLocal(result));
+      }
+      Expr::Range { start, end, inclusive } => {
+        self.expr(start);
+        self.expr(end);
+        self.emit(Op::Range { inclusive: *inclusive });
+      }
+      Expr::Eq { left, right } => {
+        self.expr(left);
+        self.expr(right);
+        self.emit(Op::Eq);
+      }
+      Expr::NotEq { left, right } => {
+        self.expr(left);
+        self.expr(right);
+        self.emit(Op::NotEq);
+      }
+      Expr::MemberExpr { object, property, computed, span } => self.spanned(*span, |c| {
+        c.expr(object);
+        match (computed, property.as_ref()) {
+          (false, Expr::Identifier(e)) => {
+            let name = c.name(e.name);
+            c.emit(Op::GetProperty(name));
+          }
+          _ => {
+            c.key(property, *computed);
+            c.emit(Op::GetIndex);
+          }
+        }
+      }),
+      Expr::CallExpr { caller, args, span, tail } => {
+        if let Expr::MemberExpr { object, property, computed: false, .. } = caller.as_ref() {
+          if let Expr::Identifier(method) = property.as_ref() {
+            self.expr(object);
+            self.invoke(method, args, *span, *tail);
+            return;
+          }
+        }
+        self.expr(caller);
+        for arg in args {
+          self.expr(arg);
+        }
+        self.emit_at(Op::Call { argc: args.len(), tail: *tail }, *span);
+      }
+      Expr::Cascade { receiver, method, args } => {
+        self.expr(receiver);
+        self.emit(Op::Dup);
+        self.invoke(method, args, method.span, false);
+        self.emit(Op::Pop);
+      }
+      Expr::IfExpr { when, then, other } => self.if_expr(when, then, other.as_deref()),
+      Expr::Try { expr, span } => {
+        self.expr(expr);
+        self.emit_at(Op::Try, *span);
+      }
+      Expr::Body { body } => self.block(body),
+      Expr::Loop { label, body } => {
+        let result = self.loop_result();
+        self.hoist(body);
+        let start = self.here();
+        self.loop_body(label, false, body, result);
+        self.emit(Op::Jump(start));
+        self.end_loop(start, result);
+      }
+      Expr::While { label, condition, body } => {
+        self.hoist(body);
+        let result = self.loop_result();
+        let start = self.here();
+        self.expr(condition);
+        let exit = self.emit(Op::JumpIfFalse(0));
+        self.loop_body(label, false, body, result);
+        self.emit(Op::Jump(start));
+        self.patch(exit);
+        self.end_loop(start, result);
+      }
+      Expr::For { label, init, condition, step, body } => {
+        if let Some(init) = init {
+          self.expr(init);
+          self.emit(Op::Pop);
+        }
+        self.hoist(body);
+        let result = self.loop_result();
+        let start = self.here();
+        let exit = condition.as_ref().map(|condition| {
+          self.expr(condition);
+          self.emit(Op::JumpIfFalse(0))
+        });
+        self.loop_body(label, false, body, result);
+        let next = self.here();
+        if let Some(step) = step {
+          self.expr(step);
+          self.emit(Op::Pop);
+        }
+        self.emit(Op::Jump(start));
+        if let Some(exit) = exit {
+          self.patch(exit);
+        }
+        self.end_loop(next, result);
+      }
+      Expr::ForIn { label, item, iterable, body } => {
+        self.expr(iterable);
+        self.emit(Op::IterStart);
+        self.hoist(body);
+        let result = self.loop_result();
+        let start = self.here();
+        let exit = self.emit(Op::IterNext(0));
+        self.store(item.name, true);
+        self.emit(Op::Pop);
+        self.loop_body(label, true, body, result);
+        self.emit(Op::Jump(start));
+        self.patch(exit);
+        self.end_loop(start, result);
+      }
+      Expr::Break { label, .. } => self.jump_out(label, true),
+      Expr::Continue { label, .. } => self.jump_out(label, false),
+      Expr::Return { expr, .. } => {
+        self.expr(expr);
+        self.emit(Op::Return);
+      }
+      Expr::Error(e) => {
+        self.raise(RuntimeError::new(ErrorKind::SyntaxError, e.clone()));
+        self.emit(Op::Never);
+      }
+      Expr::Never => {
+        self.emit(Op::Never);
+      }
+      Expr::Identifier(e) => self.load(e),
+      Expr::String(e) => self.constant(RuntimeValue::String(e.clone())),
+      Expr::Number(e) => self.number(e),
+      Expr::Vec(items) => {
+        for item in items {
+          self.expr(item);
+        }
+        self.emit(Op::Vec(items.len()));
+      }
+      Expr::Tuple(items) => {
+        for item in items {
+          self.expr(item);
+        }
+        self.emit(Op::Tuple(items.len()));
+      }
+      Expr::Object { props } => {
+        self.properties(props);
+        let shapes = &mut self.chunk().shapes;
+        shapes.push(props.iter().map(|e| e.identifier.name.to_string()).collect());
+        let shape = shapes.len() - 1;
+        self.emit(Op::Object(shape));
+      }
+      Expr::Struct { identifier, props } => {
+        self.load(identifier);
+        self.properties(props);
+        let structs = &mut self.chunk().structs;
+        structs.push((identifier.clone(), props.iter().map(|e| e.identifier.clone()).collect()));
+        let index = structs.len() - 1;
+        self.emit(Op::Struct(index));
+      }
+      Expr::Property(_) | Expr::Let { .. } => unreachable!("a property is only part of an object, a `let` only the condition of an `if`"),
+    }
+  }
+
+  fn number(&mut self, value: &str) {
+    match eval_number(value) {
+      Ok(e) => self.constant(e),
+      Err(e) => {
+        self.raise(e);
+        self.emit(Op::Never);
+      }
+    }
+  }
+
+  /// the values of [props], `{x}` is `{x: x}`
+  fn properties(&mut self, props: &[Property]) {
+    for prop in props {
+      match &prop.value {
+        Some(e) => self.expr(e),
+        None => self.load(&prop.identifier),
+      }
+    }
+  }
+
+  /// receiver.method(args), the receiver is on the stack
+  fn invoke(&mut self, method: &Symbol, args: &[Expr], span: Span, tail: bool) {
+    for arg in args {
+      self.expr(arg);
+    }
+    let symbols = &mut self.chunk().symbols;
+    symbols.push(method.clone());
+    let index = symbols.len() - 1;
+    self.emit_at(Op::Invoke { method: index, argc: args.len(), tail }, span);
+  }
+
+  /// replaces the value at [target] with what [f] makes of the one on top of the stack, leaving it there
+  /// each container and key is evaluated once, the updated copy of a value type is written back into its own container
+  fn update(&mut self, target: &Expr, f: &mut dyn FnMut(&mut Self)) {
+    match target {
+      Expr::MemberExpr { object, property, computed, span } => self.update(object, &mut |c| c.spanned(*span, |c| {
+        // container, container, key, then the new value under the last two for [Op::SetIndex]
+        c.emit(Op::Dup);
+        c.key(property, *computed);
+        c.emit(Op::Dup2);
+        c.emit(Op::GetIndex);
+        f(c);
+        c.emit(Op::Sink(2));
+        let shared = c.emit(Op::SetIndex(0));
+        // the updated copy replaces the container
+        c.emit(Op::Sink(2));
+        c.emit(Op::Pop);
+        c.emit(Op::Pop);
+        let end = c.emit(Op::Jump(0));
+        c.patch(shared);
+        c.emit(Op::Pop);
+        c.patch(end);
+      })),
+      Expr::Identifier(_) => {
+        self.expr(target);
+        f(self);
+        self.assign(target);
+      }
+      // a temporary, eg: `make()[0] += 1`, there is nothing to write it back to
+      target => {
+        self.expr(target);
+        f(self);
+      }
+    }
+  }
+
+  /// pops two operands, pushes `lhs op rhs`
+  fn binary(&mut self, op: &str) {
+    match BinaryOp::parse(op) {
+      Some(op) => self.emit(Op::Binary(op)),
+      None => {
+        self.emit(Op::Pop);
+        self.emit(Op::Pop);
+        self.raise(RuntimeError::type_error(format!("unknown operator {:?}", op)));
+        self.emit(Op::Never)
+      }
+    };
+  }
+
+  /// the index a member expression refers to, `a.b` is `a["b"]` and `t.0` is `t[0]`
+  fn key(&mut self, property: &Expr, computed: bool) {
+    match (computed, property) {
+      (true, e) => self.expr(e),
+      (false, Expr::Identifier(e)) => self.constant(RuntimeValue::String(e.name.to_string())),
+      (false, Expr::Number(e)) => self.number(e),
+      (false, _) => unreachable!("the parser only reads names and numbers after a `.`"),
+    }
+  }
+
+  fn if_expr(&mut self, when: &Expr, then: &Expr, other: Option<&Expr>) {
+    let exit = match when {
+      // the names bound by the pattern are only visible in [then]
+      Expr::Let { pattern, value } => {
+        self.expr(value);
+        let (site, names) = self.site(pattern);
+        self.emit(Op::Match(site));
+        let exit = self.emit(Op::JumpIfFalse(0));
+        let scope = self.function().scope.clone();
+        for name in names {
+          self.declare(name);
+          self.store(name, true);
+          self.emit(Op::Pop);
+        }
+        self.expr(then);
+        self.function().scope = scope;
+        exit
+      }
+      when => {
+        self.expr(when);
+        let exit = self.emit(Op::JumpIfFalse(0));
+        self.expr(then);
+        exit
+      }
+    };
+    let end = self.emit(Op::Jump(0));
+    self.patch(exit);
+    match other {
+      Some(e) => self.expr(e),
+      None => {
+        self.emit(Op::Never);
+      }
+    }
+    self.patch(end);
+  }
+
+  /// the hidden local holding the value of the last completed iteration
+  fn loop_result(&mut self) -> usize {
+    let result = self.hidden();
+    self.emit(Op::Never);
+    self.emit(Op::SetLocal(result));
+    self.emit(Op::Pop);
+    result
+  }
+
+  fn loop_body(&mut self, label: &Option<Symbol>, iterates: bool, body: &[Expr], result: usize) {
+    self.function().loops.push(Loop { label: label.clone(), iterates, breaks: Vec::new(), continues: Vec::new() });
+    self.statements(body);
+    self.emit(Op::SetLocal(result));
+    self.emit(Op::Pop);
+  }
+
+  /// patches the jumps of the innermost loop, `continue` goes to [next]
+  fn end_loop(&mut self, next: usize, result: usize) {
+    let exit = self.function().loops.pop().unwrap();
+    for at in exit.continues {
+      self.chunk().code[at] = Op::Jump(next);
+    }
+    for at in exit.breaks {
+      self.patch(at);
+    }
+    if exit.iterates {
+      self.emit(Op::IterEnd);
+    }
+    self.emit(Op::GetLocal(result));
+  }
+
+  /// `break` and `continue`, out of a function when no enclosing loop has the label
+  fn jump_out(&mut self, label: &Option<Symbol>, exit: bool) {
+    let loops = &self.function().loops;
+    let target = match label {
+      None => loops.len().checked_sub(1),
+      Some(_) => loops.iter().rposition(|e| e.label == *label),
+    };
+    let Some(target) = target else {
+      unreachable!("the resolver rejects a jump to {:?} outside of its loops", label);
+    };
+    let inner = loops[target + 1..].iter().filter(|e| e.iterates).count();
+    for _ in 0..inner {
+      self.emit(Op::IterEnd);
+    }
+    let at = self.emit(Op::Jump(0));
+    let target = &mut self.function().loops[target];
+    match exit {
+      true => target.breaks.push(at),
+      false => target.continues.push(at),
+    }
+  }
+
+  /// stores the value on top of the stack into the place described by [target], leaving it there
+  fn assign(&mut self, target: &Expr) {
+    match target {
+      Expr::Identifier(e) if e.name == Name::WILDCARD => {}
+      Expr::Identifier(e) => self.store(e.name, false),
+      Expr::MemberExpr { object, property, computed, span } => self.spanned(*span, |c| {
+        c.expr(object);
+        c.key(property, *computed);
+        let shared = c.emit(Op::SetIndex(0));
+        // a value type was copied, write the updated copy back: `a.b.c = 1`
+        c.assign(object);
+        c.emit(Op::Pop);
+        c.patch(shared);
+      }),
+      Expr::Tuple(targets) => {
+        self.emit(Op::Dup);
+        self.emit(Op::Unpack(targets.len()));
+        for target in targets {
+          self.assign(target);
+          self.emit(Op::Pop);
+        }
+      }
+      target => match target_pattern(target) {
+        Some(pattern) => self.bind(&pattern, BindKind::Assign, false),
+        None => unreachable!("the parser only assigns to names, members and the patterns they make up"),
+      },
+    }
+  }
+}
+
+/// `{x, y: b} = point`, `Paint(color) = thing`, as the pattern they destructure with
+fn target_pattern(target: &Expr) -> Option<Pattern> {
+  let fields = |props: &[Property]| -> Option<Vec<(Symbol, Pattern)>> {
+    props.iter().map(|e| match &e.value {
+      None => Some((e.identifier.clone(), Pattern::Identifier(e.identifier.clone()))),
+      Some(value) => target_pattern(value).map(|pattern| (e.identifier.clone(), pattern)),
+    }).collect()
+  };
+  match target {
+    Expr::Identifier(e) if e.name == Name::WILDCARD => Some(Pattern::Wildcard),
+    Expr::Identifier(e) => Some(Pattern::Identifier(e.clone())),
+    Expr::Tuple(items) => items.iter().map(target_pattern).collect::<Option<_>>().map(Pattern::Tuple),
+    Expr::Object { props } => Some(Pattern::Struct { ty: None, fields: fields(props)? }),
+    Expr::Struct { identifier, props } => Some(Pattern::Struct { ty: Some(identifier.clone()), fields: fields(props)? }),
+    Expr::CallExpr { caller, args, .. } => {
+      let (ty, variant) = match caller.as_ref() {
+        Expr::Identifier(variant) => (None, variant.clone()),
+        Expr::MemberExpr { object, property, computed: false, .. } => match (object.as_ref(), property.as_ref()) {
+          (Expr::Identifier(ty), Expr::Identifier(variant)) => (Some(ty.clone()), variant.clone()),
+          _ => return None,
+        },
+        _ => return None,
+      };
+      let fields = args.iter().map(target_pattern).collect::<Option<_>>()?;
+      Some(Pattern::Variant { ty, variant, fields })
+    }
+    _ => None,
+  }
+}
+
+/// what a function body declares and which of those its nested functions refer to
+#[derive(Default)]
+struct Analysis {
+  /// `let`, declarations, imports, loop items and assignments to names not visible outside, in order
+  declared: Vec<Name>,
+  /// bound by an `if` pattern, for its branch only
+  branch: HashSet<Name>,
+  captured: HashSet<Name>,
+  /// referred to, but declared outside
+  free: HashSet<Name>,
+  /// the body is nested too deeply to be analyzed, running it raises this instead
+  error: Option<RuntimeError>,
+}
+
+fn analyze(params: &[Parameter], body: &[Expr], outer: &HashSet<Name>) -> Analysis {
+  let params: HashSet<Name> = params.iter().flat_map(|e| pattern_names(&e.pattern)).collect();
+  let mut analysis = Analysis::default();
+  for expr in body {
+    if let Err(e) = declarations(expr, &params, outer, &mut analysis.declared, &mut analysis.branch) {
+      analysis.error.get_or_insert(e);
+    }
+  }
+
+  let mut scope = outer.clone();
+  scope.extend(params.iter().cloned());
+  scope.extend(analysis.declared.iter().cloned());
+  scope.extend(analysis.branch.iter().cloned());
+  let mut refs = HashSet::new();
+  let mut nested = HashSet::new();
+  for expr in body {
+    if let Err(e) = references(expr, &scope, &mut refs, &mut nested) {
+      analysis.error.get_or_insert(e);
+    }
+  }
+
+  let own = |name: &Name| params.contains(name) || analysis.declared.contains(name) || analysis.branch.contains(name);
+  analysis.captured = nested.iter().filter(|e| own(e)).cloned().collect();
+  analysis.free = refs.union(&nested)
+    .filter(|e| !params.contains(*e) && !analysis.declared.contains(*e))
+    .cloned()
+    .collect();
+  analysis
+}
+
+fn references(expr: &Expr, scope: &HashSet<Name>, refs: &mut HashSet<Name>, nested: &mut HashSet<Name>) -> Result<(), RuntimeError> {
+  stack::check_nesting()?;
+  match expr {
+    Expr::Identifier(e) => {
+      refs.insert(e.name);
+    }
+    Expr::Object { props } | Expr::Struct { props, .. } => {
+      if let Expr::Struct { identifier, .. } = expr {
+        refs.insert(identifier.name);
+      }
+      refs.extend(props.iter().filter(|e| e.value.is_none()).map(|e| e.identifier.name));
+    }
+    Expr::FnDecl { params, body, .. } | Expr::Closure { params, body, .. } => {
+      let analysis = analyze(params, std::slice::from_ref(body.as_ref()), scope);
+      if let Some(e) = analysis.error {
+        return Err(e);
+      }
+      nested.extend(analysis.free);
+    }
+    _ => {}
+  }
+  let mut result = Ok(());
+  for_each_child(expr, &mut |e| if result.is_ok() {
+    result = references(e, scope, refs, nested);
+  });
+  result
+}
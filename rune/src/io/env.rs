@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Formatter;
+use std::rc::Rc;
+use std::sync::Arc;
+use crate::io::expr::{Capture, Expr, Layout, Parameter, Slot, Symbol};
+use crate::io::gc;
+use crate::io::runtime::{RefContext, RuntimeValue};
+
+/// a variable captured by a closure of the tree-walker, [None] until assigned
+pub type Upvalue = Rc<RefCell<Option<RuntimeValue>>>;
+
+/// a function value of the tree-walker, the counterpart of [crate::io::bytecode::Closure]
+pub struct Function {
+  /// [None] for closures
+  pub identifier: Option<Symbol>,
+  pub params: Arc<Vec<Parameter>>,
+  pub body: Arc<Expr>,
+  pub layout: Arc<Layout>,
+  /// the cells of the variables it captured, in the order of [Layout::captures]
+  pub upvalues: Vec<Upvalue>,
+  /// the program or module the function was declared in
+  pub globals: RefContext,
+}
+
+impl fmt::Debug for Function {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match &self.identifier {
+      Some(identifier) => write!(f, "Function({})", identifier.name),
+      None => write!(f, "Function"),
+    }
+  }
+}
+
+/// a slot of a frame, the ones closures capture are shared with them through a cell
+#[derive(Debug, Clone)]
+enum Local {
+  Value(Option<RuntimeValue>),
+  Cell(Upvalue),
+}
+
+/// the variables the tree-walker evaluates with
+/// the slots of every call in progress share one stack, globals are looked up by name in [Env::globals]
+pub struct Env {
+  pub globals: RefContext,
+  slots: Vec<Local>,
+  /// where the slots of the innermost frame start
+  base: usize,
+  /// the function of the innermost frame, [None] for the program
+  function: Option<Rc<Function>>,
+}
+
+impl Env {
+  pub fn new(globals: RefContext) -> Self {
+    Env { globals, slots: Vec::new(), base: 0, function: None }
+  }
+
+  /// the frame of a program, laid out as [layout]
+  pub fn program(globals: RefContext, layout: &Layout) -> Self {
+    let mut env = Env::new(globals);
+    env.push(layout);
+    env
+  }
+
+  fn push(&mut self, layout: &Layout) {
+    self.base = self.slots.len();
+    self.slots.resize(self.base + layout.slots, Local::Value(None));
+    for index in &layout.cells {
+      self.slots[self.base + index] = Local::Cell(gc::alloc(RefCell::new(None)));
+    }
+  }
+
+  /// runs [f] in a new frame of [function], the frame of the caller is back once it returns
+  pub fn call<T>(&mut self, function: Rc<Function>, f: impl FnOnce(&mut Env) -> T) -> T {
+    let base = self.base;
+    let globals = std::mem::replace(&mut self.globals, function.globals.clone());
+    self.push(&function.layout);
+    let caller = self.function.replace(function);
+
+    let result = f(self);
+
+    self.slots.truncate(self.base);
+    self.base = base;
+    self.globals = globals;
+    self.function = caller;
+    result
+  }
+
+  /// the module whose code runs, [None] for the program itself
+  pub fn module(&self) -> Option<String> {
+    self.globals.borrow().module.clone()
+  }
+
+  /// the value of [symbol], [None] while it isn't assigned
+  pub fn lookup(&self, symbol: &Symbol) -> Option<RuntimeValue> {
+    match symbol.slot {
+      Some(Slot::Local(index)) => match &self.slots[self.base + index] {
+        Local::Value(e) => e.clone(),
+        Local::Cell(e) => e.borrow().clone(),
+      },
+      Some(Slot::Upvalue(index)) => self.upvalue(index).borrow().clone(),
+      None => self.globals.borrow().get_variable(symbol.name),
+    }
+  }
+
+  /// declares [symbol], like [crate::io::runtime::Context::let_variable]
+  pub fn define(&mut self, symbol: &Symbol, value: RuntimeValue) -> RuntimeValue {
+    match symbol.slot {
+      Some(slot) => self.store(slot, value.clone()),
+      None => {
+        self.globals.borrow_mut().let_variable(symbol.name, value.clone());
+      }
+    }
+    value
+  }
+
+  /// updates [symbol], like [crate::io::runtime::Context::assign_variable]
+  pub fn assign(&mut self, symbol: &Symbol, value: RuntimeValue) -> RuntimeValue {
+    match symbol.slot {
+      Some(slot) => self.store(slot, value.clone()),
+      None => {
+        self.globals.borrow_mut().assign_variable(symbol.name, value.clone());
+      }
+    }
+    value
+  }
+
+  fn store(&mut self, slot: Slot, value: RuntimeValue) {
+    match slot {
+      Slot::Local(index) => match &mut self.slots[self.base + index] {
+        Local::Value(e) => *e = Some(value),
+        Local::Cell(e) => *e.borrow_mut() = Some(value),
+      },
+      Slot::Upvalue(index) => *self.upvalue(index).borrow_mut() = Some(value),
+    }
+  }
+
+  fn upvalue(&self, index: usize) -> &Upvalue {
+    &self.function.as_ref().expect("upvalue outside of any function").upvalues[index]
+  }
+
+  /// the cells a closure laid out as [layout] captures from the innermost frame
+  pub fn capture(&self, layout: &Layout) -> Vec<Upvalue> {
+    layout.captures.iter().map(|capture| match capture {
+      Capture::Cell(index) => match &self.slots[self.base + index] {
+        Local::Cell(e) => e.clone(),
+        Local::Value(_) => unreachable!("slot {} is captured without a cell", index),
+      },
+      Capture::Upvalue(index) => self.upvalue(*index).clone(),
+    }).collect()
+  }
+}
@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use crate::io::expr::Symbol;
+use crate::io::lexer::Span;
+use crate::io::parser::ParseError;
+use crate::io::runtime::{module_name, RuntimeValue};
+
+pub type EvalResult = Result<RuntimeValue, RuntimeError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+  SyntaxError,
+  /// an undefined variable, property or module member
+  NameError,
+  /// an operation applied to a value of the wrong type
+  TypeError,
+  /// a value of the right type that can't be used, eg: a pattern that doesn't match
+  ValueError,
+  IndexError,
+  KeyError,
+  DivisionByZero,
+  Overflow,
+  /// a call with the wrong number of arguments
+  ArityError,
+  /// a private name or field used from another module
+  AccessError,
+  ImportError,
+  StackOverflow,
+  /// raised by the script itself with `panic`
+  Panic,
+  /// raised by a native or the embedder
+  HostError,
+}
+
+impl fmt::Display for ErrorKind {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+/// a call in progress, the evaluator keeps one per script function on [crate::io::runtime::Runtime::stack]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+  pub function: Symbol,
+  /// where the function was called, [None] when called by a builtin or the host
+  pub span: Option<Span>,
+  /// declaring module of the function
+  pub module: Option<String>,
+}
+
+impl fmt::Display for Frame {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{}.{}", module_name(&self.module), self.function.name)?;
+    if let Some(span) = self.span {
+      write!(f, " called at {}", span)?;
+    }
+    Ok(())
+  }
+}
+
+/// why a program, or a call into it, failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+  pub kind: ErrorKind,
+  pub message: String,
+  /// innermost expression that failed, [None] until the error passes one that has a position
+  pub span: Option<Span>,
+  /// the calls in progress when the error was raised, innermost first
+  pub trace: Vec<Frame>,
+}
+
+impl RuntimeError {
+  pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+    RuntimeError { kind, message: message.into(), span: None, trace: Vec::new() }
+  }
+
+  pub fn name(message: impl Into<String>) -> Self {
+    RuntimeError::new(ErrorKind::NameError, message)
+  }
+
+  pub fn type_error(message: impl Into<String>) -> Self {
+    RuntimeError::new(ErrorKind::TypeError, message)
+  }
+
+  pub fn value(message: impl Into<String>) -> Self {
+    RuntimeError::new(ErrorKind::ValueError, message)
+  }
+
+  pub fn index(message: impl Into<String>) -> Self {
+    RuntimeError::new(ErrorKind::IndexError, message)
+  }
+
+  pub fn arity(name: &str, expected: usize, found: usize) -> Self {
+    RuntimeError::new(ErrorKind::ArityError, format!("{} expects {} arguments, found {}", name, expected, found))
+  }
+
+  pub fn host(message: impl Into<String>) -> Self {
+    RuntimeError::new(ErrorKind::HostError, message)
+  }
+
+  /// locates the error at [span], unless an inner expression already did
+  pub fn at(mut self, span: Span) -> Self {
+    self.span.get_or_insert(span);
+    self
+  }
+
+  pub fn at_opt(self, span: Option<Span>) -> Self {
+    match span {
+      Some(span) => self.at(span),
+      None => self,
+    }
+  }
+
+  /// records the calls in progress, [stack] is innermost last
+  pub fn with_trace(mut self, stack: &[Frame]) -> Self {
+    self.trace = stack.iter().rev().cloned().collect();
+    self
+  }
+
+  /// the backtrace of the error, innermost call first
+  pub fn backtrace(&self) -> String {
+    let mut lines = Vec::new();
+    // runaway recursion repeats the same call, show it once
+    let mut frames = self.trace.iter().peekable();
+    while let Some(frame) = frames.next() {
+      let mut repeated = 1;
+      while frames.next_if_eq(&frame).is_some() {
+        repeated += 1;
+      }
+      match repeated {
+        1 => lines.push(format!("  in {}", frame)),
+        n => lines.push(format!("  in {} ({} times)", frame, n)),
+      }
+    }
+    lines.join("\n")
+  }
+}
+
+impl fmt::Display for RuntimeError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: {}", self.kind, self.message)?;
+    if let Some(span) = self.span {
+      write!(f, " at {}", span)?;
+    }
+    if !self.trace.is_empty() {
+      write!(f, "\n{}", self.backtrace())?;
+    }
+    Ok(())
+  }
+}
+
+impl Error for RuntimeError {}
+
+impl From<ParseError> for RuntimeError {
+  fn from(value: ParseError) -> Self {
+    RuntimeError::new(ErrorKind::SyntaxError, value.message)
+  }
+}
@@ -1,99 +1,347 @@
-use std::borrow::ToOwned;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::ops::{Add, Div, Mul, Rem, Sub};
-use std::sync::{Arc, Mutex};
-use crate::io::expr::{Expr, Parameter, Property, Symbol};
-use crate::io::runtime::{Context, RefContext, RuntimeValue, Signal};
-
-fn eval_number_binary_operation<T>(lhs: T, rhs: T, op: &str) -> T
-  where T: Add<Output=T> + Sub<Output=T> + Mul<Output=T> + Div<Output=T> + Rem<Output=T>
-{
+use std::panic::AssertUnwindSafe;
+use std::rc::Rc;
+use std::sync::{Arc, Once};
+use crate::io::builtins::{call_method, compare, core_type, equals, index, iterate, set_index, unwrap, with_index};
+use crate::io::bytecode::BinaryOp;
+use crate::io::env::{Env, Function};
+use crate::io::error::{ErrorKind, EvalResult, Frame, RuntimeError};
+use crate::io::expr::{Expr, ImportItem, Layout, Parameter, Pattern, Property, Symbol, Type, TypeDef};
+use crate::io::gc;
+use crate::io::intern::{Lookup, Name};
+use crate::io::lexer::Span;
+use crate::io::module::import;
+use crate::io::stack::{self, StackBase};
+use crate::io::pattern::{destructure, Bindings};
+use crate::io::{resolver, vm};
+use crate::io::runtime::{module_name, Backend, Context, RefContext, Runtime, RuntimeValue, Signal, TypeInfo};
+
+/// the value of [result], a [Signal] raised while computing it leaves the enclosing function instead
+/// eg: the `?` in `total = price(item)? * 2`
+macro_rules! unwind {
+  ($result:expr) => {
+    match $result? {
+      RuntimeValue::Signal(e) => return Ok(RuntimeValue::Signal(e)),
+      e => e,
+    }
+  };
+}
+
+/// like [unwind] for the values of [eval_all]
+macro_rules! unwind_all {
+  ($result:expr) => {
+    match $result? {
+      Ok(e) => e,
+      Err(e) => return Ok(RuntimeValue::Signal(e)),
+    }
+  };
+}
+
+/// arithmetic on integers, division by zero and overflow are errors instead of host panics
+fn eval_int_binary_operation(lhs: i64, rhs: i64, op: BinaryOp) -> EvalResult {
+  if matches!(op, BinaryOp::Div | BinaryOp::Rem) && rhs == 0 {
+    return Err(RuntimeError::new(ErrorKind::DivisionByZero, format!("{} {} 0", lhs, op.as_str())));
+  }
+  let result = match op {
+    BinaryOp::Add => lhs.checked_add(rhs),
+    BinaryOp::Sub => lhs.checked_sub(rhs),
+    BinaryOp::Mul => lhs.checked_mul(rhs),
+    BinaryOp::Div => lhs.checked_div(rhs),
+    BinaryOp::Rem => lhs.checked_rem(rhs),
+    _ => return Err(RuntimeError::type_error(format!("unknown operator {:?} for integers", op.as_str()))),
+  };
+  result
+    .map(RuntimeValue::Int)
+    .ok_or_else(|| RuntimeError::new(ErrorKind::Overflow, format!("{} {} {} overflows", lhs, op.as_str(), rhs)))
+}
+
+fn eval_float_binary_operation(lhs: f64, rhs: f64, op: BinaryOp) -> EvalResult {
   match op {
-    "+" => lhs + rhs,
-    "-" => lhs - rhs,
-    "*" => lhs * rhs,
-    "/" => lhs / rhs,
-    "%" => lhs % rhs,
-    _ => panic!()
+    BinaryOp::Add => Ok(RuntimeValue::Float(lhs + rhs)),
+    BinaryOp::Sub => Ok(RuntimeValue::Float(lhs - rhs)),
+    BinaryOp::Mul => Ok(RuntimeValue::Float(lhs * rhs)),
+    BinaryOp::Div => Ok(RuntimeValue::Float(lhs / rhs)),
+    BinaryOp::Rem => Ok(RuntimeValue::Float(lhs % rhs)),
+    _ => Err(RuntimeError::type_error(format!("unknown operator {:?} for floats", op.as_str()))),
+  }
+}
+
+/// declares every function and type of a block up front, so they can reference each other regardless of order
+fn hoist(body: &[Expr], env: &mut Env) {
+  for expr in body {
+    match expr {
+      Expr::FnDecl { identifier, params, body, layout, .. } => {
+        eval_fn_decl(identifier, params, body, layout, env);
+      }
+      Expr::TypeDecl { identifier, def, .. } => {
+        eval_type_decl(identifier, def, env);
+      }
+      _ => {}
+    }
   }
 }
 
-fn eval_program(body: Vec<Expr>, ctx: RefContext) -> RuntimeValue {
+fn eval_program(body: &[Expr], env: &mut Env) -> EvalResult {
+  // externs are bound before anything runs, an unresolved one fails the whole program
+  for expr in body {
+    if let Expr::ExternDecl { identifier, ty, .. } = expr {
+      eval_extern_decl(identifier, ty, env)?;
+    }
+  }
+  hoist(body, env);
   let mut result = RuntimeValue::Never;
   for expr in body {
-    result = eval(expr, ctx.clone());
+    // a failing top level statement stops the program
+    result = eval(expr, env)?;
   }
-  return result;
+  Ok(result)
 }
 
-fn eval_number(value: String) -> RuntimeValue {
+pub(crate) fn eval_number(value: &str) -> EvalResult {
   if value.chars().any(|e| e == '.') {
-    RuntimeValue::Float(value.parse::<f64>().unwrap())
+    value.parse::<f64>().map(RuntimeValue::Float).map_err(|_| RuntimeError::value(format!("invalid number {}", value)))
   } else {
-    RuntimeValue::Int(value.parse::<i64>().unwrap())
+    value.parse::<i64>().map(RuntimeValue::Int).map_err(|_| RuntimeError::new(ErrorKind::Overflow, format!("integer {} is out of range", value)))
   }
 }
 
-fn eval_assign_expr(target: Expr, value: Expr, ctx: RefContext) -> RuntimeValue {
-  match target {
-    Expr::Identifier(ident) => eval_var_decl(ident, value, ctx),
-    _ => RuntimeValue::Error(format!("only Expr::Identifier supported, found {:?}", target))
-  }
+fn eval_assign_expr(target: &Expr, value: &Expr, env: &mut Env) -> EvalResult {
+  // the whole right hand side is evaluated first, so `a, b = b, a` swaps
+  let rvalue = unwind!(eval(value, env));
+  assign_to(target, rvalue, env)
 }
 
-fn eval_var_decl(identifier: Symbol, value: Expr, ctx: RefContext) -> RuntimeValue {
-  let rvalue = eval(value, ctx.clone());
-  let mut context = ctx.lock().unwrap();
-  context.let_variable(identifier.name.as_str(), rvalue.clone())
+/// `target op= value`, `a[i()] += 1` calls `i` once
+fn eval_compound_assign(target: &Expr, value: &Expr, op: &str, span: Span, env: &mut Env) -> EvalResult {
+  let mut result = RuntimeValue::Never;
+  let stored = update(target, env, &mut |current, env| {
+    let rhs = unwind!(eval(value, env));
+    result = operator(op).and_then(|op| binary_operation(current, rhs, op)).map_err(|e| e.at(span))?;
+    Ok(result.clone())
+  })?;
+  match stored {
+    RuntimeValue::Signal(_) => Ok(stored),
+    _ => Ok(result),
+  }
 }
 
-fn eval_identifier(identifier: Symbol, ctx: RefContext) -> RuntimeValue {
-  let mut context = ctx.lock().unwrap();
-  context
-    .get_variable(identifier.name.as_str())
-    .unwrap_or(RuntimeValue::Error(format!("undefined {:?}", identifier)))
+/// replaces the value at [target] with what [f] makes of it, each container and key is evaluated once
+/// the updated copy of a value type is written back into its own container: `points[i()].x += 1`
+fn update(target: &Expr, env: &mut Env, f: &mut dyn FnMut(RuntimeValue, &mut Env) -> EvalResult) -> EvalResult {
+  match target {
+    Expr::MemberExpr { object, property, computed, span } => update(object, env, &mut |container, env| {
+      let key = unwind!(member_key(property, *computed, env));
+      let module = env.module();
+      let current = get_index(container.clone(), key.clone(), &module).map_err(|e| e.at(*span))?;
+      let value = unwind!(f(current, env));
+      let updated = assign_index(container.clone(), key, value, &module).map_err(|e| e.at(*span))?;
+      Ok(updated.unwrap_or(container))
+    }),
+    Expr::Identifier(_) => {
+      let current = unwind!(eval(target, env));
+      let value = unwind!(f(current, env));
+      assign_to(target, value, env)
+    }
+    // a temporary, eg: `make()[0] += 1`, there is nothing to write it back to
+    target => {
+      let current = unwind!(eval(target, env));
+      f(current, env)
+    }
+  }
 }
 
-fn eval_binary_expr(left: Expr, right: Expr, op: String, ctx: RefContext) -> RuntimeValue {
-  let lhs = eval(left, ctx.clone());
-  let rhs = eval(right, ctx.clone());
-
-  match lhs {
-    RuntimeValue::Error(e) => RuntimeValue::Error(e),
-    RuntimeValue::Float(l) => {
-      match rhs {
-        RuntimeValue::Float(r) => return RuntimeValue::Float(eval_number_binary_operation(l, r, op.as_str())),
-        RuntimeValue::Int(r) => return RuntimeValue::Float(eval_number_binary_operation(l, r as f64, op.as_str())),
-        RuntimeValue::String(r) => RuntimeValue::String(format!("{:.2}{}", l, r)),
-        RuntimeValue::Error(e) => RuntimeValue::Error(e),
-        _ => RuntimeValue::Never,
-      }
+/// stores [value] into the place described by [target]
+fn assign_to(target: &Expr, value: RuntimeValue, env: &mut Env) -> EvalResult {
+  match target {
+    Expr::Identifier(ident) if ident.name == Name::WILDCARD => Ok(value),
+    Expr::Identifier(ident) => Ok(env.assign(ident, value)),
+    Expr::MemberExpr { object, property, computed, span } => {
+      let assign = || -> EvalResult {
+        let container = eval(object, env)?;
+        let key = member_key(property, *computed, env)?;
+        let module = env.module();
+        if let Some(updated) = assign_index(container, key, value.clone(), &module)? {
+          assign_to(object, updated, env)?;
+        }
+        Ok(value)
+      };
+      assign().map_err(|e| e.at(*span))
     }
-    RuntimeValue::Int(l) => {
-      match rhs {
-        RuntimeValue::Float(r) => return RuntimeValue::Float(eval_number_binary_operation(l as f64, r, op.as_str())),
-        RuntimeValue::Int(r) => return RuntimeValue::Int(eval_number_binary_operation(l, r, op.as_str())),
-        RuntimeValue::String(r) => RuntimeValue::String(format!("{}{}", l, r)),
-        RuntimeValue::Error(e) => RuntimeValue::Error(e),
-        _ => RuntimeValue::Never,
+    Expr::Tuple(targets) => match value {
+      RuntimeValue::Tuple(values) if values.len() == targets.len() => {
+        for (target, value) in targets.iter().zip(values.clone()) {
+          assign_to(target, value, env)?;
+        }
+        Ok(RuntimeValue::Tuple(values))
       }
-    }
-    RuntimeValue::String(l) => {
-      match rhs {
-        RuntimeValue::String(r) => RuntimeValue::String(format!("{}{}", l, r)),
-        RuntimeValue::Int(r) => RuntimeValue::String(format!("{}{}", l, r)),
-        RuntimeValue::Float(r) => RuntimeValue::String(format!("{}{:.2}", l, r)),
-        _ => RuntimeValue::Never,
+      value => Err(RuntimeError::value(format!("can't assign {} to {} targets", value, targets.len()))),
+    },
+    Expr::Object { props } => assign_fields(None, props, value, env),
+    Expr::Struct { identifier, props } => assign_fields(Some(identifier), props, value, env),
+    // Paint(color) = thing
+    Expr::CallExpr { caller, args, .. } => {
+      let (ty, variant) = match caller.as_ref() {
+        Expr::Identifier(variant) => (None, variant),
+        Expr::MemberExpr { object, property, computed: false, .. } => match (object.as_ref(), property.as_ref()) {
+          (Expr::Identifier(ty), Expr::Identifier(variant)) => (Some(ty), variant),
+          _ => unreachable!("the parser only assigns to variants named by a type"),
+        },
+        _ => unreachable!("the parser only assigns to calls of variants"),
+      };
+      match value {
+        RuntimeValue::Enum { ty: actual, variant: name, values }
+        if name == variant.name && ty.is_none_or(|e| e.name == actual.name) && values.len() == args.len() => {
+          for (target, value) in args.iter().zip(values.clone()) {
+            assign_to(target, value, env)?;
+          }
+          Ok(RuntimeValue::Enum { ty: actual, variant: name, values })
+        }
+        value => Err(RuntimeError::value(format!("{} doesn't match {}", value, variant.name)).at(variant.span)),
       }
     }
-    _ => RuntimeValue::Never,
+    _ => unreachable!("the parser only assigns to names, members and the patterns they make up"),
+  }
+}
+
+/// `container[key] = value` as seen from [module]
+/// [Some] holds the updated copy of a value type, the caller writes it back: `a.b.c = 1`
+pub(crate) fn assign_index(container: RuntimeValue, key: RuntimeValue, value: RuntimeValue, module: &Option<String>) -> Result<Option<RuntimeValue>, RuntimeError> {
+  if let (RuntimeValue::Struct { ty, .. }, RuntimeValue::String(field)) = (&container, &key) {
+    ty.check_field(field.as_str(), module)?;
+  }
+  match container {
+    RuntimeValue::Vec(_) | RuntimeValue::Map(_) => set_index(container, key, value).map(|_| None),
+    container => with_index(container, key, value).map(Some),
+  }
+}
+
+/// `{x, y: b} = point`, `Vec3{x, y} = point`
+fn assign_fields(ty: Option<&Symbol>, props: &[Property], value: RuntimeValue, env: &mut Env) -> EvalResult {
+  let (fields, actual) = match &value {
+    RuntimeValue::Struct { ty: actual, fields } if ty.is_none_or(|e| e.name == actual.name) => (fields, Some(actual)),
+    RuntimeValue::Object(fields) if ty.is_none() => (fields, None),
+    value => return Err(RuntimeError::value(format!("can't destructure {} into fields", value))),
+  };
+  let module = env.module();
+  for prop in props {
+    if let Some(actual) = actual {
+      actual.check_field(prop.identifier.name.as_str(), &module).map_err(|e| e.at(prop.identifier.span))?;
+    }
+    let field = match fields.get(prop.identifier.name.as_str()) {
+      Some(e) => e.clone(),
+      None => return Err(RuntimeError::name(format!("undefined property {:?}", prop.identifier.name)).at(prop.identifier.span)),
+    };
+    match &prop.value {
+      Some(target) => assign_to(target, field, env)?,
+      None => assign_to(&Expr::Identifier(prop.identifier.clone()), field, env)?,
+    };
+  }
+  Ok(value)
+}
+
+/// the index a member expression refers to, `a.b` is `a["b"]` and `t.0` is `t[0]`
+fn member_key(property: &Expr, computed: bool, env: &mut Env) -> EvalResult {
+  match (computed, property) {
+    (true, e) => eval(e, env),
+    (false, Expr::Identifier(e)) => Ok(RuntimeValue::String(e.name.to_string())),
+    (false, Expr::Number(e)) => eval_number(e),
+    (false, _) => unreachable!("the parser only reads names and numbers after a `.`"),
+  }
+}
+
+fn eval_var_decl(pattern: &Pattern, value: &Expr, env: &mut Env) -> EvalResult {
+  let rvalue = unwind!(eval(value, env));
+  let mut bindings = Bindings::new();
+  if !destructure(pattern, rvalue.clone(), &env.globals, &mut bindings)? {
+    return Err(RuntimeError::value(format!("{} doesn't match the declared pattern", rvalue)));
+  }
+  for (name, value) in bindings {
+    env.define(&name, value);
+  }
+  Ok(rvalue)
+}
+
+fn eval_identifier(identifier: &Symbol, env: &mut Env) -> EvalResult {
+  env
+    .lookup(identifier)
+    .ok_or_else(|| RuntimeError::name(format!("undefined {:?}", identifier.name)).at(identifier.span))
+}
+
+fn eval_comparison(lhs: RuntimeValue, rhs: RuntimeValue, op: BinaryOp) -> EvalResult {
+  let ordering = compare(&lhs, &rhs)?;
+
+  match ordering {
+    Some(e) => Ok(RuntimeValue::Bool(match op {
+      BinaryOp::Lt => e.is_lt(),
+      BinaryOp::LtEq => e.is_le(),
+      BinaryOp::Gt => e.is_gt(),
+      _ => e.is_ge(),
+    })),
+    // NaN
+    None => Ok(RuntimeValue::Bool(false)),
+  }
+}
+
+fn eval_binary_expr(left: &Expr, right: &Expr, op: &str, env: &mut Env) -> EvalResult {
+  let lhs = unwind!(eval(left, env));
+  let rhs = unwind!(eval(right, env));
+  binary_operation(lhs, rhs, operator(op)?)
+}
+
+/// the operator the parser read as [op], the vm gets it resolved from the compiler
+fn operator(op: &str) -> Result<BinaryOp, RuntimeError> {
+  BinaryOp::parse(op).ok_or_else(|| RuntimeError::type_error(format!("unknown operator {:?}", op)))
+}
+
+/// `lhs op rhs` for the arithmetic and comparison operators
+pub(crate) fn binary_operation(lhs: RuntimeValue, rhs: RuntimeValue, op: BinaryOp) -> EvalResult {
+  if let BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq = op {
+    return eval_comparison(lhs, rhs, op);
+  }
+
+  match (lhs, rhs) {
+    (RuntimeValue::Float(l), RuntimeValue::Float(r)) => eval_float_binary_operation(l, r, op),
+    (RuntimeValue::Float(l), RuntimeValue::Int(r)) => eval_float_binary_operation(l, r as f64, op),
+    (RuntimeValue::Int(l), RuntimeValue::Float(r)) => eval_float_binary_operation(l as f64, r, op),
+    (RuntimeValue::Int(l), RuntimeValue::Int(r)) => eval_int_binary_operation(l, r, op),
+    (RuntimeValue::Float(l), RuntimeValue::String(r)) if op == BinaryOp::Add => Ok(RuntimeValue::String(format!("{:.2}{}", l, r))),
+    (RuntimeValue::Int(l), RuntimeValue::String(r)) if op == BinaryOp::Add => Ok(RuntimeValue::String(format!("{}{}", l, r))),
+    (RuntimeValue::String(l), RuntimeValue::String(r)) if op == BinaryOp::Add => Ok(RuntimeValue::String(format!("{}{}", l, r))),
+    (RuntimeValue::String(l), RuntimeValue::Int(r)) if op == BinaryOp::Add => Ok(RuntimeValue::String(format!("{}{}", l, r))),
+    (RuntimeValue::String(l), RuntimeValue::Float(r)) if op == BinaryOp::Add => Ok(RuntimeValue::String(format!("{}{:.2}", l, r))),
+    (l, r) => Err(RuntimeError::type_error(format!("unsupported operation {} {} {}", l, op.as_str(), r))),
   }
 }
 
 
 impl Context {
-  pub fn get_variable(&mut self, name: &str) -> Option<RuntimeValue> {
-    match self.variables.get(name) {
+  pub fn new(runtime: Runtime) -> Context {
+    Context {
+      parent: None,
+      variables: HashMap::new(),
+      runtime: gc::alloc(RefCell::new(runtime)),
+      module: None,
+    }
+  }
+
+  pub fn with_parent(parent: RefContext) -> Context {
+    let (runtime, module) = {
+      let parent = parent.borrow();
+      (parent.runtime.clone(), parent.module.clone())
+    };
+    Context {
+      parent: Some(parent),
+      variables: HashMap::new(),
+      runtime,
+      module,
+    }
+  }
+
+  pub fn get_variable(&self, name: impl Lookup) -> Option<RuntimeValue> {
+    let name = name.name()?;
+    match self.variables.get(&name) {
       None => {}
       Some(v) => return Some(v.clone()),
     }
@@ -101,25 +349,45 @@ impl Context {
     match self.parent.clone() {
       None => None,
       Some(e) => {
-        return e.lock().unwrap().get_variable(name);
+        return e.borrow().get_variable(name);
       }
     }
   }
-  pub fn let_variable(&mut self, name: &str, value: RuntimeValue) -> RuntimeValue {
+  pub fn let_variable(&mut self, name: impl Into<Name>, value: RuntimeValue) -> RuntimeValue {
     // let a = 5; // define
     // let a = "bubu"; // re-define
     // if let Some(_) = self.variables.get(name) {
     //   return RuntimeValue::Error(format!("{name:?} already defined"));
     // }
-    self.variables.insert(name.to_owned(), value.clone());
+    self.variables.insert(name.into(), value.clone());
     value
   }
 
-  pub fn get_variable_context(self, variable_name: &str) -> Option<Arc<Context>> {
-    match self.variables.get(variable_name) {
+  /// updates the closest declaration of [name], declares it in this context if there is none
+  pub fn assign_variable(&mut self, name: impl Into<Name>, value: RuntimeValue) -> RuntimeValue {
+    let name = name.into();
+    if !self.set_variable(name, value.clone()) {
+      self.let_variable(name, value.clone());
+    }
+    value
+  }
+
+  fn set_variable(&mut self, name: Name, value: RuntimeValue) -> bool {
+    if let Some(e) = self.variables.get_mut(&name) {
+      *e = value;
+      return true;
+    }
+    match self.parent.clone() {
+      None => false,
+      Some(e) => e.borrow_mut().set_variable(name, value),
+    }
+  }
+
+  pub fn get_variable_context(self, variable_name: &str) -> Option<Rc<Context>> {
+    match Name::find(variable_name).and_then(|e| self.variables.get(&e)) {
       None => {}
       Some(_) => {
-        return Some(Arc::new(self));
+        return Some(Rc::new(self));
       }
     }
 
@@ -137,184 +405,633 @@ impl Context {
   }
 }
 
-fn eval_object(props: Vec<Property>, ctx: RefContext) -> RuntimeValue {
+fn eval_object(props: &[Property], env: &mut Env) -> EvalResult {
   let mut map = HashMap::new();
   for prop in props {
-    let value = match prop.value {
-      None => ctx.lock().unwrap().get_variable(prop.identifier.name.as_str()).unwrap(),
-      Some(expr) => eval(expr, ctx.clone()),
+    let value = match &prop.value {
+      // {x} is {x: x}
+      None => eval_identifier(&prop.identifier, env)?,
+      Some(expr) => unwind!(eval(expr, env)),
     };
-    map.insert(prop.identifier.name.clone(), value);
+    map.insert(prop.identifier.name.to_string(), value);
   }
-  RuntimeValue::Object(map)
+  Ok(RuntimeValue::Object(map))
 }
 
-fn eval_call(caller: Expr, args: Vec<Expr>, ctx: RefContext) -> RuntimeValue {
-  let a = args.into_iter().map(|e| eval(e, ctx.clone())).collect();
-  let f = eval(caller.clone(), ctx.clone());
+// Name{field: value, ...}
+fn eval_struct(identifier: &Symbol, props: &[Property], env: &mut Env) -> EvalResult {
+  let ty = eval_identifier(identifier, env)?;
+  let mut fields = Vec::new();
+  for prop in props {
+    let value = match &prop.value {
+      None => eval_identifier(&prop.identifier, env)?,
+      Some(expr) => unwind!(eval(expr, env)),
+    };
+    fields.push((prop.identifier.clone(), value));
+  }
+  let module = env.module();
+  new_struct(ty, identifier, fields, &module)
+}
 
-  match f {
-    RuntimeValue::ExternFn(delegate) => delegate(a, ctx.clone()),
-    RuntimeValue::Fn { body, params, decl_ctx, .. } => {
-      let mut context = Context {
-        parent: Some(decl_ctx.clone()),
-        variables: HashMap::new(),
-      };
+/// a value of the struct type [ty], named [identifier] in the source, built from [module]
+pub(crate) fn new_struct(ty: RuntimeValue, identifier: &Symbol, fields: Vec<(Symbol, RuntimeValue)>, module: &Option<String>) -> EvalResult {
+  let ty = match ty {
+    RuntimeValue::Type(e) if matches!(e.def, TypeDef::Struct { .. }) => e,
+    e => return Err(RuntimeError::type_error(format!("{} is not a struct", e)).at(identifier.span)),
+  };
+  let names = ty.field_names();
+
+  let mut values = HashMap::new();
+  for (field, value) in fields {
+    if !names.iter().any(|e| *e == field.name) {
+      return Err(RuntimeError::name(format!("{} has no field {:?}", ty.name, field.name)).at(field.span));
+    }
+    ty.check_field(field.name.as_str(), module).map_err(|e| e.at(field.span))?;
+    values.insert(field.name.to_string(), value);
+  }
+  if let Some(name) = names.iter().find(|e| !values.contains_key(*e)) {
+    return Err(RuntimeError::value(format!("missing field {:?} of {}", name, ty.name)).at(identifier.span));
+  }
+  Ok(RuntimeValue::Struct { ty, fields: values })
+}
+
+fn eval_member_expr(object: &Expr, property: &Expr, computed: bool, env: &mut Env) -> EvalResult {
+  let value = unwind!(eval(object, env));
+  let module = env.module();
+
+  match (computed, property) {
+    (false, Expr::Identifier(e)) => get_property(value, e.name.as_str(), &module),
+    // t.0
+    (false, Expr::Number(e)) => get_index(value, eval_number(e)?, &module),
+    (true, e) => get_index(value, unwind!(eval(e, env)), &module),
+    (false, _) => unreachable!("the parser only reads names and numbers after a `.`"),
+  }
+}
+
+/// `value.name` as seen from [module]
+pub(crate) fn get_property(value: RuntimeValue, name: &str, module: &Option<String>) -> EvalResult {
+  match value {
+    RuntimeValue::Object(props) => props
+      .get(name)
+      .cloned()
+      .ok_or_else(|| RuntimeError::name(format!("undefined property {:?}", name))),
+    RuntimeValue::Struct { ty, fields } => {
+      ty.check_field(name, module)?;
+      fields.get(name).cloned().ok_or_else(|| RuntimeError::name(format!("undefined property {:?}", name)))
+    }
+    // math.sqrt
+    RuntimeValue::Module(module) => module.export(name),
+    // Things.Car
+    RuntimeValue::Type(ty) => match ty.variant_arity(name) {
+      Some(0) => Ok(RuntimeValue::Enum { ty, variant: name.to_string(), values: Vec::new() }),
+      Some(n) => Err(RuntimeError::arity(format!("{}.{}", ty.name, name).as_str(), n, 0)),
+      None => Err(RuntimeError::name(format!("{} has no variant {:?}", ty.name, name))),
+    },
+    value => Err(RuntimeError::name(format!("{} has no property {:?}", value, name))),
+  }
+}
+
+/// `value[key]` as seen from [module]
+pub(crate) fn get_index(value: RuntimeValue, key: RuntimeValue, module: &Option<String>) -> EvalResult {
+  if let (RuntimeValue::Struct { ty, .. }, RuntimeValue::String(field)) = (&value, &key) {
+    ty.check_field(field.as_str(), module)?;
+  }
+  index(value, key)
+}
+
+fn eval_vec(items: &[Expr], env: &mut Env) -> EvalResult {
+  let values = unwind_all!(eval_all(items, env));
+  Ok(RuntimeValue::Vec(gc::alloc(RefCell::new(values))))
+}
+
+fn eval_tuple(items: &[Expr], env: &mut Env) -> EvalResult {
+  Ok(RuntimeValue::Tuple(unwind_all!(eval_all(items, env))))
+}
+
+/// evaluates [items] in order, stopping at the first error or [Signal]
+fn eval_all(items: &[Expr], env: &mut Env) -> Result<Result<Vec<RuntimeValue>, Signal>, RuntimeError> {
+  let mut values = Vec::with_capacity(items.len());
+  for item in items {
+    match eval(item, env)? {
+      RuntimeValue::Signal(e) => return Ok(Err(e)),
+      e => values.push(e),
+    }
+  }
+  Ok(Ok(values))
+}
+
+fn eval_range(start: &Expr, end: &Expr, inclusive: bool, env: &mut Env) -> EvalResult {
+  let start = unwind!(eval(start, env));
+  let end = unwind!(eval(end, env));
+  new_range(start, end, inclusive)
+}
+
+pub(crate) fn new_range(start: RuntimeValue, end: RuntimeValue, inclusive: bool) -> EvalResult {
+  match (start, end) {
+    (RuntimeValue::Int(start), RuntimeValue::Int(end)) if inclusive => end
+      .checked_add(1)
+      .map(|end| RuntimeValue::Range { start, end })
+      .ok_or_else(|| RuntimeError::new(ErrorKind::Overflow, format!("range {}..={} overflows", start, end))),
+    (RuntimeValue::Int(start), RuntimeValue::Int(end)) => Ok(RuntimeValue::Range { start, end }),
+    (start, end) => Err(RuntimeError::type_error(format!("range bounds must be integers, found {} and {}", start, end))),
+  }
+}
+
+/// calls [f] from the host or a native, outside of any frame
+pub fn call_function(f: RuntimeValue, args: Vec<RuntimeValue>, ctx: RefContext) -> EvalResult {
+  call_at(f, args, None, &mut Env::new(ctx))
+}
+
+/// runs [f] as a boundary a host panic can't cross, eg: a native that unwraps, it becomes a [ErrorKind::Panic] error
+/// the calls [f] left in progress are dropped from the stack, the panic isn't reported on stderr, its error says where it happened
+pub fn contain_panic(ctx: &RefContext, f: impl FnOnce() -> EvalResult) -> EvalResult {
+  let runtime = ctx.borrow().runtime.clone();
+  let depth = runtime.borrow().stack.len();
+  quiet_contained_panics();
+  CONTAINED.with(|e| e.set(e.get() + 1));
+  let result = std::panic::catch_unwind(AssertUnwindSafe(f));
+  CONTAINED.with(|e| e.set(e.get() - 1));
+  match result {
+    Ok(e) => e,
+    Err(payload) => {
+      let location = PANIC_LOCATION.with(|e| e.take());
+      let mut runtime = runtime.borrow_mut();
+      let error = RuntimeError::new(ErrorKind::Panic, panic_message(payload.as_ref(), location)).with_trace(&runtime.stack);
+      runtime.stack.truncate(depth);
+      Err(error)
+    }
+  }
+}
+
+/// wraps the panic hook of the process once, it stays silent for the panics of a [contain_panic] of the same thread
+/// and records where they happened instead, the others are reported by the previous hook as usual
+fn quiet_contained_panics() {
+  static HOOK: Once = Once::new();
+  HOOK.call_once(|| {
+    let report = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+      if CONTAINED.try_with(|e| e.get()).unwrap_or(0) == 0 {
+        return report(info);
+      }
+      let location = info.location().map(|e| e.to_string());
+      let _ = PANIC_LOCATION.try_with(|e| e.replace(location));
+    }));
+  });
+}
+
+fn panic_message(payload: &(dyn Any + Send), location: Option<String>) -> String {
+  let at = location.map(|e| format!(" at {}", e)).unwrap_or_default();
+  match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+    (Some(e), _) => format!("host panicked{}: {}", at, e),
+    (_, Some(e)) => format!("host panicked{}: {}", at, e),
+    _ => format!("host panicked{}", at),
+  }
+}
+
+/// calls [f] from the call site at [span], script functions run in a new [Frame]
+/// the tail calls they leave are made here, one after the other, so that recursing through them doesn't grow the stack
+pub(crate) fn call_at(f: RuntimeValue, args: Vec<RuntimeValue>, span: Option<Span>, env: &mut Env) -> EvalResult {
+  let max_stack = env.globals.borrow().runtime.borrow().max_stack;
+  let _base = StackBase::enter(max_stack);
+  let (mut f, mut args, mut span) = (f, args, span);
+  loop {
+    match call_once(f, args, span, env)? {
+      RuntimeValue::Signal(Signal::TailCall { callee, args: next, span: at }) => (f, args, span) = (*callee, next, at),
+      e => return Ok(e),
+    }
+  }
+}
+
+thread_local! {
+  /// how many [contain_panic] boundaries the thread is inside of
+  static CONTAINED: Cell<usize> = const { Cell::new(0) };
+  /// where the last contained panic of the thread happened, taken by its [contain_panic]
+  static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// a "stack overflow" once [runtime] has as many calls in progress as it allows,
+/// or they took more of the host stack than [Runtime::max_stack], whichever comes first
+pub(crate) fn check_stack(runtime: &Runtime, span: Option<Span>) -> Result<(), RuntimeError> {
+  let message = if runtime.stack.len() >= runtime.max_call_depth {
+    format!("exceeded {} nested calls", runtime.max_call_depth)
+  } else if stack::used() > runtime.max_stack {
+    format!("exceeded {} bytes of host stack", runtime.max_stack)
+  } else {
+    return Ok(());
+  };
+  Err(RuntimeError::new(ErrorKind::StackOverflow, message).at_opt(span).with_trace(&runtime.stack))
+}
 
-      for (i, param) in params.iter().enumerate() {
-        match a.get(i) {
-          Some(value) => { context.let_variable(param.name.as_str(), value.to_owned()); }
-          None => { return RuntimeValue::Error(format!("invalid args")); }
+/// like [call_at], a [Signal::TailCall] is the result when [f] ends with one
+fn call_once(f: RuntimeValue, args: Vec<RuntimeValue>, span: Option<Span>, env: &mut Env) -> EvalResult {
+  let result = match f {
+    RuntimeValue::ExternFn(delegate) => delegate.call(args, env.globals.clone()),
+    RuntimeValue::Closure(closure) => vm::call(closure, args, span),
+    RuntimeValue::Fn(function) => {
+      let name = function.identifier.clone().unwrap_or_else(|| Symbol::new("closure", Span::default()));
+      if args.len() != function.params.len() {
+        return Err(RuntimeError::arity(name.name.as_str(), function.params.len(), args.len()).at_opt(span));
+      }
+      let mut bindings = Bindings::new();
+      for (param, value) in function.params.iter().zip(args) {
+        if !destructure(&param.pattern, value.clone(), &function.globals, &mut bindings)? {
+          return Err(RuntimeError::value(format!("argument {} doesn't match its parameter", value)).at_opt(span));
         }
       }
 
-      let context = Arc::new(Mutex::new(context));
-      let result = eval(*body, context.clone());
-      if let RuntimeValue::Signal(e) = result.clone() {
-        return match e {
-          Signal::Break => RuntimeValue::Never,
-          Signal::Return(e) => *e
-        };
+      let (runtime, module) = {
+        let globals = function.globals.borrow();
+        (globals.runtime.clone(), globals.module.clone())
+      };
+      {
+        let mut runtime = runtime.borrow_mut();
+        check_stack(&runtime, span)?;
+        runtime.stack.push(Frame { function: name, span, module });
+      }
+
+      let result = env.call(function.clone(), |env| {
+        for (name, value) in bindings {
+          env.define(&name, value);
+        }
+        eval(&function.body, env)
+      });
+      let mut runtime = runtime.borrow_mut();
+      // the innermost frame an error leaves records the whole stack
+      let result = result.map_err(|e| if e.trace.is_empty() { e.with_trace(&runtime.stack) } else { e });
+      runtime.stack.pop();
+
+      match result? {
+        RuntimeValue::Signal(Signal::Return(e)) => Ok(*e),
+        e => Ok(e),
       }
-      result
     }
-    _ => RuntimeValue::Error(format!("{:?} not a function ", caller))
+    e => Err(RuntimeError::type_error(format!("{} is not a function", e)))
+  };
+  result.map_err(|e| e.at_opt(span))
+}
+
+fn eval_call(caller: &Expr, args: &[Expr], span: Span, tail: bool, env: &mut Env) -> EvalResult {
+  if let Expr::MemberExpr { object, property, computed: false, .. } = caller {
+    if let Expr::Identifier(method) = property.as_ref() {
+      let receiver = unwind!(eval(object, env));
+      let a = unwind_all!(eval_all(args, env));
+      return invoke_method(receiver, method, a, span, tail, env);
+    }
+  }
+
+  let f = unwind!(eval(caller, env));
+  let a = unwind_all!(eval_all(args, env));
+  call_or_defer(f, a, span, tail, env)
+}
+
+/// a call of a script function in tail position is left to the caller of the function, see [call_at],
+/// natives are called in place so that the function stays on the stack an error of theirs records
+fn call_or_defer(f: RuntimeValue, args: Vec<RuntimeValue>, span: Span, tail: bool, env: &mut Env) -> EvalResult {
+  match f {
+    RuntimeValue::Fn(_) if tail => Ok(RuntimeValue::Signal(Signal::TailCall { callee: Box::new(f), args, span: Some(span) })),
+    f => call_at(f, args, Some(span), env),
   }
 }
 
-fn eval_body(body: Vec<Expr>, ctx: RefContext) -> RuntimeValue {
+/// receiver.method(args), unless the receiver is an object holding a function with that name
+fn invoke_method(receiver: RuntimeValue, method: &Symbol, args: Vec<RuntimeValue>, span: Span, tail: bool, env: &mut Env) -> EvalResult {
+  match receiver {
+    RuntimeValue::Object(props) if props.contains_key(method.name.as_str()) => call_or_defer(props[method.name.as_str()].clone(), args, span, tail, env),
+    RuntimeValue::Module(module) => call_or_defer(module.export(method.name).map_err(|e| e.at(method.span))?, args, span, tail, env),
+    receiver => call_method(receiver, method.name.as_str(), args, env.globals.clone()).map_err(|e| e.at(method.span)),
+  }
+}
+
+// receiver..method(args)
+fn eval_cascade(receiver: &Expr, method: &Symbol, args: &[Expr], env: &mut Env) -> EvalResult {
+  let receiver = unwind!(eval(receiver, env));
+  let a = unwind_all!(eval_all(args, env));
+  invoke_method(receiver.clone(), method, a, method.span, false, env)?;
+  Ok(receiver)
+}
+
+fn eval_body(body: &[Expr], env: &mut Env) -> EvalResult {
+  hoist(body, env);
+  eval_statements(body, env)
+}
+
+/// evaluates [body] in order, stopping at the first [Signal] or error
+fn eval_statements(body: &[Expr], env: &mut Env) -> EvalResult {
   let mut result = RuntimeValue::Never;
   for expr in body {
-    let line = eval(expr, ctx.clone());
-    if let RuntimeValue::Signal(_) = line.clone() {
-      return line;
+    let line = eval(expr, env)?;
+    if let RuntimeValue::Signal(_) = line {
+      return Ok(line);
     }
     result = line;
   }
-  result
+  Ok(result)
+}
+
+/// what a loop does with a [Signal] raised by its body
+enum Flow {
+  Next,
+  Exit,
+  Propagate(Signal),
+}
+
+fn loop_flow(signal: Signal, label: &Option<Symbol>) -> Flow {
+  match signal {
+    Signal::Break(None) => Flow::Exit,
+    Signal::Continue(None) => Flow::Next,
+    Signal::Break(target) if target == *label => Flow::Exit,
+    Signal::Continue(target) if target == *label => Flow::Next,
+    e => Flow::Propagate(e),
+  }
+}
+
+/// like [eval_all], a [Signal] raised by [when] leaves the loop or `if` it guards, eg: `while next()? {}`
+fn eval_condition(when: &Expr, env: &mut Env) -> Result<Result<bool, Signal>, RuntimeError> {
+  match eval(when, env)? {
+    RuntimeValue::Signal(e) => Ok(Err(e)),
+    e => condition(e).map(Ok),
+  }
 }
 
+pub(crate) fn condition(value: RuntimeValue) -> Result<bool, RuntimeError> {
+  match value {
+    RuntimeValue::Bool(e) => Ok(e),
+    e => Err(RuntimeError::type_error(format!("condition must be a bool, found {}", e))),
+  }
+}
 
-fn eval_loop(body: Vec<Expr>, ctx: RefContext) -> RuntimeValue {
+fn eval_loop(label: &Option<Symbol>, body: &[Expr], env: &mut Env) -> EvalResult {
+  hoist(body, env);
   let mut result = RuntimeValue::Never;
 
   loop {
-    for expr in body.clone() {
-      let line = eval(expr, ctx.clone());
-      if let RuntimeValue::Signal(e) = line.clone() {
-        return match e {
-          Signal::Break => result,
-          Signal::Return(_) => line,
-        };
-      }
-      result = line
+    match eval_statements(body, env)? {
+      RuntimeValue::Signal(e) => match loop_flow(e, label) {
+        Flow::Next => {}
+        Flow::Exit => return Ok(result),
+        Flow::Propagate(e) => return Ok(RuntimeValue::Signal(e)),
+      },
+      line => result = line,
     }
   }
 }
 
-fn eval_not_eq(left: Expr, right: Expr, ctx: RefContext) -> RuntimeValue {
-  let l = eval(left, ctx.clone());
-  let r = eval(right, ctx);
+fn eval_while(label: &Option<Symbol>, condition: &Expr, body: &[Expr], env: &mut Env) -> EvalResult {
+  hoist(body, env);
+  let mut result = RuntimeValue::Never;
 
-  match l {
-    RuntimeValue::Bool(lv) => {
-      if let RuntimeValue::Bool(rv) = r {
-        return RuntimeValue::Bool(lv != rv);
-      }
+  while unwind_all!(eval_condition(condition, env)) {
+    match eval_statements(body, env)? {
+      RuntimeValue::Signal(e) => match loop_flow(e, label) {
+        Flow::Next => {}
+        Flow::Exit => return Ok(result),
+        Flow::Propagate(e) => return Ok(RuntimeValue::Signal(e)),
+      },
+      line => result = line,
     }
-    RuntimeValue::Int(lv) => {
-      if let RuntimeValue::Int(rv) = r {
-        return RuntimeValue::Bool(lv != rv);
+  }
+  Ok(result)
+}
+
+fn eval_for(label: &Option<Symbol>, init: &Option<Box<Expr>>, condition: &Option<Box<Expr>>, step: &Option<Box<Expr>>, body: &[Expr], env: &mut Env) -> EvalResult {
+  if let Some(init) = init {
+    unwind!(eval(init, env));
+  }
+  hoist(body, env);
+  let mut result = RuntimeValue::Never;
+
+  loop {
+    if let Some(condition) = condition {
+      if !unwind_all!(eval_condition(condition, env)) {
+        return Ok(result);
       }
     }
+    match eval_statements(body, env)? {
+      RuntimeValue::Signal(e) => match loop_flow(e, label) {
+        Flow::Next => {}
+        Flow::Exit => return Ok(result),
+        Flow::Propagate(e) => return Ok(RuntimeValue::Signal(e)),
+      },
+      line => result = line,
+    }
+    if let Some(step) = step {
+      unwind!(eval(step, env));
+    }
+  }
+}
 
-    RuntimeValue::Float(lv) => {
-      if let RuntimeValue::Float(rv) = r {
-        return RuntimeValue::Bool(lv != rv);
-      }
+fn eval_for_in(label: &Option<Symbol>, item: &Symbol, iterable: &Expr, body: &[Expr], env: &mut Env) -> EvalResult {
+  let items = iterate(unwind!(eval(iterable, env)))?;
+  hoist(body, env);
+  let mut result = RuntimeValue::Never;
+
+  for value in items {
+    env.define(item, value);
+    match eval_statements(body, env)? {
+      RuntimeValue::Signal(e) => match loop_flow(e, label) {
+        Flow::Next => {}
+        Flow::Exit => return Ok(result),
+        Flow::Propagate(e) => return Ok(RuntimeValue::Signal(e)),
+      },
+      line => result = line,
     }
-    _ => {}
   }
+  Ok(result)
+}
 
-  RuntimeValue::Error("not the same types".to_string())
+/// `left == right`, or `left != right` when [negate]
+fn eval_equality(left: &Expr, right: &Expr, negate: bool, env: &mut Env) -> EvalResult {
+  let l = unwind!(eval(left, env));
+  let r = unwind!(eval(right, env));
+  equality(l, r, negate)
 }
 
-fn eval_eq(left: Expr, right: Expr, ctx: RefContext) -> RuntimeValue {
-  let l = eval(left, ctx.clone());
-  let r = eval(right, ctx);
+pub(crate) fn equality(l: RuntimeValue, r: RuntimeValue, negate: bool) -> EvalResult {
+  match equals(&l, &r) {
+    Some(e) => Ok(RuntimeValue::Bool(e != negate)),
+    None => Err(RuntimeError::type_error(format!("can't compare {} with {}", l, r))),
+  }
+}
 
-  match l {
-    RuntimeValue::Bool(lv) => {
-      if let RuntimeValue::Bool(rv) = r {
-        return RuntimeValue::Bool(lv == rv);
-      }
-    }
-    RuntimeValue::Int(lv) => {
-      if let RuntimeValue::Int(rv) = r {
-        return RuntimeValue::Bool(lv == rv);
+fn eval_if(when: &Expr, then: &Expr, other: &Option<Box<Expr>>, env: &mut Env) -> EvalResult {
+  match when {
+    // the resolver gives the names bound by the pattern slots of their own, only [then] sees them
+    Expr::Let { pattern, value } => {
+      let value = unwind!(eval(value, env));
+      let mut bindings = Bindings::new();
+      if destructure(pattern, value, &env.globals, &mut bindings)? {
+        for (name, value) in bindings {
+          env.define(&name, value);
+        }
+        return eval(then, env);
       }
     }
+    when => if unwind_all!(eval_condition(when, env)) {
+      return eval(then, env);
+    },
+  }
+  match other {
+    Some(e) => eval(e, env),
+    None => Ok(RuntimeValue::Never),
+  }
+}
 
-    RuntimeValue::Float(lv) => {
-      if let RuntimeValue::Float(rv) = r {
-        return RuntimeValue::Bool(lv == rv);
-      }
-    }
-    _ => {}
+/// `expr?`, the value of an `Ok` or `Some`, anything else is returned from the enclosing function
+fn eval_try(expr: &Expr, span: Span, env: &mut Env) -> EvalResult {
+  let value = unwind!(eval(expr, env));
+  match unwrap(value.clone()) {
+    Some(Ok(e)) => Ok(e),
+    Some(Err(e)) => Ok(RuntimeValue::Signal(Signal::Return(Box::new(e)))),
+    None => Err(RuntimeError::type_error(format!("? expects a Result or Maybe, found {}", value)).at(span)),
   }
+}
 
-  RuntimeValue::Error("not the same types".to_string())
+fn eval_fn_decl(identifier: &Symbol, params: &Arc<Vec<Parameter>>, body: &Arc<Expr>, layout: &Arc<Layout>, env: &mut Env) -> RuntimeValue {
+  let function = new_function(Some(identifier), params, body, layout, env);
+  // declare the function
+  env.define(identifier, function)
 }
 
-fn eval_if(when: Expr, then: Expr, other: Option<Box<Expr>>, ctx: RefContext) -> RuntimeValue {
-  let condition = eval(when, ctx.clone());
-  match condition {
-    RuntimeValue::Bool(branch) => {
-      if branch {
-        eval(then, ctx)
-      } else if let Some(e) = other {
-        eval(*e, ctx)
-      } else {
-        RuntimeValue::Never
-      }
+/// a function value, capturing the variables [layout] asks for from the innermost frame of [env]
+fn new_function(identifier: Option<&Symbol>, params: &Arc<Vec<Parameter>>, body: &Arc<Expr>, layout: &Arc<Layout>, env: &Env) -> RuntimeValue {
+  RuntimeValue::Fn(gc::alloc(Function {
+    identifier: identifier.cloned(),
+    params: params.clone(),
+    body: body.clone(),
+    layout: layout.clone(),
+    upvalues: env.capture(layout),
+    globals: env.globals.clone(),
+  }))
+}
+
+fn eval_type_decl(identifier: &Symbol, def: &TypeDef, env: &mut Env) -> RuntimeValue {
+  let module = env.module();
+  let ty = new_type(identifier, def.clone(), module);
+  env.define(identifier, ty)
+}
+
+/// core's declarations of the builtin types are the builtins, `?` and the natives make values of those
+pub(crate) fn new_type(identifier: &Symbol, def: TypeDef, module: Option<String>) -> RuntimeValue {
+  let builtin = match module.as_deref() {
+    Some("core") => core_type(identifier.name.as_str()),
+    _ => None,
+  };
+  RuntimeValue::Type(builtin.unwrap_or_else(|| Arc::new(TypeInfo { name: identifier.name.to_string(), def, module })))
+}
+
+fn eval_import(path: &[Symbol], alias: &Option<Symbol>, items: &Option<Vec<ImportItem>>, env: &mut Env) -> EvalResult {
+  let bindings = import_bindings(path, alias, items, env.globals.clone())?;
+  for (name, value) in bindings {
+    env.define(&name, value);
+  }
+  Ok(RuntimeValue::Never)
+}
+
+/// the names an import declares, in the order of [import_names]
+pub(crate) fn import_bindings(path: &[Symbol], alias: &Option<Symbol>, items: &Option<Vec<ImportItem>>, ctx: RefContext) -> Result<Bindings, RuntimeError> {
+  let name = path.iter().map(|e| e.name.as_str()).collect::<Vec<_>>().join(".");
+  let module = import(name.as_str(), ctx).map_err(|e| e.at(path[0].span))?;
+
+  let mut bindings = Bindings::new();
+  if let Some(items) = items {
+    for item in items {
+      let value = module.export(item.name.name).map_err(|e| e.at(item.name.span))?;
+      let binding = item.alias.as_ref().unwrap_or(&item.name);
+      bindings.push((binding.clone(), value));
     }
-    _ => RuntimeValue::Error("invalid condition".to_string())
   }
+  if let Some(binding) = import_module_binding(path, alias, items) {
+    bindings.push((binding.clone(), RuntimeValue::Module(module)));
+  }
+  Ok(bindings)
+}
+
+/// import a.b binds `b`, unless only items were asked for
+fn import_module_binding<'a>(path: &'a [Symbol], alias: &'a Option<Symbol>, items: &Option<Vec<ImportItem>>) -> Option<&'a Symbol> {
+  match (alias, items) {
+    (Some(alias), _) => Some(alias),
+    (None, None) => path.last(),
+    (None, Some(_)) => None,
+  }
+}
+
+/// the names an import declares, without loading the module
+pub(crate) fn import_names(path: &[Symbol], alias: &Option<Symbol>, items: &Option<Vec<ImportItem>>) -> Vec<Symbol> {
+  let mut names: Vec<Symbol> = items.iter().flatten().map(|e| e.alias.as_ref().unwrap_or(&e.name).clone()).collect();
+  names.extend(import_module_binding(path, alias, items).cloned());
+  names
+}
+
+// natives are registered by the host under the name of the declaring module
+fn eval_extern_decl(identifier: &Symbol, ty: &Option<Type>, env: &mut Env) -> EvalResult {
+  let native = resolve_extern(identifier, ty, &env.globals)?;
+  Ok(env.define(identifier, native))
 }
 
-fn eval_fn_decl(identifier: Symbol, params: Vec<Parameter>, body: Box<Expr>, ctx: RefContext) -> RuntimeValue {
-  let function = RuntimeValue::Fn {
-    identifier: identifier.clone(),
-    params,
-    body,
-    decl_ctx: ctx.clone(),
+/// the native an `extern` declaration of the module of [ctx] binds to
+pub(crate) fn resolve_extern(identifier: &Symbol, ty: &Option<Type>, ctx: &RefContext) -> EvalResult {
+  let (runtime, module) = {
+    let context = ctx.borrow();
+    (context.runtime.clone(), context.module.clone())
   };
-  // declare the function
-  ctx.lock().unwrap().let_variable(identifier.name.as_str(), function.clone());
-  return function;
+  let native = runtime.borrow().natives.resolve(module_name(&module), identifier.name.as_str(), ty);
+  native.map_err(|e| e.at(identifier.span))
 }
 
-pub fn eval(node: Expr, ctx: RefContext) -> RuntimeValue {
+/// runs [program] in [ctx] with the [Backend] of its runtime
+pub fn run(mut program: Expr, ctx: RefContext) -> EvalResult {
+  let (backend, max_stack) = {
+    let context = ctx.borrow();
+    let runtime = context.runtime.borrow();
+    (runtime.backend, runtime.max_stack)
+  };
+  let _base = StackBase::enter(max_stack);
+  let layout = resolver::resolve(&mut program, &ctx)?;
+  match backend {
+    Backend::TreeWalker => eval(&program, &mut Env::program(ctx, &layout)),
+    Backend::Vm => vm::run(&program, ctx),
+  }
+}
+
+pub fn eval(node: &Expr, env: &mut Env) -> EvalResult {
+  stack::check_nesting()?;
   match node {
-    Expr::Program(e) => eval_program(e, ctx.clone()),
-    Expr::Never => RuntimeValue::Never,
-    Expr::Error(e) => RuntimeValue::Error(e),
+    Expr::Program(e) => eval_program(e, env),
+    Expr::Never => Ok(RuntimeValue::Never),
+    Expr::Error(e) => Err(RuntimeError::new(ErrorKind::SyntaxError, e.clone())),
     Expr::Number(e) => eval_number(e),
-    Expr::BinaryExpr { left, right, op } => eval_binary_expr(*left, *right, op, ctx.clone()),
-    Expr::Identifier(e) => eval_identifier(e, ctx.clone()),
-    Expr::VarDecl { value, identifier, .. } => eval_var_decl(identifier, *value, ctx.clone()),
-    Expr::AssignExpr { target: lhs, value: rhs } => eval_assign_expr(*lhs, *rhs, ctx.clone()),
-    Expr::Object { props } => eval_object(props, ctx.clone()),
-    Expr::CallExpr { caller, args } => eval_call(*caller, args, ctx.clone()),
-    Expr::FnDecl { identifier, params, body } => eval_fn_decl(identifier, params, body, ctx.clone()),
-    Expr::Body { body } => eval_body(body, ctx.clone()),
-    Expr::IfExpr { when, then, other } => eval_if(*when, *then, other, ctx.clone()),
-    Expr::Loop { body } => eval_loop(body, ctx.clone()),
-    Expr::Break => RuntimeValue::Signal(Signal::Break),
-    Expr::Return { expr } => RuntimeValue::Signal(Signal::Return(Box::new(eval(*expr, ctx.clone())))),
-    Expr::Eq { left, right } => eval_eq(*left, *right, ctx.clone()),
-    Expr::NotEq { left, right } => eval_not_eq(*left, *right, ctx.clone()),
-    Expr::String(e) => RuntimeValue::String(e),
-    _ => RuntimeValue::Error(format!("{:?} doesn't implement [eval]", node))
-  }
-}
\ No newline at end of file
+    Expr::BinaryExpr { left, right, op, span } => eval_binary_expr(left, right, op, env).map_err(|e| e.at(*span)),
+    Expr::Identifier(e) => eval_identifier(e, env),
+    Expr::VarDecl { value, pattern, .. } => eval_var_decl(pattern, value, env),
+    Expr::AssignExpr { target: lhs, value: rhs } => eval_assign_expr(lhs, rhs, env),
+    Expr::CompoundAssign { target, value, op, span } => eval_compound_assign(target, value, op, *span, env),
+    Expr::Object { props } => eval_object(props, env),
+    Expr::CallExpr { caller, args, span, tail } => eval_call(caller, args, *span, *tail, env),
+    Expr::Cascade { receiver, method, args } => eval_cascade(receiver, method, args, env),
+    Expr::FnDecl { identifier, params, body, layout, .. } => Ok(eval_fn_decl(identifier, params, body, layout, env)),
+    Expr::ExternDecl { identifier, ty, .. } => eval_extern_decl(identifier, ty, env),
+    Expr::TypeDecl { identifier, def, .. } => Ok(eval_type_decl(identifier, def, env)),
+    Expr::Module { .. } => Ok(RuntimeValue::Never),
+    Expr::Import { path, alias, items } => eval_import(path, alias, items, env),
+    Expr::Struct { identifier, props } => eval_struct(identifier, props, env),
+    Expr::Closure { params, body, layout, .. } => Ok(new_function(None, params, body, layout, env)),
+    Expr::MemberExpr { object, property, computed, span } => eval_member_expr(object, property, *computed, env).map_err(|e| e.at(*span)),
+    Expr::Body { body } => eval_body(body, env),
+    Expr::IfExpr { when, then, other } => eval_if(when, then, other, env),
+    Expr::Loop { label, body } => eval_loop(label, body, env),
+    Expr::While { label, condition, body } => eval_while(label, condition, body, env),
+    Expr::For { label, init, condition, step, body } => eval_for(label, init, condition, step, body, env),
+    Expr::ForIn { label, item, iterable, body } => eval_for_in(label, item, iterable, body, env),
+    Expr::Break { label, .. } => Ok(RuntimeValue::Signal(Signal::Break(label.clone()))),
+    Expr::Continue { label, .. } => Ok(RuntimeValue::Signal(Signal::Continue(label.clone()))),
+    Expr::Return { expr, .. } => Ok(RuntimeValue::Signal(Signal::Return(Box::new(unwind!(eval(expr, env)))))),
+    Expr::Try { expr, span } => eval_try(expr, *span, env),
+    Expr::Range { start, end, inclusive } => eval_range(start, end, *inclusive, env),
+    Expr::Eq { left, right } => eval_equality(left, right, false, env),
+    Expr::NotEq { left, right } => eval_equality(left, right, true, env),
+    Expr::String(e) => Ok(RuntimeValue::String(e.clone())),
+    Expr::Vec(e) => eval_vec(e, env),
+    Expr::Tuple(e) => eval_tuple(e, env),
+    Expr::Property(_) | Expr::Let { .. } => unreachable!("a property is only part of an object, a `let` only the condition of an `if`"),
+  }
+}
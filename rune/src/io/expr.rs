@@ -1,19 +1,55 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::sync::Arc;
+use crate::io::lexer::{Span, Token};
+use crate::io::intern::Name;
+
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Expr {
   Program(Vec<Expr>),
-  VarDecl { constant: bool, identifier: Symbol, value: Box<Expr> },
-  FnDecl { identifier: Symbol, params: Vec<Parameter>, body: Box<Expr> },
-  BinaryExpr { left: Box<Expr>, right: Box<Expr>, op: String },
+  /// declarations marked [public] with `pub` can be imported by other modules
+  VarDecl { public: bool, constant: bool, pattern: Pattern, ty: Option<Type>, value: Box<Expr> },
+  /// [layout] is its frame, as the [crate::io::resolver] laid it out
+  /// [params] and [body] are shared with the function values made from it
+  FnDecl { id: NodeId, public: bool, identifier: Symbol, generics: Vec<Generic>, params: Arc<Vec<Parameter>>, ret: Option<Type>, body: Arc<Expr>, layout: Arc<Layout> },
+  ExternDecl { public: bool, identifier: Symbol, ty: Option<Type> },
+  TypeDecl { id: NodeId, public: bool, identifier: Symbol, generics: Vec<Generic>, def: TypeDef },
+  /// `module net;`, `module net.http;`, names the module a file belongs to
+  Module { identifier: Symbol },
+  /// `import net;`, `import math as m;`, `import core { Result, Maybe as Option };`
+  Import { path: Vec<Symbol>, alias: Option<Symbol>, items: Option<Vec<ImportItem>> },
+  Closure { params: Arc<Vec<Parameter>>, ret: Option<Type>, body: Arc<Expr>, layout: Arc<Layout> },
+  /// [span] of the operator
+  BinaryExpr { left: Box<Expr>, right: Box<Expr>, op: String, span: Span },
   AssignExpr { target: Box<Expr>, value: Box<Expr> },
+  /// `a += b`, `a++`, the container and key of [target] are evaluated once: `a[i()] += 1`
+  CompoundAssign { target: Box<Expr>, value: Box<Expr>, op: String, span: Span },
+  Range { start: Box<Expr>, end: Box<Expr>, inclusive: bool },
   Eq { left: Box<Expr>, right: Box<Expr> },
   NotEq { left: Box<Expr>, right: Box<Expr> },
-  MemberExpr { object: Box<Expr>, property: Box<Expr>, computed: bool },
-  CallExpr { caller: Box<Expr>, args: Vec<Expr> },
+  /// [span] of the `.` or `[`
+  MemberExpr { object: Box<Expr>, property: Box<Expr>, computed: bool, span: Span },
+  /// [span] of the `(`, [tail] when the enclosing function returns its value, as the [crate::io::resolver] found
+  CallExpr { caller: Box<Expr>, args: Vec<Expr>, span: Span, tail: bool },
+  /// `receiver..method(args)`, evaluates to the receiver
+  Cascade { receiver: Box<Expr>, method: Symbol, args: Vec<Expr> },
+  /// [when] is either a bool expression or a [Expr::Let]
   IfExpr { when: Box<Expr>, then: Box<Expr>, other: Option<Box<Expr>> },
+  /// `Error(e) = expr` as the condition of an `if`, holds when the value matches
+  Let { pattern: Pattern, value: Box<Expr> },
+  /// `expr?`, returns an `Error` or `None` from the enclosing function, [span] of the `?`
+  Try { expr: Box<Expr>, span: Span },
   Body { body: Vec<Expr> },
-  Loop { body: Vec<Expr> },
-  Break,
-  Return { expr: Box<Expr> },
+  Loop { label: Option<Symbol>, body: Vec<Expr> },
+  While { label: Option<Symbol>, condition: Box<Expr>, body: Vec<Expr> },
+  For { label: Option<Symbol>, init: Option<Box<Expr>>, condition: Option<Box<Expr>>, step: Option<Box<Expr>>, body: Vec<Expr> },
+  ForIn { label: Option<Symbol>, item: Symbol, iterable: Box<Expr>, body: Vec<Expr> },
+  /// [span] of the keyword, one outside of the loops of its function fails ahead of time
+  Break { label: Option<Symbol>, span: Span },
+  Continue { label: Option<Symbol>, span: Span },
+  /// [span] of the keyword, like `?` it only leaves a function, the program has none to leave
+  Return { expr: Box<Expr>, span: Span },
 
   //TODO: Halt / Panic / ....
   Error(String),
@@ -25,7 +61,10 @@ pub enum Expr {
   //TODO: turn into RuntimeValue
   String(String),
   Number(String),
+  Vec(Vec<Expr>),
+  Tuple(Vec<Expr>),
   Object { props: Vec<Property> },
+  Struct { identifier: Symbol, props: Vec<Property> },
   Property(Box<Property>),
 }
 
@@ -35,12 +74,158 @@ pub struct Property {
   pub value: Option<Expr>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// a declaration the backends refer to again once the tree is built, unique within the program it was parsed with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct NodeId(pub u32);
+
+#[derive(Debug, Clone)]
 pub struct Symbol {
-  pub name: String,
+  pub name: Name,
+  pub span: Span,
+  /// where the [crate::io::resolver] found the variable, [None] for globals, looked up by name
+  pub slot: Option<Slot>,
+}
+
+impl Symbol {
+  pub fn new(name: impl Into<Name>, span: Span) -> Self {
+    Symbol { name: name.into(), span, slot: None }
+  }
+}
+
+impl From<Token> for Symbol {
+  fn from(value: Token) -> Self {
+    Symbol::new(value.name.unwrap_or_else(|| Name::new(&value.value)), value.span)
+  }
+}
+
+/// where a function finds a variable that isn't a global
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Slot {
+  /// in its own frame
+  Local(usize),
+  /// in an enclosing function, through the cell it captured
+  Upvalue(usize),
+}
+
+/// where a closure finds a captured variable in the frame creating it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capture {
+  Cell(usize),
+  Upvalue(usize),
+}
+
+/// the frame of a function, or of the program
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+  pub slots: usize,
+  /// the slots closures capture, they are cells the frame shares with them
+  pub cells: Vec<usize>,
+  /// the upvalues of the function, in the order its [Slot::Upvalue]s number them
+  pub captures: Vec<Capture>,
+}
+
+/// the same name, wherever it was written
+impl PartialEq for Symbol {
+  fn eq(&self, other: &Self) -> bool {
+    self.name == other.name
+  }
 }
 
 #[derive(Debug, Clone)]
 pub struct Parameter {
+  pub pattern: Pattern,
+  pub ty: Option<Type>,
+  pub mutable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportItem {
+  pub name: Symbol,
+  pub alias: Option<Symbol>,
+}
+
+/// left hand side of `let` and parameters, binds the parts of a value to names
+#[derive(Debug, Clone)]
+pub enum Pattern {
+  /// `_`
+  Wildcard,
+  /// `name`
+  Identifier(Symbol),
+  /// `42`, `"text"`
+  Literal(Box<Expr>),
+  /// `(a, b)`
+  Tuple(Vec<Pattern>),
+  /// `{x, y: (a, b)}`, `Vec3{x, y}`
+  Struct { ty: Option<Symbol>, fields: Vec<(Symbol, Pattern)> },
+  /// `Paint(color)`, `Things.Car`, `None`
+  Variant { ty: Option<Symbol>, variant: Symbol, fields: Vec<Pattern> },
+}
+
+/// generic parameter of a declaration, eg: `T: Ord`
+#[derive(Debug, Clone)]
+pub struct Generic {
+  pub name: String,
+  pub bounds: Vec<Type>,
+}
+
+/// type annotation as written in the source, not checked (yet)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+  /// `()`
+  Unit,
+  /// `i32`, `Vec<T>`, `Map<K, V>`
+  Named { name: Symbol, args: Vec<Type> },
+  /// `(T) -> R`, `(item: T) -> K`
+  Fn { params: Vec<Type>, ret: Option<Box<Type>> },
+  /// `(A, B)`
+  Tuple(Vec<Type>),
+  /// `[u8, 4]`
+  Array { item: Box<Type>, size: usize },
+  /// `struct{a, b: i32}`, anonymous
+  Struct { fields: Vec<Field> },
+}
+
+impl fmt::Display for Type {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let list = |items: &[Type]| items.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", ");
+    match self {
+      Type::Unit => write!(f, "()"),
+      Type::Named { name, args } if args.is_empty() => write!(f, "{}", name.name),
+      Type::Named { name, args } => write!(f, "{}<{}>", name.name, list(args)),
+      Type::Fn { params, ret: None } => write!(f, "({})", list(params)),
+      Type::Fn { params, ret: Some(ret) } => write!(f, "({}) -> {}", list(params), ret),
+      Type::Tuple(items) if items.len() == 1 => write!(f, "({},)", items[0]),
+      Type::Tuple(items) => write!(f, "({})", list(items)),
+      Type::Array { item, size } => write!(f, "[{}, {}]", item, size),
+      Type::Struct { fields } => {
+        let fields: Vec<String> = fields.iter().map(|e| format!("{}: {}", e.name, e.ty)).collect();
+        write!(f, "struct{{{}}}", fields.join(", "))
+      }
+    }
+  }
+}
+
+/// right hand side of `type Name: ...`
+#[derive(Debug, Clone)]
+pub enum TypeDef {
+  Struct { fields: Vec<Field> },
+  Enum { variants: Vec<Variant> },
+  /// `trait{format: (self) -> Utf8}`, declared only, nothing implements one yet
+  Trait { methods: Vec<Field> },
+  Alias(Type),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+  pub name: String,
+  pub ty: Type,
+  /// `pub x: i32`, other modules can only access public fields
+  pub public: bool,
+}
+
+/// `Car`, `Paint(Color)`, `Ok: R`
+#[derive(Debug, Clone)]
+pub struct Variant {
   pub name: String,
+  pub fields: Vec<Type>,
 }
\ No newline at end of file
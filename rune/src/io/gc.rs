@@ -0,0 +1,253 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use crate::io::bytecode::Closure;
+use crate::io::collections::Table;
+use crate::io::env::Function;
+use crate::io::module::Module;
+use crate::io::runtime::{Context, Runtime, RuntimeValue, Signal};
+
+/// a collection runs once this many objects were allocated since the last one, or as many as survived it if more
+const MIN_THRESHOLD: usize = 10_000;
+
+/// an object the collector can free when it's only reachable through a cycle
+///
+/// reference counting frees everything else on its own, the collector looks for groups of objects
+/// whose every strong reference comes from inside the group, like a scope holding a function declared in it
+pub trait Trace {
+  /// calls [visit] with every tracked object this one holds a strong reference to, once per reference
+  /// returns false when the object is borrowed and can't be looked at, it's kept alive then
+  fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool;
+
+  /// drops the references this object holds, only called on garbage
+  fn clear(&self);
+}
+
+/// what the collector knows about the objects of the current thread
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeapStats {
+  /// tracked objects not freed yet, garbage included until a collection finds it
+  pub live: usize,
+  /// objects allocated since the thread started
+  pub allocated: usize,
+  /// objects freed by collections, the ones reference counting freed aren't counted
+  pub freed: usize,
+  pub collections: usize,
+}
+
+struct Heap {
+  objects: Vec<Weak<dyn Trace>>,
+  /// allocations since the last collection
+  pending: usize,
+  threshold: usize,
+  stats: HeapStats,
+}
+
+thread_local! {
+  static HEAP: RefCell<Heap> = RefCell::new(Heap {
+    objects: Vec::new(),
+    pending: 0,
+    threshold: MIN_THRESHOLD,
+    stats: HeapStats::default(),
+  });
+}
+
+/// allocates [value] as an object of the collector, the way every vector, map, scope and function is made
+pub fn alloc<T: Trace + 'static>(value: T) -> Rc<T> {
+  let object = Rc::new(value);
+  let weak: Weak<dyn Trace> = Rc::downgrade(&object) as Weak<dyn Trace>;
+  let due = HEAP.with(|heap| {
+    let mut heap = heap.borrow_mut();
+    heap.objects.push(weak);
+    heap.pending += 1;
+    heap.stats.allocated += 1;
+    heap.pending >= heap.threshold
+  });
+  if due {
+    collect();
+  }
+  object
+}
+
+/// frees the cycles nothing outside of them refers to, returns how many objects were freed
+pub fn collect() -> usize {
+  let weaks = HEAP.with(|heap| std::mem::take(&mut heap.borrow_mut().objects));
+  let objects: Vec<Rc<dyn Trace>> = weaks.iter().filter_map(Weak::upgrade).collect();
+  let index: HashMap<*const (), usize> = objects.iter().enumerate().map(|(i, e)| (address(e), i)).collect();
+
+  // references from outside of the tracked objects, [objects] itself holds one of each
+  let mut external: Vec<isize> = objects.iter().map(|e| Rc::strong_count(e) as isize - 1).collect();
+  let mut traced = vec![true; objects.len()];
+  for (i, object) in objects.iter().enumerate() {
+    traced[i] = object.trace(&mut |e| if let Some(j) = index.get(&e) {
+      external[*j] -= 1;
+    });
+  }
+
+  // anything reachable from an object referred to from outside, or one that couldn't be traced, survives
+  let mut reachable = vec![false; objects.len()];
+  let mut pending: Vec<usize> = (0..objects.len()).filter(|i| external[*i] > 0 || !traced[*i]).collect();
+  while let Some(i) = pending.pop() {
+    if std::mem::replace(&mut reachable[i], true) {
+      continue;
+    }
+    objects[i].trace(&mut |e| if let Some(j) = index.get(&e) {
+      if !reachable[*j] {
+        pending.push(*j);
+      }
+    });
+  }
+
+  let garbage: Vec<&Rc<dyn Trace>> = objects.iter().enumerate().filter(|(i, _)| !reachable[*i]).map(|(_, e)| e).collect();
+  for object in &garbage {
+    object.clear();
+  }
+  let freed = garbage.len();
+  let survivors: Vec<Weak<dyn Trace>> = objects.iter().enumerate().filter(|(i, _)| reachable[*i]).map(|(_, e)| Rc::downgrade(e)).collect();
+  drop(garbage);
+  drop(objects);
+
+  HEAP.with(|heap| {
+    let mut heap = heap.borrow_mut();
+    // objects allocated while the garbage was dropped
+    let allocated = std::mem::take(&mut heap.objects);
+    heap.objects = survivors;
+    heap.objects.extend(allocated);
+    heap.pending = 0;
+    heap.threshold = MIN_THRESHOLD.max(heap.objects.len());
+    heap.stats.freed += freed;
+    heap.stats.collections += 1;
+  });
+  freed
+}
+
+/// statistics of the heap of the current thread
+pub fn stats() -> HeapStats {
+  HEAP.with(|heap| {
+    let heap = heap.borrow();
+    HeapStats { live: heap.objects.iter().filter(|e| e.strong_count() > 0).count(), ..heap.stats }
+  })
+}
+
+fn address(object: &Rc<dyn Trace>) -> *const () {
+  Rc::as_ptr(object) as *const ()
+}
+
+/// visits the objects [value] refers to, values nested in tuples, objects, structs and enums included
+pub fn trace_value(value: &RuntimeValue, visit: &mut dyn FnMut(*const ())) {
+  match value {
+    RuntimeValue::Vec(e) => visit(Rc::as_ptr(e) as *const ()),
+    RuntimeValue::Map(e) | RuntimeValue::Set(e) => visit(Rc::as_ptr(e) as *const ()),
+    RuntimeValue::Module(e) => visit(Rc::as_ptr(e) as *const ()),
+    RuntimeValue::Fn(e) => visit(Rc::as_ptr(e) as *const ()),
+    RuntimeValue::Closure(e) => visit(Rc::as_ptr(e) as *const ()),
+    RuntimeValue::Tuple(values) | RuntimeValue::Enum { values, .. } => values.iter().for_each(|e| trace_value(e, visit)),
+    RuntimeValue::Object(fields) | RuntimeValue::Struct { fields, .. } => fields.values().for_each(|e| trace_value(e, visit)),
+    RuntimeValue::Signal(Signal::Return(e)) => trace_value(e, visit),
+    RuntimeValue::Signal(Signal::TailCall { callee, args, .. }) => {
+      trace_value(callee, visit);
+      args.iter().for_each(|e| trace_value(e, visit));
+    }
+    _ => {}
+  }
+}
+
+impl Trace for RefCell<Vec<RuntimeValue>> {
+  fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool {
+    let Ok(items) = self.try_borrow() else { return false };
+    items.iter().for_each(|e| trace_value(e, visit));
+    true
+  }
+
+  fn clear(&self) {
+    let items = self.try_borrow_mut().map(|mut e| std::mem::take(&mut *e));
+    drop(items);
+  }
+}
+
+impl Trace for RefCell<Table> {
+  fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool {
+    let Ok(table) = self.try_borrow() else { return false };
+    table.values().for_each(|e| trace_value(e, visit));
+    true
+  }
+
+  fn clear(&self) {
+    let table = self.try_borrow_mut().map(|mut e| std::mem::take(&mut *e));
+    drop(table);
+  }
+}
+
+/// a cell of a captured variable, of either backend
+impl Trace for RefCell<Option<RuntimeValue>> {
+  fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool {
+    let Ok(value) = self.try_borrow() else { return false };
+    value.iter().for_each(|e| trace_value(e, visit));
+    true
+  }
+
+  fn clear(&self) {
+    let value = self.try_borrow_mut().map(|mut e| e.take());
+    drop(value);
+  }
+}
+
+impl Trace for RefCell<Context> {
+  fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool {
+    let Ok(context) = self.try_borrow() else { return false };
+    if let Some(parent) = &context.parent {
+      visit(Rc::as_ptr(parent) as *const ());
+    }
+    visit(Rc::as_ptr(&context.runtime) as *const ());
+    context.variables.values().for_each(|e| trace_value(e, visit));
+    true
+  }
+
+  fn clear(&self) {
+    let contents = self.try_borrow_mut().map(|mut e| (std::mem::take(&mut e.variables), e.parent.take()));
+    drop(contents);
+  }
+}
+
+/// the loader holds every module imported, whose scopes refer back to the runtime
+impl Trace for RefCell<Runtime> {
+  fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool {
+    let Ok(runtime) = self.try_borrow() else { return false };
+    runtime.loader.modules().for_each(|e| visit(Rc::as_ptr(e) as *const ()));
+    true
+  }
+
+  fn clear(&self) {
+    let modules = self.try_borrow_mut().map(|mut e| e.loader.unload());
+    drop(modules);
+  }
+}
+
+impl Trace for Module {
+  fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool {
+    visit(Rc::as_ptr(&self.ctx) as *const ());
+    true
+  }
+
+  fn clear(&self) {}
+}
+
+impl Trace for Function {
+  fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool {
+    self.upvalues.iter().for_each(|e| visit(Rc::as_ptr(e) as *const ()));
+    visit(Rc::as_ptr(&self.globals) as *const ());
+    true
+  }
+
+  fn clear(&self) {}
+}
+
+impl Trace for Closure {
+  fn trace(&self, visit: &mut dyn FnMut(*const ())) -> bool {
+    self.upvalues.iter().for_each(|e| visit(Rc::as_ptr(e) as *const ()));
+    visit(Rc::as_ptr(&self.globals) as *const ());
+    true
+  }
+
+  fn clear(&self) {}
+}
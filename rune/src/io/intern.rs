@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::ops::Deref;
+use std::sync::{Mutex, OnceLock};
+
+/// an interned identifier, compared and hashed as a number, [Name::as_str] gives its text back
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Name(u32);
+
+/// interned before any other, in the order of the constants of [Name]
+const KNOWN: [&str; 1] = ["_"];
+
+/// every name seen by the process, they are few and live as long as it does
+#[derive(Default)]
+struct Interner {
+  ids: HashMap<&'static str, Name>,
+  len: usize,
+}
+
+fn interner() -> &'static Mutex<Interner> {
+  static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+  INTERNER.get_or_init(|| {
+    let mut interner = Interner::default();
+    for text in KNOWN {
+      interner.push(text);
+    }
+    Mutex::new(interner)
+  })
+}
+
+/// the first segment of [TEXTS] holds this many names, every next one twice as many as the one before
+const FIRST: usize = 64;
+
+/// the text of every name by its number, appended to under the lock of the [Interner] and read without it,
+/// a segment once allocated never moves so the texts in it are handed out as they are
+static TEXTS: [OnceLock<Box<[OnceLock<&'static str>]>>; 26] = [const { OnceLock::new() }; 26];
+
+/// the segment of [TEXTS] name [id] is in, and where in it
+fn slot(id: usize) -> (usize, usize) {
+  let n = id + FIRST;
+  let segment = (n.ilog2() - FIRST.ilog2()) as usize;
+  (segment, n - (FIRST << segment))
+}
+
+impl Interner {
+  fn push(&mut self, text: &'static str) -> Name {
+    let name = Name(self.len as u32);
+    let (segment, offset) = slot(self.len);
+    let texts = TEXTS[segment].get_or_init(|| (0..FIRST << segment).map(|_| OnceLock::new()).collect());
+    let _ = texts[offset].set(text);
+    self.ids.insert(text, name);
+    self.len += 1;
+    name
+  }
+}
+
+impl Name {
+  /// `_`, the wildcard of patterns and assignments
+  pub const WILDCARD: Name = Name(0);
+
+  pub fn new(text: &str) -> Name {
+    let mut interner = interner().lock().unwrap();
+    if let Some(name) = interner.ids.get(text) {
+      return *name;
+    }
+    let text: &'static str = Box::leak(text.to_string().into_boxed_str());
+    interner.push(text)
+  }
+
+  /// the name of [text] if it was ever interned, a lookup of a name no one used leaves no trace
+  pub fn find(text: &str) -> Option<Name> {
+    interner().lock().unwrap().ids.get(text).copied()
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    if let Some(text) = KNOWN.get(self.0 as usize) {
+      return text;
+    }
+    let (segment, offset) = slot(self.0 as usize);
+    TEXTS[segment].get().and_then(|e| e[offset].get()).expect("a name is interned before it's used")
+  }
+}
+
+/// what a variable is looked up by, text that was never interned names no variable
+pub trait Lookup {
+  fn name(&self) -> Option<Name>;
+}
+
+impl Lookup for Name {
+  fn name(&self) -> Option<Name> {
+    Some(*self)
+  }
+}
+
+impl Lookup for &str {
+  fn name(&self) -> Option<Name> {
+    Name::find(self)
+  }
+}
+
+impl Lookup for &String {
+  fn name(&self) -> Option<Name> {
+    Name::find(self)
+  }
+}
+
+impl From<&str> for Name {
+  fn from(value: &str) -> Self {
+    Name::new(value)
+  }
+}
+
+impl From<&String> for Name {
+  fn from(value: &String) -> Self {
+    Name::new(value)
+  }
+}
+
+impl From<String> for Name {
+  fn from(value: String) -> Self {
+    Name::new(&value)
+  }
+}
+
+impl Deref for Name {
+  type Target = str;
+
+  fn deref(&self) -> &Self::Target {
+    self.as_str()
+  }
+}
+
+impl PartialEq<str> for Name {
+  fn eq(&self, other: &str) -> bool {
+    self.as_str() == other
+  }
+}
+
+impl PartialEq<&str> for Name {
+  fn eq(&self, other: &&str) -> bool {
+    self.as_str() == *other
+  }
+}
+
+impl PartialEq<String> for Name {
+  fn eq(&self, other: &String) -> bool {
+    self.as_str() == other
+  }
+}
+
+impl PartialEq<Name> for String {
+  fn eq(&self, other: &Name) -> bool {
+    self == other.as_str()
+  }
+}
+
+impl fmt::Display for Name {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+/// like the text, diagnostics quote names with `{:?}`
+impl fmt::Debug for Name {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{:?}", self.as_str())
+  }
+}
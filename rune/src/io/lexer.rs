@@ -1,5 +1,9 @@
+use std::cell::Cell;
 use std::clone::Clone;
+use std::fmt;
+use std::fmt::Formatter;
 use std::sync::Mutex;
+use crate::io::intern::Name;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum TokenKind {
@@ -13,10 +17,23 @@ pub enum TokenKind {
   Eq,
   NotEq,
   Not,
+  Lt,
+  Gt,
+  LtEq,
+  GtEq,
+  AssignOperator,
+  Increment,
+  Decrement,
+  Arrow,
+  FatArrow,
   Semicolon,
   Colon,
   Comma,
   Dot,
+  /// `..`, `..<`, `..=`, the parser tells a `..` range from a cascade: `items..push(1)`
+  Range,
+  /// postfix `?`, propagates an `Error` or `None`
+  Question,
   OpenParenthesis,
   CloseParenthesis,
   OpenBrace,
@@ -26,22 +43,49 @@ pub enum TokenKind {
   Type,
   Struct,
   Fn,
+  Extern,
+  Mut,
   Union,
   Enum,
+  Trait,
   If,
   Else,
   Pub,
   Match,
   Loop,
+  While,
+  For,
+  In,
   Break,
+  Continue,
+  Label,
   Return,
+  Module,
+  Import,
+  As,
   EOF,
 }
 
+/// where a token starts in the source, line and column count from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+  pub line: usize,
+  pub column: usize,
+}
+
+impl fmt::Display for Span {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}", self.line, self.column)
+  }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
   pub kind: TokenKind,
   pub value: String,
+  /// the interned [value] of an identifier
+  pub name: Option<Name>,
+  pub span: Span,
 }
 
 pub fn keyword(value: &str) -> Option<TokenKind> {
@@ -52,14 +96,24 @@ pub fn keyword(value: &str) -> Option<TokenKind> {
     "enum" => Some(TokenKind::Enum),
     "union" => Some(TokenKind::Union),
     "struct" => Some(TokenKind::Struct),
+    "trait" => Some(TokenKind::Trait),
     "fn" => Some(TokenKind::Fn),
+    "extern" => Some(TokenKind::Extern),
+    "mut" => Some(TokenKind::Mut),
     "if" => Some(TokenKind::If),
     "else" => Some(TokenKind::Else),
     "pub" => Some(TokenKind::Pub),
     "match" => Some(TokenKind::Match),
     "loop" => Some(TokenKind::Loop),
+    "while" => Some(TokenKind::While),
+    "for" => Some(TokenKind::For),
+    "in" => Some(TokenKind::In),
     "break" => Some(TokenKind::Break),
+    "continue" => Some(TokenKind::Continue),
     "return" => Some(TokenKind::Return),
+    "module" => Some(TokenKind::Module),
+    "import" => Some(TokenKind::Import),
+    "as" => Some(TokenKind::As),
     _ => None
   }
 }
@@ -68,23 +122,29 @@ pub fn tokenize(source: &str) -> Vec<Token> {
   let src = source;
 
   let mut tokens: Vec<Token> = vec![];
+  // the characters of the source and where each starts, [index] counts characters
+  let chars: Vec<(usize, char)> = src.char_indices().collect();
   let index = Mutex::new(0);
+  // offset of the token being lexed
+  let start = Cell::new(0);
+  let lines: Vec<usize> = std::iter::once(0).chain(src.match_indices('\n').map(|(i, _)| i + 1)).collect();
+  let span = |offset: usize| {
+    let line = lines.partition_point(|e| *e <= offset);
+    Span { line, column: offset - lines[line - 1] + 1 }
+  };
 
   fn is_skippable(e: &str) -> bool {
-    match e {
-      " " | "\n" | "\t" | "\r" => true,
-      _ => false,
-    }
+    matches!(e, " " | "\n" | "\t" | "\r")
   }
 
   fn is_alphabetic(e: &str) -> bool {
-    e.chars().all(char::is_alphabetic)
+    e.chars().all(|e| char::is_alphabetic(e) || e == '_')
   }
 
   fn is_identifier(e: &str) -> bool {
     e.chars().all(|e|
       match e {
-        a if char::is_alphabetic(a) => true,
+        a if char::is_alphanumeric(a) => true,
         '_' => true,
         _ => false
       }
@@ -96,36 +156,50 @@ pub fn tokenize(source: &str) -> Vec<Token> {
   }
 
   fn is_number(e: &str) -> bool {
-    e.chars().all(|e|
-      match e {
-        '.' | '0'..='9' => true,
-        _ => false
-      })
+    e.chars().all(|e| matches!(e, '.' | '0'..='9'))
   }
 
+  // the character at [i], empty past the end
+  let char_at = |i: usize| -> &str {
+    match chars.get(i) {
+      Some((offset, e)) => &src[*offset..*offset + e.len_utf8()],
+      None => "",
+    }
+  };
+
+  let offset = |i: usize| -> usize {
+    chars.get(i).map_or(src.len(), |(offset, _)| *offset)
+  };
+
   let more = || -> bool {
     let i = index.lock().unwrap();
-    return src.len() > *i;
+    chars.len() > *i
   };
 
   let at = || -> &str {
     let i = index.lock().unwrap();
-    let result = &src[*i..*i + 1];
-    return result;
+    char_at(*i)
+  };
+
+  let next = || -> &str {
+    let i = index.lock().unwrap();
+    char_at(*i + 1)
   };
 
   let shift = || -> &str {
     let mut i = index.lock().unwrap();
-    let val = &src[*i..*i + 1];
-    *i += 1;
-    return val;
+    let val = char_at(*i);
+    *i = chars.len().min(*i + 1);
+    val
   };
 
   let mut push = |kind, value: &str| {
-    tokens.push(Token { kind, value: value.to_owned() })
+    let name = (kind == TokenKind::Identifier).then(|| Name::new(value));
+    tokens.push(Token { kind, value: value.to_owned(), name, span: span(start.get()) })
   };
 
   while more() {
+    start.set(offset(*index.lock().unwrap()));
     match at() {
       "(" => push(TokenKind::OpenParenthesis, shift()),
       ")" => push(TokenKind::CloseParenthesis, shift()),
@@ -136,8 +210,110 @@ pub fn tokenize(source: &str) -> Vec<Token> {
       ":" => push(TokenKind::Colon, shift()),
       ";" => push(TokenKind::Semicolon, shift()),
       "," => push(TokenKind::Comma, shift()),
-      "." => push(TokenKind::Dot, shift()),
-      "-" | "+" | "*" | "/" | "%" => push(TokenKind::BinaryOperator, shift()),
+      "?" => push(TokenKind::Question, shift()),
+      "." => {
+        shift();
+        if more() && at() == "." {
+          shift();
+          if more() && at() == "<" {
+            shift();
+            push(TokenKind::Range, "..<");
+          } else if more() && at() == "=" {
+            shift();
+            push(TokenKind::Range, "..=");
+          } else {
+            push(TokenKind::Range, "..");
+          }
+        } else {
+          push(TokenKind::Dot, ".");
+        }
+      }
+      "<" => {
+        shift();
+        if more() && at() == "=" {
+          shift();
+          push(TokenKind::LtEq, "<=");
+        } else {
+          push(TokenKind::Lt, "<");
+        }
+      }
+      ">" => {
+        shift();
+        if more() && at() == "=" {
+          shift();
+          push(TokenKind::GtEq, ">=");
+        } else {
+          push(TokenKind::Gt, ">");
+        }
+      }
+      "+" => {
+        shift();
+        if more() && at() == "+" {
+          shift();
+          push(TokenKind::Increment, "++");
+        } else if more() && at() == "=" {
+          shift();
+          push(TokenKind::AssignOperator, "+=");
+        } else {
+          push(TokenKind::BinaryOperator, "+");
+        }
+      }
+      "-" => {
+        shift();
+        if more() && at() == ">" {
+          shift();
+          push(TokenKind::Arrow, "->");
+        } else if more() && at() == "-" {
+          shift();
+          push(TokenKind::Decrement, "--");
+        } else if more() && at() == "=" {
+          shift();
+          push(TokenKind::AssignOperator, "-=");
+        } else {
+          push(TokenKind::BinaryOperator, "-");
+        }
+      }
+      "/" => {
+        shift();
+        if more() && at() == "/" {
+          // line comment
+          while more() && at() != "\n" {
+            shift();
+          }
+        } else if more() && at() == "*" {
+          // block comment
+          shift();
+          let mut prev = "";
+          while more() && !(prev == "*" && at() == "/") {
+            prev = shift();
+          }
+          if more() {
+            shift();
+          }
+        } else if more() && at() == "=" {
+          shift();
+          push(TokenKind::AssignOperator, "/=");
+        } else {
+          push(TokenKind::BinaryOperator, "/");
+        }
+      }
+      "*" | "%" => {
+        let op = shift();
+        if more() && at() == "=" {
+          shift();
+          push(TokenKind::AssignOperator, format!("{op}=").as_str());
+        } else {
+          push(TokenKind::BinaryOperator, op);
+        }
+      }
+      "'" => {
+        shift();
+        let mut value = "".to_owned();
+        while more() && is_identifier(at()) {
+          value.push_str(shift())
+        }
+        push(TokenKind::Label, value.as_str());
+      }
       "\"" => {
         shift();
         let mut value = "".to_owned();
@@ -164,12 +340,17 @@ pub fn tokenize(source: &str) -> Vec<Token> {
             shift();
             push(TokenKind::Eq, "==");
           }
+          ">" => {
+            shift();
+            push(TokenKind::FatArrow, "=>");
+          }
           _ => push(TokenKind::Equals, "="),
         }
       }
       e if is_int(e) => {
         let mut value = "".to_owned();
-        while more() && is_number(at()) {
+        // 0..10 is a range and t.0.x a member access, not floats
+        while more() && is_number(at()) && !(at() == "." && (next().is_empty() || !is_int(next()))) {
           value.push_str(shift())
         }
         push(TokenKind::Number, value.as_str())
@@ -192,6 +373,7 @@ pub fn tokenize(source: &str) -> Vec<Token> {
       }
     }
   }
+  start.set(src.len());
   push(TokenKind::EOF, "EOF");
-  return tokens;
+  tokens
 }
\ No newline at end of file
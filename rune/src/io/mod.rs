@@ -2,4 +2,19 @@ pub mod lexer;
 pub mod parser;
 pub mod eval;
 pub mod runtime;
+pub mod env;
+pub mod builtins;
+pub mod collections;
+pub mod pattern;
+pub mod module;
+pub mod native;
+pub mod error;
+pub mod bytecode;
+pub mod compiler;
+pub mod vm;
+pub mod resolver;
+pub mod intern;
+pub mod shared;
+pub mod gc;
+pub(crate) mod stack;
 mod expr;
\ No newline at end of file
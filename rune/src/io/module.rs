@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::io::error::{ErrorKind, EvalResult, RuntimeError};
+use crate::io::eval::run;
+use crate::io::expr::{Expr, Pattern};
+use crate::io::gc;
+use crate::io::intern::Name;
+use crate::io::lexer::tokenize;
+use crate::io::parser::parse;
+use crate::io::runtime::{Context, RefContext, RuntimeValue};
+
+pub const DEFAULT_MODULE_ROOT: &str = "modules";
+
+/// a loaded module, its top level declarations live in [ctx]
+#[derive(Debug)]
+pub struct Module {
+  pub name: String,
+  pub ctx: RefContext,
+  /// names declared `pub`, the only ones other modules can see
+  pub exports: HashSet<Name>,
+}
+
+impl Module {
+  pub fn get(&self, name: impl Into<Name>) -> Option<RuntimeValue> {
+    self.ctx.borrow().variables.get(&name.into()).cloned()
+  }
+
+  /// [name] as seen from another module
+  pub fn export(&self, name: impl Into<Name>) -> EvalResult {
+    let name = name.into();
+    match self.get(name) {
+      Some(_) if !self.exports.contains(&name) => Err(RuntimeError::new(ErrorKind::AccessError, format!("{:?} is private to module {}", name, self.name))),
+      Some(e) => Ok(e),
+      None => Err(RuntimeError::name(format!("module {} has no {:?}", self.name, name))),
+    }
+  }
+}
+
+/// a function declared under the name of an extern of the same module, eg: `panic<E: Debug>` next to `extern panic`
+/// calls aren't dispatched on argument types, one would silently replace the other
+fn check_externs(body: &[Expr]) -> Result<(), RuntimeError> {
+  let externs: HashSet<Name> = body.iter().filter_map(|e| match e {
+    Expr::ExternDecl { identifier, .. } => Some(identifier.name),
+    _ => None,
+  }).collect();
+  for expr in body {
+    if let Expr::FnDecl { identifier, .. } = expr {
+      if externs.contains(&identifier.name) {
+        return Err(RuntimeError::name(format!("{:?} is declared both as an extern and a function", identifier.name)).at(identifier.span));
+      }
+    }
+  }
+  Ok(())
+}
+
+/// names declared `pub` at the top level of [body]
+fn exports(body: &[Expr], names: &mut HashSet<Name>) {
+  for expr in body {
+    match expr {
+      Expr::FnDecl { public: true, identifier, .. }
+      | Expr::TypeDecl { public: true, identifier, .. }
+      | Expr::ExternDecl { public: true, identifier, .. } => { names.insert(identifier.name); }
+      Expr::VarDecl { public: true, pattern, .. } => pattern_names(pattern, names),
+      _ => {}
+    }
+  }
+}
+
+fn pattern_names(pattern: &Pattern, names: &mut HashSet<Name>) {
+  match pattern {
+    Pattern::Identifier(e) => { names.insert(e.name); }
+    Pattern::Tuple(e) | Pattern::Variant { fields: e, .. } => e.iter().for_each(|e| pattern_names(e, names)),
+    Pattern::Struct { fields, .. } => fields.iter().for_each(|(_, e)| pattern_names(e, names)),
+    Pattern::Wildcard | Pattern::Literal(_) => {}
+  }
+}
+
+/// finds, loads and caches the modules imported by a program
+#[derive(Debug)]
+pub struct Loader {
+  /// searched in order, module `a.b` is either `a/b.io` or every file of the `a/b/` directory
+  pub roots: Vec<PathBuf>,
+  modules: HashMap<String, Rc<Module>>,
+  /// modules being evaluated, importing one of them again is a cycle
+  loading: Vec<String>,
+}
+
+impl Default for Loader {
+  fn default() -> Self {
+    Loader {
+      roots: vec![PathBuf::from(DEFAULT_MODULE_ROOT)],
+      modules: HashMap::new(),
+      loading: Vec::new(),
+    }
+  }
+}
+
+impl Loader {
+  /// source files of module [name], the first root holding it wins
+  pub fn resolve(&self, name: &str) -> Result<Vec<PathBuf>, String> {
+    let relative: PathBuf = name.split('.').collect();
+    for root in &self.roots {
+      let file = root.join(&relative).with_extension("io");
+      if file.is_file() {
+        return Ok(vec![file]);
+      }
+
+      let dir = root.join(&relative);
+      if dir.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+          .map_err(|e| format!("unable to read {}: {}", dir.display(), e))?
+          .filter_map(|e| e.ok().map(|e| e.path()))
+          .filter(|e| e.is_file() && e.extension().is_some_and(|e| e == "io"))
+          .collect();
+        if files.is_empty() {
+          continue;
+        }
+        files.sort();
+        return Ok(files);
+      }
+    }
+    let roots: Vec<String> = self.roots.iter().map(|e| e.display().to_string()).collect();
+    Err(format!("module {} not found in [{}]", name, roots.join(", ")))
+  }
+
+  fn begin(&mut self, name: &str) -> Result<(), String> {
+    if self.loading.iter().any(|e| e == name) {
+      let mut cycle = self.loading.clone();
+      cycle.push(name.to_string());
+      return Err(format!("import cycle: {}", cycle.join(" -> ")));
+    }
+    self.loading.push(name.to_string());
+    Ok(())
+  }
+
+  fn finish(&mut self, name: &str, module: Option<Rc<Module>>) {
+    self.loading.retain(|e| e != name);
+    if let Some(module) = module {
+      self.modules.insert(name.to_string(), module);
+    }
+  }
+
+  /// the modules loaded so far
+  pub fn modules(&self) -> impl Iterator<Item=&Rc<Module>> {
+    self.modules.values()
+  }
+
+  /// forgets every module loaded, importing one again evaluates it again
+  pub fn unload(&mut self) -> HashMap<String, Rc<Module>> {
+    std::mem::take(&mut self.modules)
+  }
+}
+
+/// the outermost context, holding the builtins every module starts with
+fn root(ctx: RefContext) -> RefContext {
+  let mut ctx = ctx;
+  loop {
+    let parent = ctx.borrow().parent.clone();
+    match parent {
+      Some(e) => ctx = e,
+      None => return ctx,
+    }
+  }
+}
+
+/// loads module [name] on first use, later imports share the same instance
+pub fn import(name: &str, ctx: RefContext) -> Result<Rc<Module>, RuntimeError> {
+  let runtime = ctx.borrow().runtime.clone();
+  let files = {
+    let mut runtime = runtime.borrow_mut();
+    if let Some(e) = runtime.loader.modules.get(name) {
+      return Ok(e.clone());
+    }
+    runtime.loader.begin(name).map_err(import_error)?;
+    runtime.loader.resolve(name)
+  };
+
+  // never evaluate while holding the runtime, calls need it
+  let module = files.map_err(import_error).and_then(|files| load(name, files, ctx));
+  runtime.borrow_mut().loader.finish(name, module.as_ref().ok().cloned());
+  module
+}
+
+fn import_error(message: String) -> RuntimeError {
+  RuntimeError::new(ErrorKind::ImportError, message)
+}
+
+fn load(name: &str, files: Vec<PathBuf>, ctx: RefContext) -> Result<Rc<Module>, RuntimeError> {
+  let mut context = Context::with_parent(root(ctx));
+  context.module = Some(name.to_string());
+  let module_ctx = gc::alloc(RefCell::new(context));
+  let mut names = HashSet::new();
+
+  for file in files {
+    let source = fs::read_to_string(&file)
+      .map_err(|e| import_error(format!("unable to read {}: {}", file.display(), e)))?;
+    let program = parse(tokenize(source.as_str()))
+      .map_err(|e| RuntimeError::new(ErrorKind::SyntaxError, format!("{}: {}", file.display(), e.message)))?;
+
+    if let Expr::Program(body) = &program {
+      check_externs(body).map_err(|mut e| {
+        e.message = format!("{}: {}", file.display(), e.message);
+        e
+      })?;
+      exports(body, &mut names);
+      for expr in body {
+        if let Expr::Module { identifier } = expr {
+          if identifier.name != name {
+            return Err(import_error(format!("{} declares module {}, expected {}", file.display(), identifier.name, name)));
+          }
+        }
+      }
+    }
+
+    run(program, module_ctx.clone()).map_err(|mut e| {
+      e.message = format!("{}: {}", file.display(), e.message);
+      e
+    })?;
+  }
+  Ok(gc::alloc(Module { name: name.to_string(), ctx: module_ctx, exports: names }))
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+use crate::io::error::{EvalResult, RuntimeError};
+use crate::io::expr::{Type, TypeDef};
+use crate::io::lexer::tokenize;
+use crate::io::parser::{parse_type, ParseError};
+use crate::io::runtime::{ExternFn, RuntimeValue, TypeInfo};
+use crate::io::builtins::new_vec;
+
+/// a host function, bound to the `extern` declaration with the same qualified name
+#[derive(Debug, Clone)]
+pub struct Native {
+  pub signature: Type,
+  pub function: ExternFn,
+}
+
+/// host functions scripts can declare with `extern`, keyed by `module.name`
+#[derive(Debug, Default)]
+pub struct Registry {
+  natives: HashMap<String, Native>,
+  /// types the host provides, bound to untyped `extern` declarations
+  types: HashMap<String, Arc<TypeInfo>>,
+}
+
+impl Registry {
+  /// registers [function] as `extern name: signature;` of [module]
+  /// arguments are converted with [FromValue] and the result with [IntoValue], [signature] must name their types
+  pub fn register<Args, F: IntoNative<Args>>(&mut self, module: &str, name: &str, signature: &str, function: F) -> Result<(), RuntimeError> {
+    let declared = parse_type(tokenize(signature))?;
+    let (params, ret) = F::signature();
+    if !matches_native(&declared, &params, ret) {
+      return Err(RuntimeError::type_error(format!(
+        "{}.{} is registered as {}, but the function takes ({}) and returns {}", module, name, declared, params.join(", "), ret
+      )));
+    }
+    let function = function.into_native(name);
+    self.natives.insert(format!("{}.{}", module, name), Native { signature: declared, function });
+    Ok(())
+  }
+
+  /// like [Registry::register], for functions taking any number of untyped arguments
+  pub fn register_raw(&mut self, module: &str, name: &str, signature: &str, function: ExternFn) -> Result<(), ParseError> {
+    let signature = parse_type(tokenize(signature))?;
+    self.natives.insert(format!("{}.{}", module, name), Native { signature, function });
+    Ok(())
+  }
+
+  /// registers a type as `extern name;` of [module]
+  pub fn register_type(&mut self, module: &str, name: &str, def: TypeDef) {
+    let ty = TypeInfo { name: name.to_string(), def, module: Some(module.to_string()) };
+    self.types.insert(format!("{}.{}", module, name), Arc::new(ty));
+  }
+
+  pub fn get(&self, module: &str, name: &str) -> Option<&Native> {
+    self.natives.get(&format!("{}.{}", module, name))
+  }
+
+  /// the native an `extern` declaration refers to, its declared type must match the registered signature
+  pub fn resolve(&self, module: &str, name: &str, declared: &Option<Type>) -> EvalResult {
+    let native = match (self.get(module, name), self.types.get(&format!("{}.{}", module, name))) {
+      (Some(e), _) => e,
+      (None, Some(ty)) if declared.is_none() => return Ok(RuntimeValue::Type(ty.clone())),
+      _ => return Err(RuntimeError::name(format!("unresolved extern {}.{}", module, name))),
+    };
+    match declared {
+      Some(declared) if !same_signature(declared, &native.signature) => Err(RuntimeError::type_error(format!(
+        "extern {}.{} is declared as {}, but the host registered {}", module, name, declared, native.signature
+      ))),
+      _ => Ok(RuntimeValue::ExternFn(native.function.clone())),
+    }
+  }
+}
+
+/// `(T)` and `(T) -> ()` both return nothing
+fn same_signature(lhs: &Type, rhs: &Type) -> bool {
+  match (lhs, rhs) {
+    (Type::Fn { params: lp, ret: lr }, Type::Fn { params: rp, ret: rr }) => {
+      let unit = Box::new(Type::Unit);
+      lp == rp && lr.as_ref().unwrap_or(&unit) == rr.as_ref().unwrap_or(&unit)
+    }
+    (l, r) => l == r,
+  }
+}
+
+/// whether [signature] declares the [params] and [ret] types of a typed native, `any` and `shareable` stand for every type
+fn matches_native(signature: &Type, params: &[&str], ret: &str) -> bool {
+  let matches = |ty: &Type, rust: &str| match ty {
+    _ if rust == "any" || rust == "shareable" => true,
+    Type::Unit => rust == "()",
+    Type::Named { name, .. } => name.name == rust,
+    _ => false,
+  };
+  match signature {
+    Type::Fn { params: declared, ret: declared_ret } => {
+      declared.len() == params.len()
+        && declared.iter().zip(params).all(|(ty, rust)| matches(ty, rust))
+        && matches(declared_ret.as_deref().unwrap_or(&Type::Unit), ret)
+    }
+    _ => false,
+  }
+}
+
+/// a script value a native accepts as argument
+pub trait FromValue: Sized {
+  /// shown in type errors
+  const TYPE: &'static str;
+
+  fn from_value(value: RuntimeValue) -> Option<Self>;
+}
+
+/// a native result handed back to the script
+pub trait IntoValue {
+  /// the return type a signature declares
+  const TYPE: &'static str;
+
+  fn into_value(self) -> RuntimeValue;
+}
+
+impl FromValue for RuntimeValue {
+  const TYPE: &'static str = "any";
+
+  fn from_value(value: RuntimeValue) -> Option<Self> {
+    Some(value)
+  }
+}
+
+impl FromValue for bool {
+  const TYPE: &'static str = "bool";
+
+  fn from_value(value: RuntimeValue) -> Option<Self> {
+    match value {
+      RuntimeValue::Bool(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+impl FromValue for i64 {
+  const TYPE: &'static str = "i64";
+
+  fn from_value(value: RuntimeValue) -> Option<Self> {
+    match value {
+      RuntimeValue::Int(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+/// integers widen, like they do in arithmetic
+impl FromValue for f64 {
+  const TYPE: &'static str = "f64";
+
+  fn from_value(value: RuntimeValue) -> Option<Self> {
+    match value {
+      RuntimeValue::Float(e) => Some(e),
+      RuntimeValue::Int(e) => Some(e as f64),
+      _ => None,
+    }
+  }
+}
+
+impl FromValue for String {
+  const TYPE: &'static str = "Utf8";
+
+  fn from_value(value: RuntimeValue) -> Option<Self> {
+    match value {
+      RuntimeValue::String(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+/// a copy of the items, every one of them must convert
+impl<T: FromValue> FromValue for Vec<T> {
+  const TYPE: &'static str = "Vec";
+
+  fn from_value(value: RuntimeValue) -> Option<Self> {
+    match value {
+      RuntimeValue::Vec(e) => e.borrow().iter().cloned().map(T::from_value).collect(),
+      _ => None,
+    }
+  }
+}
+
+impl IntoValue for RuntimeValue {
+  const TYPE: &'static str = "any";
+
+  fn into_value(self) -> RuntimeValue {
+    self
+  }
+}
+
+impl IntoValue for () {
+  const TYPE: &'static str = "()";
+
+  fn into_value(self) -> RuntimeValue {
+    RuntimeValue::Never
+  }
+}
+
+impl IntoValue for bool {
+  const TYPE: &'static str = "bool";
+
+  fn into_value(self) -> RuntimeValue {
+    RuntimeValue::Bool(self)
+  }
+}
+
+impl IntoValue for i64 {
+  const TYPE: &'static str = "i64";
+
+  fn into_value(self) -> RuntimeValue {
+    RuntimeValue::Int(self)
+  }
+}
+
+impl IntoValue for f64 {
+  const TYPE: &'static str = "f64";
+
+  fn into_value(self) -> RuntimeValue {
+    RuntimeValue::Float(self)
+  }
+}
+
+impl IntoValue for String {
+  const TYPE: &'static str = "Utf8";
+
+  fn into_value(self) -> RuntimeValue {
+    RuntimeValue::String(self)
+  }
+}
+
+impl IntoValue for &str {
+  const TYPE: &'static str = "Utf8";
+
+  fn into_value(self) -> RuntimeValue {
+    RuntimeValue::String(self.to_string())
+  }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+  const TYPE: &'static str = "Vec";
+
+  fn into_value(self) -> RuntimeValue {
+    new_vec(self.into_iter().map(T::into_value).collect())
+  }
+}
+
+/// nothing is the unit value
+impl<T: IntoValue> IntoValue for Option<T> {
+  const TYPE: &'static str = T::TYPE;
+
+  fn into_value(self) -> RuntimeValue {
+    self.map_or(RuntimeValue::Never, T::into_value)
+  }
+}
+
+/// what a native returns, either a value or a result whose error fails the script
+pub trait IntoResult {
+  /// the return type a signature declares, an error isn't returned to the script
+  const TYPE: &'static str;
+
+  fn into_result(self) -> EvalResult;
+}
+
+impl<T: IntoValue> IntoResult for T {
+  const TYPE: &'static str = T::TYPE;
+
+  fn into_result(self) -> EvalResult {
+    Ok(self.into_value())
+  }
+}
+
+impl<T: IntoValue, E: Display> IntoResult for Result<T, E> {
+  const TYPE: &'static str = T::TYPE;
+
+  fn into_result(self) -> EvalResult {
+    self.map(T::into_value).map_err(|e| RuntimeError::host(e.to_string()))
+  }
+}
+
+/// a rust closure callable from scripts, [Args] are the types of its parameters
+pub trait IntoNative<Args> {
+  /// [name] is only used in arity and type errors
+  fn into_native(self, name: &str) -> ExternFn;
+
+  /// the [FromValue::TYPE] of the parameters and the [IntoResult::TYPE] of the result
+  fn signature() -> (Vec<&'static str>, &'static str);
+}
+
+fn convert<T: FromValue>(name: &str, position: usize, value: RuntimeValue) -> Result<T, RuntimeError> {
+  let found = value.to_string();
+  T::from_value(value).ok_or_else(|| RuntimeError::type_error(format!(
+    "argument {} of {} expected {}, found {}", position, name, T::TYPE, found
+  )))
+}
+
+macro_rules! into_native {
+  ($($arg:ident),*) => {
+    impl<F, R, $($arg),*> IntoNative<($($arg,)*)> for F
+    where
+      F: Fn($($arg),*) -> R + Send + Sync + 'static,
+      R: IntoResult,
+      $($arg: FromValue),*
+    {
+      #[allow(non_snake_case, unused_mut, unused_variables)]
+      fn into_native(self, name: &str) -> ExternFn {
+        let name: Arc<str> = Arc::from(name);
+        ExternFn::new(move |args, _| {
+          let arity = <[&str]>::len(&[$(stringify!($arg)),*]);
+          if args.len() != arity {
+            return Err(RuntimeError::arity(&name, arity, args.len()));
+          }
+          let mut args = args.into_iter();
+          let mut position = 0;
+          $(
+            position += 1;
+            let $arg: $arg = convert(&name, position, args.next().unwrap_or(RuntimeValue::Never))?;
+          )*
+          self($($arg),*).into_result()
+        })
+      }
+
+      fn signature() -> (Vec<&'static str>, &'static str) {
+        (vec![$($arg::TYPE),*], R::TYPE)
+      }
+    }
+  };
+}
+
+into_native!();
+into_native!(A);
+into_native!(A, B);
+into_native!(A, B, C);
+into_native!(A, B, C, D);
+into_native!(A, B, C, D, E);
@@ -1,6 +1,9 @@
-use std::sync::Mutex;
-use crate::io::expr::{Expr, Parameter, Property, Symbol};
-use crate::io::lexer::{Token, TokenKind};
+use std::sync::{Arc, Mutex};
+use crate::io::expr::{Expr, Field, Generic, ImportItem, NodeId, Parameter, Pattern, Property, Symbol, Type, TypeDef, Variant};
+use crate::io::intern::Name;
+use crate::io::lexer::{Span, Token, TokenKind};
+use crate::io::runtime::DEFAULT_MAX_STACK;
+use crate::io::stack::{self, StackBase};
 
 // Order Precedence
 // assign
@@ -11,6 +14,7 @@ use crate::io::lexer::{Token, TokenKind};
 // member
 // primary
 
+#[derive(Debug)]
 pub struct ParseError {
   pub message: String,
 }
@@ -20,6 +24,25 @@ pub type ParseResult = Result<Expr, ParseError>;
 struct ProgramParser {
   tokens: Vec<Token>,
   index: Mutex<usize>,
+  /// set while parsing conditions, where `{` opens the body:
+  /// `if (a) {` is not a closure and `if a {` is not a struct literal
+  condition: Mutex<bool>,
+  /// the next [NodeId] to hand out
+  ids: Mutex<u32>,
+  /// how deep the tree being built is, see [MAX_DEPTH]
+  depth: Mutex<usize>,
+}
+
+/// deeper trees are rejected, walking or dropping them would take more of the host stack than a thread has
+const MAX_DEPTH: usize = 1000;
+
+/// a level of the tree being parsed, the depth goes back down once it's dropped
+struct Level<'a>(&'a Mutex<usize>);
+
+impl Drop for Level<'_> {
+  fn drop(&mut self) {
+    *self.0.lock().unwrap() -= 1;
+  }
 }
 
 impl From<Expr> for ParseResult {
@@ -36,7 +59,18 @@ impl From<ParseError> for ParseResult {
 
 
 pub fn parse(tokens: Vec<Token>) -> ParseResult {
-  ProgramParser { tokens, index: Mutex::new(0) }.parse()
+  // an engine parsing its programs gives the stack its scripts may take
+  let _base = StackBase::enter(DEFAULT_MAX_STACK);
+  ProgramParser { tokens, index: Mutex::new(0), condition: Mutex::new(false), ids: Mutex::new(0), depth: Mutex::new(0) }.parse()
+}
+
+/// a lone type annotation, eg: the signature of a native `(f64) -> f64`
+pub fn parse_type(tokens: Vec<Token>) -> Result<Type, ParseError> {
+  let _base = StackBase::enter(DEFAULT_MAX_STACK);
+  let parser = ProgramParser { tokens, index: Mutex::new(0), condition: Mutex::new(false), ids: Mutex::new(0), depth: Mutex::new(0) };
+  let ty = parser.parse_type()?;
+  parser.expect(TokenKind::EOF)?;
+  Ok(ty)
 }
 
 impl ProgramParser {
@@ -64,6 +98,28 @@ impl ProgramParser {
     Expr::Program(body).into()
   }
 
+  /// one level deeper into the tree, too deep a tree is an error instead of a crash of the host: `((((1))))`
+  fn level(&self) -> Result<Level<'_>, ParseError> {
+    let depth = {
+      let mut depth = self.depth.lock().unwrap();
+      *depth += 1;
+      *depth
+    };
+    let level = Level(&self.depth);
+    let span = self.at().span;
+    if depth > MAX_DEPTH {
+      return Err(ParseError { message: format!("nested deeper than {} levels at {}", MAX_DEPTH, span) });
+    }
+    stack::check_nesting().map_err(|e| ParseError { message: format!("{} at {}", e.message, span) })?;
+    Ok(level)
+  }
+
+  fn node_id(&self) -> NodeId {
+    let mut ids = self.ids.lock().unwrap();
+    *ids += 1;
+    NodeId(*ids - 1)
+  }
+
   fn at(&self) -> Token {
     let i = self.index.lock().unwrap();
     self.tokens[*i].clone()
@@ -79,20 +135,39 @@ impl ProgramParser {
   fn expect(&self, kind: TokenKind) -> Result<Token, ParseError> {
     let prev = self.eat();
     if prev.kind != kind {
-      let error = ParseError { message: format!("expected {:?}, found {:?}", kind, prev.kind) };
+      let error = ParseError { message: format!("expected {:?}, found {:?} at {}", kind, prev.kind, prev.span) };
       return Err(error);
     }
     Ok(prev)
   }
 
+  fn peek(&self, offset: usize) -> Token {
+    let i = self.index.lock().unwrap();
+    let at = (*i + offset).min(self.tokens.len() - 1);
+    self.tokens[at].clone()
+  }
+
+  fn restrict<T>(&self, condition: bool, f: impl FnOnce() -> T) -> T {
+    let prev = std::mem::replace(&mut *self.condition.lock().unwrap(), condition);
+    let result = f();
+    *self.condition.lock().unwrap() = prev;
+    result
+  }
+
   fn more(&self) -> bool {
     self.at().kind != TokenKind::EOF
   }
 
-  // (LET | CONST) IDENT = EXPR;
+  // (LET | CONST) PATTERN [: TYPE] = EXPR;
   fn parse_var_declaration(&self) -> ParseResult {
     let constant = self.eat().kind == TokenKind::Const;
-    let identifier = self.expect(TokenKind::Identifier)?.value;
+    let pattern = self.parse_pattern()?;
+    let ty = if self.at().kind == TokenKind::Colon {
+      self.eat();
+      Some(self.parse_type()?)
+    } else {
+      None
+    };
 
     // TODO: undefined var declaration :thinking:
     // if self.at().kind == TokenKind::Semicolon {
@@ -103,17 +178,174 @@ impl ProgramParser {
     let expr = self.parse_expr()?;
     self.expect(TokenKind::Semicolon)?;
 
-    return Expr::VarDecl {
+    Expr::VarDecl {
+      public: false,
       constant,
-      identifier: Symbol { name: identifier },
+      pattern,
+      ty,
       value: Box::new(expr),
-    }.into();
+    }.into()
+  }
+
+  // _ | IDENT | LITERAL | (PATTERN, ...) | [IDENT]{IDENT [: PATTERN], ...} | [IDENT.]IDENT(PATTERN, ...) | IDENT.IDENT
+  fn parse_pattern(&self) -> Result<Pattern, ParseError> {
+    let _level = self.level()?;
+    match self.at().kind {
+      TokenKind::Number | TokenKind::String => Ok(Pattern::Literal(Box::new(self.parse_primary_expr()?))),
+      TokenKind::OpenParenthesis => {
+        self.eat();
+        let mut items = Vec::new();
+        let mut tuple = false;
+        while self.at().kind != TokenKind::CloseParenthesis {
+          items.push(self.parse_pattern()?);
+          if self.at().kind != TokenKind::Comma {
+            break;
+          }
+          self.eat();
+          tuple = true;
+        }
+        self.expect(TokenKind::CloseParenthesis)?;
+        // (a) is just a
+        if items.len() == 1 && !tuple {
+          return Ok(items.remove(0));
+        }
+        Ok(Pattern::Tuple(items))
+      }
+      TokenKind::OpenBrace => self.parse_struct_pattern(None),
+      TokenKind::Identifier => {
+        let name = Symbol::from(self.eat());
+        match self.at().kind {
+          TokenKind::OpenBrace => self.parse_struct_pattern(Some(name)),
+          TokenKind::OpenParenthesis => Ok(Pattern::Variant { ty: None, variant: name, fields: self.parse_variant_patterns()? }),
+          TokenKind::Dot => {
+            self.eat();
+            let variant = Symbol::from(self.expect(TokenKind::Identifier)?);
+            let fields = self.parse_variant_patterns()?;
+            Ok(Pattern::Variant { ty: Some(name), variant, fields })
+          }
+          _ if name.name == Name::WILDCARD => Ok(Pattern::Wildcard),
+          _ => Ok(Pattern::Identifier(name)),
+        }
+      }
+      _ => Err(ParseError { message: format!("expected pattern, found {:?} at {}", self.at().kind, self.at().span) }),
+    }
   }
 
-  fn parse_loop(&self) -> ParseResult {
+  // {IDENT [: PATTERN], ...}
+  fn parse_struct_pattern(&self, ty: Option<Symbol>) -> Result<Pattern, ParseError> {
+    self.expect(TokenKind::OpenBrace)?;
+    let mut fields = Vec::new();
+    while self.at().kind != TokenKind::CloseBrace {
+      let field = Symbol::from(self.expect(TokenKind::Identifier)?);
+      let pattern = if self.at().kind == TokenKind::Colon {
+        self.eat();
+        self.parse_pattern()?
+      } else {
+        Pattern::Identifier(field.clone())
+      };
+      fields.push((field, pattern));
+
+      if self.at().kind != TokenKind::Comma {
+        break;
+      }
+      self.eat();
+    }
+    self.expect(TokenKind::CloseBrace)?;
+    Ok(Pattern::Struct { ty, fields })
+  }
+
+  // [(PATTERN, ...)]
+  fn parse_variant_patterns(&self) -> Result<Vec<Pattern>, ParseError> {
+    let mut fields = Vec::new();
+    if self.at().kind != TokenKind::OpenParenthesis {
+      return Ok(fields);
+    }
+    self.eat();
+    while self.at().kind != TokenKind::CloseParenthesis {
+      fields.push(self.parse_pattern()?);
+      if self.at().kind != TokenKind::Comma {
+        break;
+      }
+      self.eat();
+    }
+    self.expect(TokenKind::CloseParenthesis)?;
+    Ok(fields)
+  }
+
+  // 'LABEL: (LOOP | WHILE | FOR)
+  fn parse_labeled_statement(&self) -> ParseResult {
+    let label = Some(Symbol::from(self.eat()));
+    self.expect(TokenKind::Colon)?;
+    match self.at().kind {
+      TokenKind::Loop => self.parse_loop(label),
+      TokenKind::While => self.parse_while(label),
+      TokenKind::For => self.parse_for(label),
+      _ => ParseError { message: format!("expected loop after label, found {:?} at {}", self.at().kind, self.at().span) }.into()
+    }
+  }
+
+  // [break | continue] ['LABEL]
+  fn parse_label(&self) -> Option<Symbol> {
+    if self.at().kind != TokenKind::Label {
+      return None;
+    }
+    Some(Symbol::from(self.eat()))
+  }
+
+  fn parse_loop(&self, label: Option<Symbol>) -> ParseResult {
     self.eat();
     let body = self.parse_statement_body()?;
-    Expr::Loop { body }.into()
+    Expr::Loop { label, body }.into()
+  }
+
+  // WHILE EXPR { BODY }
+  fn parse_while(&self, label: Option<Symbol>) -> ParseResult {
+    self.eat();
+    let condition = Box::new(self.parse_condition()?);
+    let body = self.parse_statement_body()?;
+    Expr::While { label, condition, body }.into()
+  }
+
+  // FOR IDENT IN EXPR { BODY }
+  // FOR [INIT]; [EXPR]; [STEP] { BODY }
+  fn parse_for(&self, label: Option<Symbol>) -> ParseResult {
+    self.eat();
+
+    if self.at().kind == TokenKind::Identifier && self.peek(1).kind == TokenKind::In {
+      let item = Symbol::from(self.eat());
+      self.eat();
+      let iterable = Box::new(self.parse_condition()?);
+      let body = self.parse_statement_body()?;
+      return Expr::ForIn { label, item, iterable, body }.into();
+    }
+
+    let init = match self.at().kind {
+      TokenKind::Semicolon => {
+        self.eat();
+        None
+      }
+      // consumes its own semicolon
+      TokenKind::Let | TokenKind::Const => Some(Box::new(self.parse_var_declaration()?)),
+      _ => {
+        let init = self.parse_condition()?;
+        self.expect(TokenKind::Semicolon)?;
+        Some(Box::new(init))
+      }
+    };
+
+    let condition = match self.at().kind {
+      TokenKind::Semicolon => None,
+      _ => Some(Box::new(self.parse_condition()?)),
+    };
+    self.expect(TokenKind::Semicolon)?;
+
+    let step = match self.at().kind {
+      TokenKind::OpenBrace => None,
+      _ => Some(Box::new(self.parse_condition()?)),
+    };
+
+    let body = self.parse_statement_body()?;
+    Expr::For { label, init, condition, step, body }.into()
   }
 
   fn parse_statement_body(&self) -> Result<Vec<Expr>, ParseError> {
@@ -129,9 +361,36 @@ impl ProgramParser {
     Ok(body)
   }
 
+  fn parse_condition(&self) -> ParseResult {
+    self.restrict(true, || self.parse_expr())
+  }
+
+  // [LET] PATTERN = EXPR | EXPR
+  fn parse_if_condition(&self) -> ParseResult {
+    if self.at().kind == TokenKind::Let {
+      self.eat();
+      let pattern = self.parse_pattern()?;
+      self.expect(TokenKind::Equals)?;
+      let value = Box::new(self.parse_condition()?);
+      return Expr::Let { pattern, value }.into();
+    }
+    // anything but a pattern followed by `=` is a plain condition
+    let start = *self.index.lock().unwrap();
+    if let Ok(pattern) = self.parse_pattern() {
+      if self.at().kind == TokenKind::Equals {
+        self.eat();
+        let value = Box::new(self.parse_condition()?);
+        return Expr::Let { pattern, value }.into();
+      }
+    }
+    *self.index.lock().unwrap() = start;
+    self.parse_condition()
+  }
+
   fn parse_if_statement(&self) -> ParseResult {
+    let _level = self.level()?;
     self.eat();
-    let condition = self.parse_expr()?;
+    let condition = self.parse_if_condition()?;
     let then = Expr::Body { body: self.parse_statement_body()? };
 
     let other = if self.at().kind == TokenKind::Else {
@@ -154,40 +413,485 @@ impl ProgramParser {
     }.into()
   }
 
-  fn parse_fn_declaration(&self) -> ParseResult {
-    self.eat();
-    let identifier = Symbol { name: self.expect(TokenKind::Identifier)?.value };
-    let args = self.parse_args()?;
-
-    let mut params = Vec::new();
-    for arg in args {
-      match arg {
-        Expr::Identifier(e) => { params.push(Parameter { name: e.name.clone() }); }
-        _ => return ParseError { message: "Only [Identifier]s expected".to_string() }.into()
+  // IDENT [<GENERICS>] : (
+  fn is_fn_declaration(&self) -> bool {
+    let mut offset = 1;
+    if self.peek(offset).kind == TokenKind::Lt {
+      let mut depth = 0;
+      loop {
+        match self.peek(offset).kind {
+          TokenKind::Lt => depth += 1,
+          TokenKind::Gt => {
+            depth -= 1;
+            if depth == 0 {
+              break;
+            }
+          }
+          TokenKind::Identifier | TokenKind::Colon | TokenKind::Comma | TokenKind::BinaryOperator => {}
+          _ => return false,
+        }
+        offset += 1;
       }
+      offset += 1;
     }
+    self.peek(offset).kind == TokenKind::Colon && self.peek(offset + 1).kind == TokenKind::OpenParenthesis
+  }
+
+  // fn IDENT(PARAMS) { BODY }
+  // IDENT [<GENERICS>] : (PARAMS) [-> TYPE] [=] { BODY }
+  // IDENT [<GENERICS>] : (PARAMS) [-> TYPE] => EXPR
+  fn parse_fn_declaration(&self) -> ParseResult {
+    let keyword = self.at().kind == TokenKind::Fn;
+    if keyword {
+      self.eat();
+    }
+    let identifier = Symbol::from(self.expect(TokenKind::Identifier)?);
+
+    let (generics, params, ret) = if keyword {
+      (Vec::new(), self.parse_params()?, None)
+    } else {
+      let generics = self.parse_generics()?;
+      self.expect(TokenKind::Colon)?;
+      let params = self.parse_params()?;
+      (generics, params, self.parse_return_type()?)
+    };
 
-    let body = Box::new(Expr::Body { body: self.parse_statement_body()? });
+    let body = Arc::new(self.parse_fn_body()?);
 
     Expr::FnDecl {
+      id: self.node_id(),
+      public: false,
       identifier,
-      params,
+      generics,
+      params: Arc::new(params),
+      ret,
       body,
+      layout: Default::default(),
     }.into()
   }
 
+  // [=] { BODY } | => EXPR
+  fn parse_fn_body(&self) -> ParseResult {
+    match self.at().kind {
+      TokenKind::FatArrow => {
+        self.eat();
+        self.parse_expr()
+      }
+      TokenKind::Equals => {
+        self.eat();
+        Expr::Body { body: self.parse_statement_body()? }.into()
+      }
+      _ => Expr::Body { body: self.parse_statement_body()? }.into(),
+    }
+  }
+
+  // (PARAMS) [-> TYPE] { | (PARAMS) [-> TYPE] =>
+  fn is_closure(&self) -> bool {
+    let params = match self.peek(1).kind {
+      TokenKind::CloseParenthesis | TokenKind::Mut | TokenKind::OpenParenthesis | TokenKind::OpenBrace => true,
+      TokenKind::Identifier => matches!(self.peek(2).kind, TokenKind::Comma | TokenKind::Colon | TokenKind::CloseParenthesis | TokenKind::OpenBrace),
+      _ => false,
+    };
+    if !params {
+      return false;
+    }
+
+    let mut offset = 0;
+    let mut depth = 0;
+    loop {
+      match self.peek(offset).kind {
+        TokenKind::OpenParenthesis => depth += 1,
+        TokenKind::CloseParenthesis => {
+          depth -= 1;
+          if depth == 0 {
+            break;
+          }
+        }
+        TokenKind::EOF => return false,
+        _ => {}
+      }
+      offset += 1;
+    }
+
+    match self.peek(offset + 1).kind {
+      TokenKind::Arrow | TokenKind::FatArrow => true,
+      TokenKind::OpenBrace => !*self.condition.lock().unwrap(),
+      _ => false,
+    }
+  }
+
+  // (PARAMS) [-> TYPE] ([=] { BODY } | => EXPR)
+  fn parse_closure(&self) -> ParseResult {
+    let params = self.parse_params()?;
+    let ret = self.parse_return_type()?;
+    let body = Arc::new(self.restrict(false, || self.parse_fn_body())?);
+    Expr::Closure { params: Arc::new(params), ret, body, layout: Default::default() }.into()
+  }
+
+  // extern IDENT [: TYPE];
+  fn parse_extern_declaration(&self) -> ParseResult {
+    self.eat();
+    let identifier = Symbol::from(self.expect(TokenKind::Identifier)?);
+    let ty = if self.at().kind == TokenKind::Colon {
+      self.eat();
+      Some(self.parse_type()?)
+    } else {
+      None
+    };
+    self.expect(TokenKind::Semicolon)?;
+    Expr::ExternDecl { public: false, identifier, ty }.into()
+  }
+
+  // ([mut] PATTERN [: TYPE], ...)
+  // untyped names take the type of the next typed one: (a, b: Vec3)
+  fn parse_params(&self) -> Result<Vec<Parameter>, ParseError> {
+    self.expect(TokenKind::OpenParenthesis)?;
+    let mut params = Vec::new();
+    while self.at().kind != TokenKind::CloseParenthesis {
+      let mutable = self.at().kind == TokenKind::Mut;
+      if mutable {
+        self.eat();
+      }
+      let pattern = self.parse_pattern()?;
+      let ty = if self.at().kind == TokenKind::Colon {
+        self.eat();
+        Some(self.parse_type()?)
+      } else {
+        None
+      };
+      params.push(Parameter { pattern, ty, mutable });
+
+      if self.at().kind != TokenKind::Comma {
+        break;
+      }
+      self.eat();
+    }
+    self.expect(TokenKind::CloseParenthesis)?;
+
+    let mut ty = None;
+    for param in params.iter_mut().rev() {
+      match &param.ty {
+        Some(e) => ty = Some(e.clone()),
+        None => param.ty = ty.clone(),
+      }
+    }
+    Ok(params)
+  }
+
+  // [<IDENT [: TYPE [+ TYPE]], ...>]
+  fn parse_generics(&self) -> Result<Vec<Generic>, ParseError> {
+    let mut generics = Vec::new();
+    if self.at().kind != TokenKind::Lt {
+      return Ok(generics);
+    }
+    self.eat();
+    while self.at().kind != TokenKind::Gt {
+      let name = self.expect(TokenKind::Identifier)?.value;
+      let mut bounds = Vec::new();
+      if self.at().kind == TokenKind::Colon {
+        self.eat();
+        bounds.push(self.parse_type()?);
+        while self.at().value == "+" {
+          self.eat();
+          bounds.push(self.parse_type()?);
+        }
+      }
+      generics.push(Generic { name, bounds });
+
+      if self.at().kind != TokenKind::Comma {
+        break;
+      }
+      self.eat();
+    }
+    self.expect(TokenKind::Gt)?;
+    Ok(generics)
+  }
+
+  // [-> TYPE]
+  fn parse_return_type(&self) -> Result<Option<Type>, ParseError> {
+    if self.at().kind != TokenKind::Arrow {
+      return Ok(None);
+    }
+    self.eat();
+    Ok(Some(self.parse_type()?))
+  }
+
+  // () | ([IDENT:] TYPE, ...) [-> TYPE] | (TYPE, TYPE, ...) | [TYPE, SIZE] | struct{FIELDS} | IDENT [<TYPE, ...>]
+  fn parse_type(&self) -> Result<Type, ParseError> {
+    let _level = self.level()?;
+    match self.at().kind {
+      TokenKind::OpenParenthesis => {
+        self.eat();
+        let mut params = Vec::new();
+        let mut trailing = false;
+        while self.at().kind != TokenKind::CloseParenthesis {
+          trailing = false;
+          // parameter names are documentation only
+          if self.at().kind == TokenKind::Identifier && self.peek(1).kind == TokenKind::Colon {
+            self.eat();
+            self.eat();
+          }
+          params.push(self.parse_type()?);
+
+          if self.at().kind != TokenKind::Comma {
+            break;
+          }
+          self.eat();
+          trailing = true;
+        }
+        self.expect(TokenKind::CloseParenthesis)?;
+
+        let ret = self.parse_return_type()?.map(Box::new);
+        match ret {
+          None if params.is_empty() => Ok(Type::Unit),
+          // (T) alone is a setter, tuples have at least two items or a trailing comma
+          None if params.len() > 1 || trailing => Ok(Type::Tuple(params)),
+          ret => Ok(Type::Fn { params, ret }),
+        }
+      }
+      TokenKind::OpenBracket => {
+        self.eat();
+        let item = Box::new(self.parse_type()?);
+        self.expect(TokenKind::Comma)?;
+        let size = self.expect(TokenKind::Number)?.value;
+        let size = size.parse::<usize>()
+          .map_err(|_| ParseError { message: format!("invalid array size {:?}", size) })?;
+        self.expect(TokenKind::CloseBracket)?;
+        Ok(Type::Array { item, size })
+      }
+      TokenKind::Struct => {
+        self.eat();
+        Ok(Type::Struct { fields: self.parse_fields()? })
+      }
+      TokenKind::Identifier => {
+        let name = Symbol::from(self.eat());
+        let mut args = Vec::new();
+        if self.at().kind == TokenKind::Lt {
+          self.eat();
+          while self.at().kind != TokenKind::Gt {
+            args.push(self.parse_type()?);
+            if self.at().kind != TokenKind::Comma {
+              break;
+            }
+            self.eat();
+          }
+          self.expect(TokenKind::Gt)?;
+        }
+        Ok(Type::Named { name, args })
+      }
+      _ => Err(ParseError { message: format!("expected type, found {:?} at {}", self.at().kind, self.at().span) })
+    }
+  }
+
+  // pub (FN | TYPE | LET | CONST | EXTERN)
+  fn parse_public_declaration(&self) -> ParseResult {
+    let span = self.eat().span;
+    let mut declaration = self.parse_statement()?;
+    match &mut declaration {
+      Expr::FnDecl { public, .. }
+      | Expr::TypeDecl { public, .. }
+      | Expr::VarDecl { public, .. }
+      | Expr::ExternDecl { public, .. } => *public = true,
+      _ => return ParseError { message: format!("only declarations can be `pub` at {}", span) }.into(),
+    }
+    declaration.into()
+  }
+
+  // module IDENT[.IDENT];
+  fn parse_module_declaration(&self) -> ParseResult {
+    self.eat();
+    let mut identifier = Symbol::from(self.expect(TokenKind::Identifier)?);
+    while self.at().kind == TokenKind::Dot {
+      self.eat();
+      identifier.name = format!("{}.{}", identifier.name, self.expect(TokenKind::Identifier)?.value).into();
+    }
+    self.expect(TokenKind::Semicolon)?;
+    Expr::Module { identifier }.into()
+  }
+
+  // import IDENT[.IDENT] [as IDENT] [{IDENT [as IDENT], ...}] [;]
+  fn parse_import(&self) -> ParseResult {
+    self.eat();
+    let mut path = vec![Symbol::from(self.expect(TokenKind::Identifier)?)];
+    while self.at().kind == TokenKind::Dot {
+      self.eat();
+      path.push(Symbol::from(self.expect(TokenKind::Identifier)?));
+    }
+    let alias = self.parse_alias()?;
+
+    let items = if self.at().kind == TokenKind::OpenBrace {
+      self.eat();
+      let mut items = Vec::new();
+      while self.at().kind != TokenKind::CloseBrace {
+        let name = Symbol::from(self.expect(TokenKind::Identifier)?);
+        items.push(ImportItem { name, alias: self.parse_alias()? });
+        if self.at().kind != TokenKind::Comma {
+          break;
+        }
+        self.eat();
+      }
+      self.expect(TokenKind::CloseBrace)?;
+      Some(items)
+    } else {
+      None
+    };
+
+    if self.at().kind == TokenKind::Semicolon {
+      self.eat();
+    }
+    Expr::Import { path, alias, items }.into()
+  }
+
+  // [as IDENT]
+  fn parse_alias(&self) -> Result<Option<Symbol>, ParseError> {
+    if self.at().kind != TokenKind::As {
+      return Ok(None);
+    }
+    self.eat();
+    Ok(Some(Symbol::from(self.expect(TokenKind::Identifier)?)))
+  }
+
+  // type IDENT [<GENERICS>] (: | =) (struct{FIELDS} | enum{VARIANTS} | trait{FIELDS} | TYPE)
+  fn parse_type_declaration(&self) -> ParseResult {
+    self.eat();
+    let identifier = Symbol::from(self.expect(TokenKind::Identifier)?);
+    let generics = self.parse_generics()?;
+    match self.at().kind {
+      TokenKind::Colon | TokenKind::Equals => { self.eat(); }
+      _ => return ParseError { message: format!("expected type definition, found {:?} at {}", self.at().kind, self.at().span) }.into(),
+    }
+
+    let def = match self.at().kind {
+      TokenKind::Struct => {
+        self.eat();
+        TypeDef::Struct { fields: self.parse_fields()? }
+      }
+      TokenKind::Enum => {
+        self.eat();
+        TypeDef::Enum { variants: self.parse_variants()? }
+      }
+      TokenKind::Trait => {
+        self.eat();
+        TypeDef::Trait { methods: self.parse_fields()? }
+      }
+      _ => TypeDef::Alias(self.parse_type()?),
+    };
+    if self.at().kind == TokenKind::Semicolon {
+      self.eat();
+    }
+    Expr::TypeDecl { id: self.node_id(), public: false, identifier, generics, def }.into()
+  }
+
+  // {[pub] IDENT [, IDENT]: TYPE (; | ,) ...}
+  fn parse_fields(&self) -> Result<Vec<Field>, ParseError> {
+    self.expect(TokenKind::OpenBrace)?;
+    let mut fields = Vec::new();
+    let mut names = Vec::new();
+    let mut public = false;
+    while self.at().kind != TokenKind::CloseBrace {
+      // pub a, b: i32
+      if names.is_empty() {
+        public = self.at().kind == TokenKind::Pub;
+        if public {
+          self.eat();
+        }
+      }
+      names.push(self.expect(TokenKind::Identifier)?.value);
+      // a, b: i32
+      if self.at().kind == TokenKind::Comma {
+        self.eat();
+        continue;
+      }
+      self.expect(TokenKind::Colon)?;
+      let ty = self.parse_type()?;
+      for name in names.drain(..) {
+        fields.push(Field { name, ty: ty.clone(), public });
+      }
+      match self.at().kind {
+        TokenKind::Semicolon | TokenKind::Comma => { self.eat(); }
+        _ => break,
+      }
+    }
+    if let Some(name) = names.first() {
+      return Err(ParseError { message: format!("missing type of field {:?}", name) });
+    }
+    self.expect(TokenKind::CloseBrace)?;
+    Ok(fields)
+  }
+
+  // {IDENT [(TYPE, ...) | : TYPE] (; | ,) ...}
+  fn parse_variants(&self) -> Result<Vec<Variant>, ParseError> {
+    self.expect(TokenKind::OpenBrace)?;
+    let mut variants = Vec::new();
+    while self.at().kind != TokenKind::CloseBrace {
+      let name = self.expect(TokenKind::Identifier)?.value;
+      let mut fields = Vec::new();
+      match self.at().kind {
+        TokenKind::OpenParenthesis => {
+          self.eat();
+          while self.at().kind != TokenKind::CloseParenthesis {
+            fields.push(self.parse_type()?);
+            if self.at().kind != TokenKind::Comma {
+              break;
+            }
+            self.eat();
+          }
+          self.expect(TokenKind::CloseParenthesis)?;
+        }
+        TokenKind::Colon => {
+          self.eat();
+          fields.push(self.parse_type()?);
+        }
+        _ => {}
+      }
+      variants.push(Variant { name, fields });
+      match self.at().kind {
+        TokenKind::Semicolon | TokenKind::Comma => { self.eat(); }
+        _ => break,
+      }
+    }
+    self.expect(TokenKind::CloseBrace)?;
+    Ok(variants)
+  }
+
   fn parse_expr(&self) -> ParseResult {
     self.parse_assign_expr()
   }
 
+  // TARGET, TARGET, ... = EXPR, EXPR, ...
+  // every value is evaluated before anything is assigned: a, b = b, a
+  fn parse_multi_assign(&self, start: Span, first: Expr) -> ParseResult {
+    let mut targets = vec![(start, first)];
+    while self.at().kind == TokenKind::Comma {
+      self.eat();
+      targets.push((self.at().span, self.parse_object_expr()?));
+    }
+    self.expect(TokenKind::Equals)?;
+    for (start, target) in &targets {
+      assignable(target, *start)?;
+    }
+    let targets = targets.into_iter().map(|(_, e)| e).collect();
+    let mut values = vec![self.parse_expr()?];
+    while self.at().kind == TokenKind::Comma {
+      self.eat();
+      values.push(self.parse_expr()?);
+    }
+    // a, b = pair
+    let value = if values.len() == 1 { values.remove(0) } else { Expr::Tuple(values) };
+    Expr::AssignExpr {
+      target: Box::new(Expr::Tuple(targets)),
+      value: Box::new(value),
+    }.into()
+  }
+
   fn parse_conditional_expr(&self) -> ParseResult {
     //TODO: a == b == c => a == b && b == c
     //NOTE: maybe unwinding the parsed expr if conditional
-    let left = self.parse_add_expr()?;
+    let left = self.parse_comparison_expr()?;
     match self.at().kind {
       TokenKind::Eq => {
         self.eat();
-        let right = self.parse_add_expr()?;
+        let right = self.parse_comparison_expr()?;
         return Expr::Eq {
           left: Box::new(left),
           right: Box::new(right),
@@ -195,7 +899,7 @@ impl ProgramParser {
       }
       TokenKind::NotEq => {
         self.eat();
-        let right = self.parse_add_expr()?;
+        let right = self.parse_comparison_expr()?;
         return Expr::NotEq {
           left: Box::new(left),
           right: Box::new(right),
@@ -206,15 +910,74 @@ impl ProgramParser {
     left.into()
   }
 
+  // EXPR (< | <= | > | >=) EXPR
+  fn parse_comparison_expr(&self) -> ParseResult {
+    let left = self.parse_range_expr()?;
+    match self.at().kind {
+      TokenKind::Lt | TokenKind::LtEq | TokenKind::Gt | TokenKind::GtEq => {
+        let operator = self.eat();
+        let right = self.parse_range_expr()?;
+        Expr::BinaryExpr {
+          left: Box::new(left),
+          right: Box::new(right),
+          op: operator.value,
+          span: operator.span,
+        }.into()
+      }
+      _ => left.into()
+    }
+  }
+
+  // EXPR (.. | ..< | ..=) EXPR
+  fn parse_range_expr(&self) -> ParseResult {
+    let start = self.parse_add_expr()?;
+    if self.at().kind != TokenKind::Range {
+      return start.into();
+    }
+    let inclusive = self.eat().value != "..<";
+    let end = self.parse_add_expr()?;
+    Expr::Range {
+      start: Box::new(start),
+      end: Box::new(end),
+      inclusive,
+    }.into()
+  }
+
   fn parse_assign_expr(&self) -> ParseResult {
+    let _level = self.level()?;
+    let start = self.at().span;
     let mut target = self.parse_object_expr()?;
-    if self.at().kind == TokenKind::Equals {
-      self.eat();
-      let rhs = self.parse_assign_expr();
-      target = Expr::AssignExpr {
-        target: Box::new(target),
-        value: Box::new(rhs?),
-      };
+    match self.at().kind {
+      TokenKind::Equals => {
+        assignable(&target, start)?;
+        self.eat();
+        let rhs = self.parse_assign_expr();
+        target = Expr::AssignExpr {
+          target: Box::new(target),
+          value: Box::new(rhs?),
+        };
+      }
+      // a += b
+      TokenKind::AssignOperator => {
+        updatable(&target, start)?;
+        let operator = self.eat();
+        let op = operator.value.trim_end_matches('=').to_string();
+        let rhs = self.parse_assign_expr()?;
+        target = Expr::CompoundAssign { target: Box::new(target), value: Box::new(rhs), op, span: operator.span };
+      }
+      // a++ => a += 1
+      TokenKind::Increment | TokenKind::Decrement => {
+        updatable(&target, start)?;
+        let operator = self.eat();
+        let op = if operator.kind == TokenKind::Increment { "+" } else { "-" };
+        target = Expr::CompoundAssign {
+          target: Box::new(target),
+          value: Box::new(Expr::Number("1".to_string())),
+          op: op.to_string(),
+          span: operator.span,
+        };
+      }
+      _ => {}
     }
     target.into()
   }
@@ -229,7 +992,7 @@ impl ProgramParser {
     self.eat();
     let mut props = Vec::new();
     while self.at().kind != TokenKind::CloseBrace {
-      let identifier = Symbol { name: self.expect(TokenKind::Identifier)?.value };
+      let identifier = Symbol::from(self.expect(TokenKind::Identifier)?);
 
       if self.at().kind == TokenKind::Comma {
         self.eat();
@@ -241,7 +1004,10 @@ impl ProgramParser {
       }
       self.expect(TokenKind::Colon)?;
       let value = self.parse_expr();
-      props.push(Property { identifier, value: Some(value?) })
+      props.push(Property { identifier, value: Some(value?) });
+      if self.at().kind == TokenKind::Comma {
+        self.eat();
+      }
     }
 
     self.expect(TokenKind::CloseBrace)?;
@@ -250,74 +1016,161 @@ impl ProgramParser {
   }
 
   fn parse_statement(&self) -> ParseResult {
+    let _level = self.level()?;
     match self.at().kind {
       TokenKind::Let | TokenKind::Const => self.parse_var_declaration(),
       TokenKind::Fn => self.parse_fn_declaration(),
+      TokenKind::Extern => self.parse_extern_declaration(),
+      TokenKind::Type => self.parse_type_declaration(),
+      TokenKind::Module => self.parse_module_declaration(),
+      TokenKind::Pub => self.parse_public_declaration(),
+      TokenKind::Import => self.parse_import(),
+      TokenKind::Identifier if self.is_fn_declaration() => self.parse_fn_declaration(),
       TokenKind::If => self.parse_if_statement(),
-      TokenKind::Loop => self.parse_loop(),
+      TokenKind::Loop => self.parse_loop(None),
+      TokenKind::While => self.parse_while(None),
+      TokenKind::For => self.parse_for(None),
+      TokenKind::Label => self.parse_labeled_statement(),
       TokenKind::OpenBrace => Expr::Body { body: self.parse_statement_body()? }.into(),
       TokenKind::Break => {
-        self.eat();
-        Expr::Break.into()
+        let span = self.eat().span;
+        Expr::Break { label: self.parse_label(), span }.into()
+      }
+      TokenKind::Continue => {
+        let span = self.eat().span;
+        Expr::Continue { label: self.parse_label(), span }.into()
       }
       TokenKind::Return => {
-        self.eat();
+        let span = self.eat().span;
         Expr::Return {
           expr: Box::new(self.parse_expr()?),
+          span,
         }.into()
       }
-      _ => self.parse_expr(),
+      _ => {
+        let start = self.at().span;
+        let expr = self.parse_expr()?;
+        if self.at().kind == TokenKind::Comma {
+          return self.parse_multi_assign(start, expr);
+        }
+        expr.into()
+      }
     }
   }
 
   fn parse_primary_expr(&self) -> ParseResult {
+    let _level = self.level()?;
     let current = self.at();
     match current.kind {
       TokenKind::Number => Expr::Number(self.eat().value.clone()).into(),
       TokenKind::String => Expr::String(self.eat().value.clone()).into(),
-      TokenKind::Identifier => Expr::Identifier(Symbol { name: self.eat().value.clone() }).into(),
+      // Name{field: value, ...}
+      TokenKind::Identifier if self.peek(1).kind == TokenKind::OpenBrace && !*self.condition.lock().unwrap() => {
+        let identifier = Symbol::from(self.eat());
+        match self.parse_object_expr()? {
+          Expr::Object { props } => Expr::Struct { identifier, props }.into(),
+          e => e.into(),
+        }
+      }
+      TokenKind::Identifier => Expr::Identifier(Symbol::from(self.eat())).into(),
+      TokenKind::OpenBracket => {
+        self.eat();
+        let mut items = Vec::new();
+        while self.at().kind != TokenKind::CloseBracket {
+          items.push(self.restrict(false, || self.parse_expr())?);
+          if self.at().kind != TokenKind::Comma {
+            break;
+          }
+          self.eat();
+        }
+        self.expect(TokenKind::CloseBracket)?;
+        Expr::Vec(items).into()
+      }
+      TokenKind::OpenParenthesis if self.is_closure() => self.parse_closure(),
+      // () | (EXPR) | (EXPR, ...)
       TokenKind::OpenParenthesis => {
         self.eat();
-        let expr = self.parse_expr();
+        let mut items = Vec::new();
+        let mut tuple = false;
+        while self.at().kind != TokenKind::CloseParenthesis {
+          items.push(self.parse_expr()?);
+          if self.at().kind != TokenKind::Comma {
+            break;
+          }
+          self.eat();
+          tuple = true;
+        }
         self.expect(TokenKind::CloseParenthesis)?;
-        expr
+        if items.len() == 1 && !tuple {
+          return items.remove(0).into();
+        }
+        Expr::Tuple(items).into()
       }
-      _ => ParseError { message: format!("unknown {:?}", current) }.into()
+      _ => ParseError { message: format!("unexpected {:?} {:?} at {}", current.kind, current.value, current.span) }.into()
     }
   }
 
   fn parse_mul_expr(&self) -> ParseResult {
     let mut left = self.parse_call_member_expr()?;
-    loop {
-      match self.at().value.as_str() {
-        "*" | "/" | "%" => {
-          let op = self.eat().value.clone();
-          let right = self.parse_call_member_expr()?;
-          let copy = left.clone();
-          left = Expr::BinaryExpr {
-            left: Box::new(copy),
-            right: Box::new(right),
-            op,
-          }
-        }
-        _ => break
+    let mut levels = Vec::new();
+    while let "*" | "/" | "%" = self.at().value.as_str() {
+      levels.push(self.level()?);
+      let operator = self.eat();
+      let right = self.parse_call_member_expr()?;
+      left = Expr::BinaryExpr {
+        left: Box::new(left),
+        right: Box::new(right),
+        op: operator.value,
+        span: operator.span,
       }
     }
     left.into()
   }
 
   fn parse_call_member_expr(&self) -> ParseResult {
-    let member = self.parse_member_expr()?;
-    if self.at().kind == TokenKind::OpenParenthesis {
-      return self.parse_call_expr(member).into();
+    let mut expr = self.parse_member_expr(self.parse_primary_expr()?)?;
+    let mut levels = Vec::new();
+    loop {
+      levels.push(self.level()?);
+      match self.at().kind {
+        TokenKind::OpenParenthesis => expr = self.parse_call_expr(expr)?,
+        // a..b()..c()
+        TokenKind::Range if self.is_cascade(&expr) => {
+          self.eat();
+          let method = Symbol::from(self.expect(TokenKind::Identifier)?);
+          let args = self.parse_args()?;
+          expr = Expr::Cascade { receiver: Box::new(expr), method, args };
+        }
+        // a()?.b
+        TokenKind::Question => {
+          let span = self.eat().span;
+          expr = Expr::Try { expr: Box::new(expr), span };
+        }
+        _ => break,
+      }
+      // a().b
+      expr = self.parse_member_expr(expr)?;
     }
-    member.into()
+    expr.into()
+  }
+
+  // RECEIVER..IDENT(
+  // a literal can't be a receiver, the `..` after it is a range: 0..n()
+  fn is_cascade(&self, receiver: &Expr) -> bool {
+    self.at().value == ".."
+      && self.peek(1).kind == TokenKind::Identifier
+      && self.peek(2).kind == TokenKind::OpenParenthesis
+      && !matches!(receiver, Expr::Number(_) | Expr::String(_))
   }
 
   fn parse_call_expr(&self, caller: Expr) -> ParseResult {
+    let _level = self.level()?;
+    let span = self.at().span;
     let mut call_expr = Expr::CallExpr {
       caller: Box::new(caller),
       args: self.parse_args()?,
+      span,
+      tail: false,
     };
 
     if self.at().kind == TokenKind::OpenParenthesis {
@@ -326,19 +1179,35 @@ impl ProgramParser {
     call_expr.into()
   }
 
-  fn parse_member_expr(&self) -> ParseResult {
-    let mut object = self.parse_primary_expr()?;
+  fn parse_member_expr(&self, object: Expr) -> ParseResult {
+    let mut object = object;
+    let mut levels = Vec::new();
     while self.at().kind == TokenKind::Dot || self.at().kind == TokenKind::OpenBracket {
+      levels.push(self.level()?);
       let operator = self.eat();
-      let property: Expr;
+      let mut property: Expr;
       let computed: bool;
       if operator.kind == TokenKind::Dot {
         computed = false;
-        // get identifier
+        if !matches!(self.at().kind, TokenKind::Identifier | TokenKind::Number) {
+          return ParseError { message: format!("expected property, found {:?} at {}", self.at().kind, self.at().span) }.into();
+        }
         property = self.parse_primary_expr()?;
+        // t.0.1 is lexed as t . 0.1
+        if let Expr::Number(e) = &property {
+          if let Some((first, second)) = e.split_once('.') {
+            object = Expr::MemberExpr {
+              computed,
+              object: Box::new(object),
+              property: Box::new(Expr::Number(first.to_string())),
+              span: operator.span,
+            };
+            property = Expr::Number(second.to_string());
+          }
+        }
       } else {
         computed = true;
-        property = self.parse_expr()?;
+        property = self.restrict(false, || self.parse_expr())?;
         self.expect(TokenKind::CloseBracket)?;
       }
 
@@ -346,6 +1215,7 @@ impl ProgramParser {
         computed,
         object: Box::new(object),
         property: Box::new(property),
+        span: operator.span,
       }
     }
 
@@ -355,7 +1225,7 @@ impl ProgramParser {
 
   fn parse_args(&self) -> Result<Vec<Expr>, ParseError> {
     self.expect(TokenKind::OpenParenthesis)?;
-    let args = if self.at().kind == TokenKind::CloseParenthesis { Vec::new() } else { self.parse_args_list()? };
+    let args = if self.at().kind == TokenKind::CloseParenthesis { Vec::new() } else { self.restrict(false, || self.parse_args_list())? };
     self.expect(TokenKind::CloseParenthesis)?;
     Ok(args)
   }
@@ -371,20 +1241,70 @@ impl ProgramParser {
 
   fn parse_add_expr(&self) -> ParseResult {
     let mut left = self.parse_mul_expr()?;
-    loop {
-      match self.at().value.as_str() {
-        "+" | "-" => {
-          let op = self.eat().value.clone();
-          let right = self.parse_mul_expr()?;
-          left = Expr::BinaryExpr {
-            left: Box::new(left),
-            right: Box::new(right),
-            op,
-          }
-        }
-        _ => break
+    // a + b + c is (a + b) + c, each operator is a level of the tree
+    let mut levels = Vec::new();
+    while let "+" | "-" = self.at().value.as_str() {
+      levels.push(self.level()?);
+      let operator = self.eat();
+      let right = self.parse_mul_expr()?;
+      left = Expr::BinaryExpr {
+        left: Box::new(left),
+        right: Box::new(right),
+        op: operator.value,
+        span: operator.span,
       }
     }
     left.into()
   }
-}
\ No newline at end of file
+}
+
+/// `a = 1`, `a.b = 1`, `(a, b) = pair`, `{x, y: b} = point`, `Paint(color) = thing`,
+/// anything else is an error at [start], where [target] begins
+fn assignable(target: &Expr, start: Span) -> Result<(), ParseError> {
+  let fields = |props: &[Property]| props.iter().filter_map(|e| e.value.as_ref()).try_for_each(|e| assignable(e, start));
+  match target {
+    Expr::Identifier(_) | Expr::MemberExpr { .. } => Ok(()),
+    Expr::Tuple(targets) => targets.iter().try_for_each(|e| assignable(e, start)),
+    Expr::Object { props } | Expr::Struct { props, .. } => fields(props),
+    Expr::CallExpr { caller, args, .. } if variant(caller) => args.iter().try_for_each(|e| assignable(e, start)),
+    target => Err(ParseError { message: format!("can't assign to {} at {}", describe(target), start) }),
+  }
+}
+
+/// `a += 1`, `a.b++`, only a name or a member is updated in place
+fn updatable(target: &Expr, start: Span) -> Result<(), ParseError> {
+  match target {
+    Expr::Identifier(_) | Expr::MemberExpr { .. } => Ok(()),
+    target => Err(ParseError { message: format!("can't assign to {} at {}", describe(target), start) }),
+  }
+}
+
+/// `Paint` or `Color.Paint` as the caller of a variant
+fn variant(caller: &Expr) -> bool {
+  match caller {
+    Expr::Identifier(_) => true,
+    Expr::MemberExpr { object, property, computed: false, .. } => {
+      matches!((object.as_ref(), property.as_ref()), (Expr::Identifier(_), Expr::Identifier(_)))
+    }
+    _ => false,
+  }
+}
+
+/// what [expr] is, as an error names it
+fn describe(expr: &Expr) -> &'static str {
+  match expr {
+    Expr::Number(_) => "a number",
+    Expr::String(_) => "a string",
+    Expr::Vec(_) => "a vec",
+    Expr::Tuple(_) => "a tuple",
+    Expr::Object { .. } | Expr::Struct { .. } => "an object",
+    Expr::Range { .. } => "a range",
+    Expr::CallExpr { .. } | Expr::Cascade { .. } => "a call",
+    Expr::Closure { .. } => "a function",
+    Expr::BinaryExpr { .. } | Expr::Eq { .. } | Expr::NotEq { .. } => "an operation",
+    Expr::Try { .. } => "a `?`",
+    Expr::IfExpr { .. } => "an if",
+    Expr::Body { .. } => "a block",
+    _ => "an expression",
+  }
+}
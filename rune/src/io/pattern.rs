@@ -0,0 +1,87 @@
+use crate::io::builtins::equals;
+use crate::io::env::Env;
+use crate::io::error::RuntimeError;
+use crate::io::eval::eval;
+use crate::io::expr::{Pattern, Symbol};
+use crate::io::intern::Name;
+use crate::io::runtime::{RefContext, RuntimeValue};
+
+/// names bound by a successful [destructure], in pattern order
+pub type Bindings = Vec<(Symbol, RuntimeValue)>;
+
+/// matches [value] against [pattern], collecting the bound names into [bindings]
+/// Ok(false) when the value doesn't have the shape of the pattern
+pub fn destructure(pattern: &Pattern, value: RuntimeValue, ctx: &RefContext, bindings: &mut Bindings) -> Result<bool, RuntimeError> {
+  match (pattern, value) {
+    (Pattern::Wildcard, _) => Ok(true),
+    // `None`, a name bound to a unit variant matches that variant instead of binding
+    (Pattern::Identifier(e), value) if e.name.starts_with(char::is_uppercase) && unit_variant(e.name, ctx) => {
+      Ok(matches!(value, RuntimeValue::Enum { variant, values, .. } if variant == e.name && values.is_empty()))
+    }
+    (Pattern::Identifier(e), value) => {
+      bindings.push((e.clone(), value));
+      Ok(true)
+    }
+    (Pattern::Literal(e), value) => {
+      let literal = eval(e, &mut Env::new(ctx.clone()))?;
+      Ok(equals(&literal, &value) == Some(true))
+    }
+    (Pattern::Tuple(patterns), RuntimeValue::Tuple(values)) => {
+      if patterns.len() != values.len() {
+        return Ok(false);
+      }
+      destructure_all(patterns, values, ctx, bindings)
+    }
+    // () is also the unit value
+    (Pattern::Tuple(patterns), RuntimeValue::Never) => Ok(patterns.is_empty()),
+    (Pattern::Struct { ty, fields: patterns }, value) => {
+      let mut fields = match value {
+        RuntimeValue::Struct { ty: actual, fields } => {
+          if ty.as_ref().is_some_and(|e| e.name != actual.name) {
+            return Ok(false);
+          }
+          let module = ctx.borrow().module.clone();
+          for (field, _) in patterns {
+            actual.check_field(field.name.as_str(), &module).map_err(|e| e.at(field.span))?;
+          }
+          fields
+        }
+        RuntimeValue::Object(fields) if ty.is_none() => fields,
+        _ => return Ok(false),
+      };
+      for (field, pattern) in patterns {
+        match fields.remove(field.name.as_str()) {
+          Some(value) => if !destructure(pattern, value, ctx, bindings)? {
+            return Ok(false);
+          },
+          None => return Err(RuntimeError::name(format!("undefined property {:?}", field.name)).at(field.span)),
+        }
+      }
+      Ok(true)
+    }
+    (Pattern::Variant { ty, variant, fields: patterns }, RuntimeValue::Enum { ty: actual, variant: name, values }) => {
+      if ty.as_ref().is_some_and(|e| e.name != actual.name) || variant.name != name {
+        return Ok(false);
+      }
+      if patterns.len() != values.len() {
+        return Err(RuntimeError::value(format!("{} has {} values, found {} patterns", name, values.len(), patterns.len())).at(variant.span));
+      }
+      destructure_all(patterns, values, ctx, bindings)
+    }
+    _ => Ok(false),
+  }
+}
+
+fn destructure_all(patterns: &[Pattern], values: Vec<RuntimeValue>, ctx: &RefContext, bindings: &mut Bindings) -> Result<bool, RuntimeError> {
+  for (pattern, value) in patterns.iter().zip(values) {
+    if !destructure(pattern, value, ctx, bindings)? {
+      return Ok(false);
+    }
+  }
+  Ok(true)
+}
+
+fn unit_variant(name: Name, ctx: &RefContext) -> bool {
+  let value = ctx.borrow().get_variable(name);
+  matches!(value, Some(RuntimeValue::Enum { variant, values, .. }) if variant == name && values.is_empty())
+}
@@ -0,0 +1,617 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use crate::io::error::{ErrorKind, RuntimeError};
+use crate::io::eval::import_names;
+use crate::io::intern::Name;
+use crate::io::lexer::Span;
+use crate::io::stack;
+use crate::io::expr::{Capture, Expr, Layout, Parameter, Pattern, Property, Slot, Symbol};
+use crate::io::runtime::{RefContext, RuntimeValue};
+
+/// the variables of a function, or of the program
+struct Frame {
+  /// every name it declares, wherever in its body
+  names: HashSet<Name>,
+  /// the slots of [names], [None] for the program, its names are globals looked up by name
+  slots: Option<HashMap<Name, usize>>,
+  /// the names declared so far, and their slots
+  scope: HashMap<Name, Option<usize>>,
+  count: usize,
+  /// the slots nested functions capture
+  cells: HashSet<usize>,
+  captures: Vec<Capture>,
+  /// the upvalue of each captured (frame, slot)
+  upvalues: HashMap<(usize, usize), usize>,
+  /// the labels of the loops around the expression being resolved, a function body starts outside of any
+  loops: Vec<Option<Name>>,
+}
+
+impl Frame {
+  fn new(names: HashSet<Name>, program: bool) -> Self {
+    let slots = (!program).then(|| names.iter().cloned().enumerate().map(|(i, e)| (e, i)).collect());
+    let count = if program { 0 } else { names.len() };
+    Frame { names, slots, scope: HashMap::new(), count, cells: HashSet::new(), captures: Vec::new(), upvalues: HashMap::new(), loops: Vec::new() }
+  }
+
+  fn layout(self) -> Layout {
+    let mut cells: Vec<usize> = self.cells.into_iter().collect();
+    cells.sort();
+    Layout { slots: self.count, cells, captures: self.captures }
+  }
+}
+
+/// gives every variable of [program] its [Slot], the evaluator reads them from frames instead of looking them up by name
+/// names that are neither declared by then nor visible from [ctx] fail ahead of time
+/// calls in tail position are marked for the evaluator to make once the caller's frame is gone
+/// the program's own frame is laid out as the result
+pub fn resolve(program: &mut Expr, ctx: &RefContext) -> Result<Layout, RuntimeError> {
+  let globals = visible_names(ctx);
+  let mut resolver = Resolver { frames: Vec::new(), globals, ctx: ctx.clone() };
+  match program {
+    Expr::Program(body) => {
+      let names = resolver.declared(&[], body)?;
+      resolver.frames.push(Frame::new(names, true));
+      // externs are bound before anything runs
+      for expr in body.iter_mut() {
+        if let Expr::ExternDecl { identifier, .. } = expr {
+          resolver.declare(identifier);
+        }
+      }
+      resolver.block(body)?;
+    }
+    e => {
+      let names = resolver.declared(&[], std::slice::from_ref(e))?;
+      resolver.frames.push(Frame::new(names, true));
+      resolver.expr(e)?;
+    }
+  }
+  Ok(resolver.frames.pop().unwrap().layout())
+}
+
+struct Resolver {
+  /// innermost last, the program first
+  frames: Vec<Frame>,
+  /// the names of the context the program runs in
+  globals: HashSet<Name>,
+  ctx: RefContext,
+}
+
+impl Resolver {
+  fn frame(&mut self) -> &mut Frame {
+    self.frames.last_mut().unwrap()
+  }
+
+  /// the names a body declares, those already visible outside are assigned, not declared
+  fn declared(&self, params: &[Parameter], body: &[Expr]) -> Result<HashSet<Name>, RuntimeError> {
+    let mut outer = self.globals.clone();
+    for frame in &self.frames {
+      outer.extend(frame.names.iter().cloned());
+      outer.extend(frame.scope.keys().cloned());
+    }
+    let params: HashSet<Name> = params.iter().flat_map(|e| pattern_names(&e.pattern)).collect();
+    let mut declared = Vec::new();
+    for expr in body {
+      declarations(expr, &params, &outer, &mut declared, &mut HashSet::new())?;
+    }
+    Ok(params.into_iter().chain(declared).filter(|e| self.binds(*e)).collect())
+  }
+
+  /// `None` in a pattern matches the unit variant it names instead of binding it
+  fn binds(&self, name: Name) -> bool {
+    if !name.starts_with(char::is_uppercase) {
+      return true;
+    }
+    let value = self.ctx.borrow().get_variable(name);
+    !matches!(value, Some(RuntimeValue::Enum { variant, values, .. }) if variant == name && values.is_empty())
+  }
+
+  /// [symbol] is declared from here on
+  fn declare(&mut self, symbol: &mut Symbol) {
+    let frame = self.frame();
+    let index = match &mut frame.slots {
+      None => None,
+      Some(slots) => Some(*slots.entry(symbol.name).or_insert_with(|| {
+        frame.count += 1;
+        frame.count - 1
+      })),
+    };
+    symbol.slot = index.map(Slot::Local);
+    frame.scope.insert(symbol.name, index);
+  }
+
+  fn reference(&mut self, symbol: &mut Symbol) -> Result<(), RuntimeError> {
+    let innermost = self.frames.len() - 1;
+    let mut later = false;
+    let mut found = None;
+    for (level, frame) in self.frames.iter().enumerate().rev() {
+      if let Some(index) = frame.scope.get(&symbol.name) {
+        found = Some(index.map(|index| (level, index)));
+        break;
+      }
+      if !frame.names.contains(&symbol.name) {
+        continue;
+      }
+      if level < innermost {
+        // a function can run after the declarations that follow it
+        found = Some(frame.slots.as_ref().map(|e| (level, e[&symbol.name])));
+        break;
+      }
+      later = true;
+      // a local shadows outer names for the whole function, only the program's are looked up by name before
+      if frame.slots.is_some() {
+        break;
+      }
+    }
+    if let Some(found) = found {
+      symbol.slot = found.map(|(level, index)| self.slot(level, index));
+      return Ok(());
+    }
+    let shadowed = later && self.frames.len() > 1;
+    if !shadowed && self.globals.contains(&symbol.name) {
+      symbol.slot = None;
+      return Ok(());
+    }
+    let error = match later {
+      true => RuntimeError::name(format!("{:?} is used before its declaration", symbol.name)),
+      false => RuntimeError::name(format!("undefined {:?}", symbol.name)),
+    };
+    Err(error.at(symbol.span))
+  }
+
+  /// slot [index] of the frame at [level], as the innermost function sees it
+  fn slot(&mut self, level: usize, index: usize) -> Slot {
+    let innermost = self.frames.len() - 1;
+    match level == innermost {
+      true => Slot::Local(index),
+      false => Slot::Upvalue(self.upvalue(innermost, level, index)),
+    }
+  }
+
+  /// the upvalue of the frame at [at] for slot [index] of the enclosing frame at [level]
+  /// the functions in between capture it too, to hand it down
+  fn upvalue(&mut self, at: usize, level: usize, index: usize) -> usize {
+    if let Some(e) = self.frames[at].upvalues.get(&(level, index)) {
+      return *e;
+    }
+    let capture = match at - 1 == level {
+      true => {
+        self.frames[level].cells.insert(index);
+        Capture::Cell(index)
+      }
+      false => Capture::Upvalue(self.upvalue(at - 1, level, index)),
+    };
+    let frame = &mut self.frames[at];
+    frame.captures.push(capture);
+    frame.upvalues.insert((level, index), frame.captures.len() - 1);
+    frame.captures.len() - 1
+  }
+
+  /// an assignment declares a name of the function nothing outside declares
+  fn assign(&mut self, symbol: &mut Symbol) -> Result<(), RuntimeError> {
+    let frame = self.frame();
+    if !frame.scope.contains_key(&symbol.name) && frame.names.contains(&symbol.name) {
+      self.declare(symbol);
+      return Ok(());
+    }
+    self.reference(symbol)
+  }
+
+  /// the parser's nodes aren't shared yet, [Arc::make_mut] doesn't copy them
+  fn function(&mut self, params: &mut Arc<Vec<Parameter>>, body: &mut Arc<Expr>, layout: &mut Arc<Layout>) -> Result<(), RuntimeError> {
+    let (params, body) = (Arc::make_mut(params), Arc::make_mut(body));
+    let names = self.declared(params, std::slice::from_ref(body))?;
+    self.frames.push(Frame::new(names, false));
+    let result = self.function_body(params, body);
+    tail_calls(body);
+    *layout = Arc::new(self.frames.pop().unwrap().layout());
+    result
+  }
+
+  fn function_body(&mut self, params: &mut [Parameter], body: &mut Expr) -> Result<(), RuntimeError> {
+    for param in params.iter_mut() {
+      self.declare_pattern(&mut param.pattern)?;
+    }
+    self.expr(body)
+  }
+
+  fn declare_pattern(&mut self, pattern: &mut Pattern) -> Result<(), RuntimeError> {
+    self.pattern_literals(pattern)?;
+    let mut symbols = Vec::new();
+    bound_symbols(&|e| self.binds(e), pattern, &mut symbols);
+    for symbol in symbols {
+      self.declare(symbol);
+    }
+    Ok(())
+  }
+
+  fn pattern_literals(&mut self, pattern: &mut Pattern) -> Result<(), RuntimeError> {
+    match pattern {
+      Pattern::Literal(e) => self.expr(e),
+      Pattern::Tuple(patterns) | Pattern::Variant { fields: patterns, .. } => patterns.iter_mut().try_for_each(|e| self.pattern_literals(e)),
+      Pattern::Struct { fields, .. } => fields.iter_mut().try_for_each(|(_, e)| self.pattern_literals(e)),
+      _ => Ok(()),
+    }
+  }
+
+  fn block(&mut self, body: &mut [Expr]) -> Result<(), RuntimeError> {
+    self.hoist(body);
+    body.iter_mut().try_for_each(|e| self.expr(e))
+  }
+
+  /// functions and types are declared for the whole block
+  fn hoist(&mut self, body: &mut [Expr]) {
+    for expr in body {
+      if let Expr::FnDecl { identifier, .. } | Expr::TypeDecl { identifier, .. } = expr {
+        self.declare(identifier);
+      }
+    }
+  }
+
+  fn exprs(&mut self, items: &mut [Expr]) -> Result<(), RuntimeError> {
+    items.iter_mut().try_for_each(|e| self.expr(e))
+  }
+
+  fn properties(&mut self, props: &mut [Property]) -> Result<(), RuntimeError> {
+    for prop in props {
+      match &mut prop.value {
+        Some(e) => self.expr(e)?,
+        // {x} is {x: x}
+        None => self.reference(&mut prop.identifier)?,
+      }
+    }
+    Ok(())
+  }
+
+  fn expr(&mut self, expr: &mut Expr) -> Result<(), RuntimeError> {
+    stack::check_nesting()?;
+    match expr {
+      Expr::Program(body) | Expr::Body { body } => self.block(body),
+      Expr::VarDecl { pattern, value, .. } => {
+        self.expr(value)?;
+        self.declare_pattern(pattern)
+      }
+      Expr::FnDecl { identifier, params, body, layout, .. } => {
+        self.declare(identifier);
+        self.function(params, body, layout)
+      }
+      Expr::ExternDecl { identifier, .. } | Expr::TypeDecl { identifier, .. } => {
+        self.declare(identifier);
+        Ok(())
+      }
+      Expr::Import { path, alias, items } => {
+        // the symbols the bindings of the import are made with
+        for item in items.iter_mut().flatten() {
+          match &mut item.alias {
+            Some(alias) => self.declare(alias),
+            None => self.declare(&mut item.name),
+          }
+        }
+        match (alias, &items) {
+          (Some(alias), _) => self.declare(alias),
+          (None, None) => self.declare(path.last_mut().unwrap()),
+          (None, Some(_)) => {}
+        }
+        Ok(())
+      }
+      Expr::Closure { params, body, layout, .. } => self.function(params, body, layout),
+      Expr::AssignExpr { target, value } => {
+        self.expr(value)?;
+        self.target(target)
+      }
+      // reads [target] before storing to it, it declares nothing
+      Expr::CompoundAssign { target, value, .. } => {
+        self.expr(target)?;
+        self.expr(value)
+      }
+      Expr::BinaryExpr { left, right, .. } | Expr::Eq { left, right } | Expr::NotEq { left, right } | Expr::Range { start: left, end: right, .. } => {
+        self.expr(left)?;
+        self.expr(right)
+      }
+      Expr::MemberExpr { object, property, computed, .. } => {
+        self.expr(object)?;
+        match computed {
+          true => self.expr(property),
+          false => Ok(()),
+        }
+      }
+      Expr::CallExpr { caller, args, .. } => {
+        self.expr(caller)?;
+        self.exprs(args)
+      }
+      Expr::Cascade { receiver, args, .. } => {
+        self.expr(receiver)?;
+        self.exprs(args)
+      }
+      Expr::IfExpr { when, then, other } => {
+        match when.as_mut() {
+          // the names bound by the pattern get slots of their own, visible in [then] only
+          Expr::Let { pattern, value } => {
+            self.expr(value)?;
+            self.pattern_literals(pattern)?;
+            let mut symbols = Vec::new();
+            bound_symbols(&|e| self.binds(e), pattern, &mut symbols);
+            let mut shadowed = Vec::new();
+            for symbol in symbols {
+              let frame = self.frame();
+              frame.count += 1;
+              let index = frame.count - 1;
+              symbol.slot = Some(Slot::Local(index));
+              shadowed.push((symbol.name, frame.scope.insert(symbol.name, Some(index))));
+            }
+            self.expr(then)?;
+            for (name, previous) in shadowed.into_iter().rev() {
+              match previous {
+                Some(e) => self.frame().scope.insert(name, e),
+                None => self.frame().scope.remove(&name),
+              };
+            }
+          }
+          when => {
+            self.expr(when)?;
+            self.expr(then)?;
+          }
+        }
+        match other {
+          Some(e) => self.expr(e),
+          None => Ok(()),
+        }
+      }
+      Expr::Return { expr: value, span } => {
+        self.in_function("return", *span)?;
+        tail_calls(value);
+        self.expr(value)
+      }
+      Expr::Try { expr: value, span } => {
+        self.in_function("?", *span)?;
+        self.expr(value)
+      }
+      Expr::Let { value, .. } => self.expr(value),
+      Expr::Loop { label, body } => self.looping(label, |e| e.block(body)),
+      Expr::While { label, condition, body } => {
+        self.hoist(body);
+        self.expr(condition)?;
+        self.looping(label, |e| e.exprs(body))
+      }
+      Expr::For { label, init, condition, step, body } => {
+        if let Some(init) = init {
+          self.expr(init)?;
+        }
+        self.hoist(body);
+        if let Some(condition) = condition {
+          self.expr(condition)?;
+        }
+        self.looping(label, |e| e.exprs(body))?;
+        match step {
+          Some(step) => self.expr(step),
+          None => Ok(()),
+        }
+      }
+      Expr::ForIn { label, item, iterable, body } => {
+        self.expr(iterable)?;
+        self.declare(item);
+        self.looping(label, |e| e.block(body))
+      }
+      Expr::Break { label, span } => self.jump("break", label, *span),
+      Expr::Continue { label, span } => self.jump("continue", label, *span),
+      Expr::Identifier(e) => self.reference(e),
+      Expr::Vec(items) | Expr::Tuple(items) => self.exprs(items),
+      Expr::Object { props } => self.properties(props),
+      Expr::Struct { identifier, props } => {
+        self.reference(identifier)?;
+        self.properties(props)
+      }
+      Expr::Property(prop) => match &mut prop.value {
+        Some(e) => self.expr(e),
+        None => Ok(()),
+      },
+      Expr::Module { .. } | Expr::Error(_) | Expr::Never | Expr::String(_) | Expr::Number(_) => Ok(()),
+    }
+  }
+
+  /// [f] resolves the body of a loop labelled [label]
+  fn looping(&mut self, label: &Option<Symbol>, f: impl FnOnce(&mut Self) -> Result<(), RuntimeError>) -> Result<(), RuntimeError> {
+    self.frame().loops.push(label.as_ref().map(|e| e.name));
+    let result = f(self);
+    self.frame().loops.pop();
+    result
+  }
+
+  /// a `break` or `continue` leaves a loop of its own function, never the function itself
+  fn jump(&mut self, keyword: &str, label: &Option<Symbol>, span: Span) -> Result<(), RuntimeError> {
+    let loops = &self.frame().loops;
+    let message = match label {
+      None if loops.is_empty() => format!("{} outside of a loop", keyword),
+      Some(label) if !loops.contains(&Some(label.name)) => format!("{} '{} names no enclosing loop", keyword, label.name),
+      _ => return Ok(()),
+    };
+    Err(RuntimeError::new(ErrorKind::SyntaxError, message).at(span))
+  }
+
+  /// `return` and `?` leave the function they are in, the program isn't one
+  fn in_function(&mut self, keyword: &str, span: Span) -> Result<(), RuntimeError> {
+    match self.frame().slots {
+      Some(_) => Ok(()),
+      None => Err(RuntimeError::new(ErrorKind::SyntaxError, format!("{} outside of a function", keyword)).at(span)),
+    }
+  }
+
+  /// the place an assignment stores to
+  fn target(&mut self, target: &mut Expr) -> Result<(), RuntimeError> {
+    match target {
+      Expr::Identifier(e) if e.name == Name::WILDCARD => Ok(()),
+      Expr::Identifier(e) => self.assign(e),
+      Expr::MemberExpr { object, property, computed, .. } => {
+        self.expr(object)?;
+        if *computed {
+          self.expr(property)?;
+        }
+        // a copied value is written back to the object
+        self.target(object)
+      }
+      Expr::Tuple(targets) | Expr::CallExpr { args: targets, .. } => targets.iter_mut().try_for_each(|e| self.target(e)),
+      Expr::Object { props } | Expr::Struct { props, .. } => {
+        for prop in props {
+          match &mut prop.value {
+            Some(e) => self.target(e)?,
+            None => self.assign(&mut prop.identifier)?,
+          }
+        }
+        Ok(())
+      }
+      e => self.expr(e),
+    }
+  }
+}
+
+/// the symbols [pattern] binds, in the order [crate::io::pattern::destructure] binds them
+fn bound_symbols<'a>(binds: &dyn Fn(Name) -> bool, pattern: &'a mut Pattern, symbols: &mut Vec<&'a mut Symbol>) {
+  match pattern {
+    Pattern::Identifier(e) if binds(e.name) => symbols.push(e),
+    Pattern::Tuple(patterns) | Pattern::Variant { fields: patterns, .. } => {
+      patterns.iter_mut().for_each(|e| bound_symbols(binds, e, symbols));
+    }
+    Pattern::Struct { fields, .. } => fields.iter_mut().for_each(|(_, e)| bound_symbols(binds, e, symbols)),
+    _ => {}
+  }
+}
+
+/// marks the calls [expr] evaluates to, a function whose body is [expr] returns their value as is
+fn tail_calls(expr: &mut Expr) {
+  match expr {
+    Expr::CallExpr { tail, .. } => *tail = true,
+    Expr::Body { body } => body.last_mut().into_iter().for_each(tail_calls),
+    Expr::IfExpr { then, other, .. } => {
+      tail_calls(then);
+      other.iter_mut().for_each(|e| tail_calls(e));
+    }
+    _ => {}
+  }
+}
+
+/// every name [ctx] and its parents hold
+pub(crate) fn visible_names(ctx: &RefContext) -> HashSet<Name> {
+  let mut names = HashSet::new();
+  let mut next = Some(ctx.clone());
+  while let Some(ctx) = next {
+    let context = ctx.borrow();
+    names.extend(context.variables.keys().cloned());
+    next = context.parent.clone();
+  }
+  names
+}
+
+/// collects the names [expr] declares into [declared], in order, and the ones `if` patterns bind into [branch]
+/// an assignment only declares names neither [params] nor [outer] hold
+pub(crate) fn declarations(expr: &Expr, params: &HashSet<Name>, outer: &HashSet<Name>, declared: &mut Vec<Name>, branch: &mut HashSet<Name>) -> Result<(), RuntimeError> {
+  stack::check_nesting()?;
+  let declare = |name: Name, declared: &mut Vec<Name>| {
+    if !declared.contains(&name) {
+      declared.push(name);
+    }
+  };
+  match expr {
+    Expr::VarDecl { pattern, .. } => pattern_names(pattern).iter().for_each(|e| declare(*e, declared)),
+    Expr::FnDecl { identifier, .. } | Expr::TypeDecl { identifier, .. } | Expr::ExternDecl { identifier, .. } => {
+      declare(identifier.name, declared);
+    }
+    Expr::Import { path, alias, items } => import_names(path, alias, items).iter().for_each(|e| declare(e.name, declared)),
+    Expr::ForIn { item, .. } => declare(item.name, declared),
+    Expr::AssignExpr { target, .. } => {
+      for name in target_names(target) {
+        if !outer.contains(&name) && !params.contains(&name) {
+          declare(name, declared);
+        }
+      }
+    }
+    Expr::IfExpr { when, .. } => if let Expr::Let { pattern, .. } = when.as_ref() {
+      branch.extend(pattern_names(pattern));
+    },
+    _ => {}
+  }
+  let mut result = Ok(());
+  for_each_child(expr, &mut |e| if result.is_ok() {
+    result = declarations(e, params, outer, declared, branch);
+  });
+  result
+}
+
+/// the names an assignment to [target] stores to
+fn target_names(target: &Expr) -> Vec<Name> {
+  match target {
+    Expr::Identifier(e) if e.name == Name::WILDCARD => Vec::new(),
+    Expr::Identifier(e) => vec![e.name],
+    Expr::Tuple(targets) | Expr::CallExpr { args: targets, .. } => targets.iter().flat_map(target_names).collect(),
+    Expr::Object { props } | Expr::Struct { props, .. } => props.iter().flat_map(|e| match &e.value {
+      Some(value) => target_names(value),
+      None => vec![e.identifier.name],
+    }).collect(),
+    _ => Vec::new(),
+  }
+}
+
+pub(crate) fn pattern_names(pattern: &Pattern) -> Vec<Name> {
+  match pattern {
+    Pattern::Identifier(e) => vec![e.name],
+    Pattern::Tuple(patterns) | Pattern::Variant { fields: patterns, .. } => patterns.iter().flat_map(pattern_names).collect(),
+    Pattern::Struct { fields, .. } => fields.iter().flat_map(|(_, e)| pattern_names(e)).collect(),
+    _ => Vec::new(),
+  }
+}
+
+/// the expressions directly under [expr] evaluated as part of it, function bodies aren't
+pub(crate) fn for_each_child(expr: &Expr, f: &mut impl FnMut(&Expr)) {
+  match expr {
+    Expr::Program(body) | Expr::Body { body } | Expr::Loop { body, .. } | Expr::Vec(body) | Expr::Tuple(body) => body.iter().for_each(f),
+    Expr::VarDecl { value, .. } | Expr::Try { expr: value, .. } | Expr::Return { expr: value, .. } | Expr::Let { value, .. } => f(value),
+    Expr::BinaryExpr { left, right, .. } | Expr::Eq { left, right } | Expr::NotEq { left, right } => {
+      f(left);
+      f(right);
+    }
+    Expr::AssignExpr { target, value } | Expr::CompoundAssign { target, value, .. } => {
+      f(target);
+      f(value);
+    }
+    Expr::Range { start, end, .. } => {
+      f(start);
+      f(end);
+    }
+    Expr::MemberExpr { object, property, computed, .. } => {
+      f(object);
+      if *computed {
+        f(property);
+      }
+    }
+    Expr::CallExpr { caller, args, .. } => {
+      f(caller);
+      args.iter().for_each(f);
+    }
+    Expr::Cascade { receiver, args, .. } => {
+      f(receiver);
+      args.iter().for_each(f);
+    }
+    Expr::IfExpr { when, then, other } => {
+      f(when);
+      f(then);
+      if let Some(other) = other {
+        f(other);
+      }
+    }
+    Expr::While { condition, body, .. } => {
+      f(condition);
+      body.iter().for_each(f);
+    }
+    Expr::For { init, condition, step, body, .. } => {
+      [init, condition, step].into_iter().flatten().for_each(|e| f(e));
+      body.iter().for_each(f);
+    }
+    Expr::ForIn { iterable, body, .. } => {
+      f(iterable);
+      body.iter().for_each(f);
+    }
+    Expr::Object { props } | Expr::Struct { props, .. } => props.iter().filter_map(|e| e.value.as_ref()).for_each(f),
+    Expr::Property(prop) => if let Some(value) = &prop.value {
+      f(value);
+    },
+    _ => {}
+  }
+}
@@ -1,26 +1,79 @@
 use std::collections::HashMap;
+use std::cell::RefCell;
 use std::fmt;
 use std::fmt::Formatter;
+use std::io::Write;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::Sender;
-use crate::io::expr::{Expr, Parameter, Symbol};
+use crate::io::bytecode::Closure;
+use crate::io::collections::Table;
+use crate::io::env::Function;
+use crate::io::error::{ErrorKind, EvalResult, Frame, RuntimeError};
+use crate::io::intern::Name;
+use crate::io::lexer::Span;
+use crate::io::expr::{Symbol, TypeDef};
+use crate::io::module::{Loader, Module};
+use crate::io::native::Registry;
 
-pub type RefContext = Arc<Mutex<Context>>;
+/// an interpreter lives on one thread, its handles aren't locked, see [crate::io::shared] to pass values between threads
+pub type RefContext = Rc<RefCell<Context>>;
+pub type RefRuntime = Rc<RefCell<Runtime>>;
+pub type RefVec = Rc<RefCell<Vec<RuntimeValue>>>;
+pub type RefTable = Rc<RefCell<Table>>;
 pub type RefSignal = Arc<Sender<Signal>>;
+/// a variable of the [crate::io::vm] captured by a closure, it outlives the frame declaring it, [None] until it's assigned
+pub type Cell = Rc<RefCell<Option<RuntimeValue>>>;
 
-pub type ExternFn = fn(args: Vec<RuntimeValue>, ctx: RefContext) -> RuntimeValue;
+/// a host function, unlike a plain fn pointer it can capture state
+#[derive(Clone)]
+pub struct ExternFn(Arc<dyn Fn(Vec<RuntimeValue>, RefContext) -> EvalResult + Send + Sync>);
 
-#[derive(Debug, Clone)]
+impl ExternFn {
+  pub fn new(function: impl Fn(Vec<RuntimeValue>, RefContext) -> EvalResult + Send + Sync + 'static) -> Self {
+    ExternFn(Arc::new(function))
+  }
+
+  pub fn call(&self, args: Vec<RuntimeValue>, ctx: RefContext) -> EvalResult {
+    (self.0)(args, ctx)
+  }
+}
+
+impl fmt::Debug for ExternFn {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "ExternFn")
+  }
+}
+
+#[derive(Clone)]
 pub enum RuntimeValue {
   Never,
   Bool(bool),
   Float(f64),
   Int(i64),
   String(String),
+  /// integers from [start] up to, but excluding, [end]
+  Range { start: i64, end: i64 },
+  /// shared by reference, mutating methods are visible to every holder
+  Vec(RefVec),
+  /// shared by reference like [RuntimeValue::Vec]
+  Map(RefTable),
+  /// a [Table] whose values are all [RuntimeValue::Never]
+  Set(RefTable),
+  /// copied on assignment, like numbers
+  Tuple(Vec<RuntimeValue>),
   Object(HashMap<String, RuntimeValue>),
-  Error(String),
+  /// a declared `type`, holds the constructors of its values
+  Type(Arc<TypeInfo>),
+  Struct { ty: Arc<TypeInfo>, fields: HashMap<String, RuntimeValue> },
+  Enum { ty: Arc<TypeInfo>, variant: String, values: Vec<RuntimeValue> },
+  /// bound by `import name;`, members are the module's top level declarations
+  Module(Rc<Module>),
   ExternFn(ExternFn),
-  Fn { identifier: Symbol, params: Vec<Parameter>, body: Box<Expr>, decl_ctx: RefContext },
+  /// a function of the tree-walker
+  Fn(Rc<Function>),
+  /// a function compiled for the [crate::io::vm]
+  Closure(Rc<Closure>),
 
   /// investigate if we can move one level higher ( potentially at the state machine level )
   Signal(Signal),
@@ -28,17 +81,110 @@ pub enum RuntimeValue {
 
 impl fmt::Display for RuntimeValue {
   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-    match self.to_owned() {
-      RuntimeValue::Never => write!(f, "never"),
-      RuntimeValue::Bool(e) => write!(f, "{:?}", e),
-      RuntimeValue::Float(e) => write!(f, "{:?}", e),
-      RuntimeValue::Int(e) => write!(f, "{:?}", e),
-      RuntimeValue::String(e) => write!(f, "{:?}", e),
-      RuntimeValue::Object(_) => write!(f, "object"),
-      RuntimeValue::Error(e) => write!(f, "{:?}", e),
-      RuntimeValue::ExternFn(_) => write!(f, "external_fn"),
-      RuntimeValue::Fn { identifier, .. } => write!(f, "{:?}", identifier),
-      RuntimeValue::Signal(e) => write!(f, "signal::{:?}", e),
+    f.write_str(&show(self, &mut Vec::new()))
+  }
+}
+
+/// the same text as [fmt::Display], `println` shows its arguments this way
+impl fmt::Debug for RuntimeValue {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str(&show(self, &mut Vec::new()))
+  }
+}
+
+/// the text of [value], [path] holds the collections being shown, one that contains itself shows as `[...]` or `{...}` there
+fn show(value: &RuntimeValue, path: &mut Vec<*const ()>) -> String {
+  let list = |items: &[RuntimeValue], path: &mut Vec<*const ()>| items.iter().map(|e| show(e, path)).collect::<Vec<_>>().join(", ");
+  match value {
+    RuntimeValue::Never => "never".to_string(),
+    RuntimeValue::Bool(e) => format!("{:?}", e),
+    RuntimeValue::Float(e) => format!("{:?}", e),
+    RuntimeValue::Int(e) => format!("{:?}", e),
+    RuntimeValue::String(e) => format!("{:?}", e),
+    RuntimeValue::Range { start, end } => format!("{}..<{}", start, end),
+    RuntimeValue::Vec(e) if path.contains(&(Rc::as_ptr(e) as *const ())) => "[...]".to_string(),
+    RuntimeValue::Vec(e) => {
+      path.push(Rc::as_ptr(e) as *const ());
+      let items = list(&e.borrow(), path);
+      path.pop();
+      format!("[{}]", items)
+    }
+    RuntimeValue::Map(e) if path.contains(&(Rc::as_ptr(e) as *const ())) => "{...}".to_string(),
+    RuntimeValue::Map(e) => {
+      path.push(Rc::as_ptr(e) as *const ());
+      let entries: Vec<String> = e.borrow().entries().map(|(k, v)| format!("{}: {}", k, show(v, path))).collect();
+      path.pop();
+      format!("{{{}}}", entries.join(", "))
+    }
+    RuntimeValue::Set(e) if path.contains(&(Rc::as_ptr(e) as *const ())) => "{...}".to_string(),
+    RuntimeValue::Set(e) => {
+      path.push(Rc::as_ptr(e) as *const ());
+      let keys: Vec<String> = e.borrow().keys().map(|e| show(&RuntimeValue::from(e.clone()), path)).collect();
+      path.pop();
+      format!("{{{}}}", keys.join(", "))
+    }
+    RuntimeValue::Tuple(e) if e.len() == 1 => format!("({},)", show(&e[0], path)),
+    RuntimeValue::Tuple(e) => format!("({})", list(e, path)),
+    RuntimeValue::Object(_) => "object".to_string(),
+    RuntimeValue::Type(e) => e.name.clone(),
+    RuntimeValue::Struct { ty, fields } => {
+      let fields: Vec<String> = ty.field_names().iter()
+        .filter_map(|name| fields.get(name).map(|value| format!("{}: {}", name, show(value, path))))
+        .collect();
+      format!("{}{{{}}}", ty.name, fields.join(", "))
+    }
+    RuntimeValue::Enum { variant, values, .. } if values.is_empty() => variant.clone(),
+    RuntimeValue::Enum { variant, values, .. } => format!("{}({})", variant, list(values, path)),
+    RuntimeValue::Module(e) => format!("module {}", e.name),
+    RuntimeValue::ExternFn(_) => "external_fn".to_string(),
+    RuntimeValue::Fn(e) => match &e.identifier {
+      Some(identifier) => identifier.name.to_string(),
+      None => "closure".to_string(),
+    },
+    RuntimeValue::Closure(e) => match &e.proto.name {
+      Some(identifier) => identifier.name.to_string(),
+      None => "closure".to_string(),
+    },
+    RuntimeValue::Signal(e) => format!("signal::{:?}", e),
+  }
+}
+
+/// runtime side of a `type` declaration, values of the type point back to it
+#[derive(Debug)]
+pub struct TypeInfo {
+  pub name: String,
+  pub def: TypeDef,
+  /// declaring module, [None] for the program itself
+  pub module: Option<String>,
+}
+
+impl TypeInfo {
+  /// declared field names of a struct, in order
+  pub fn field_names(&self) -> Vec<String> {
+    match &self.def {
+      TypeDef::Struct { fields } => fields.iter().map(|e| e.name.clone()).collect(),
+      _ => Vec::new(),
+    }
+  }
+
+  /// private fields are only accessible from the declaring module
+  pub fn check_field(&self, field: &str, from: &Option<String>) -> Result<(), RuntimeError> {
+    if self.module == *from {
+      return Ok(());
+    }
+    match &self.def {
+      TypeDef::Struct { fields } if fields.iter().any(|e| e.name == field && !e.public) => {
+        Err(RuntimeError::new(ErrorKind::AccessError, format!("field {:?} of {} is private to module {}", field, self.name, module_name(&self.module))))
+      }
+      _ => Ok(()),
+    }
+  }
+
+  /// number of values carried by [variant], [None] if the type has no such variant
+  pub fn variant_arity(&self, variant: &str) -> Option<usize> {
+    match &self.def {
+      TypeDef::Enum { variants } => variants.iter().find(|e| e.name == variant).map(|e| e.fields.len()),
+      _ => None,
     }
   }
 }
@@ -47,12 +193,94 @@ impl fmt::Display for RuntimeValue {
 #[derive(Debug, Clone)]
 pub enum Signal {
   //Exit,
-  Break,
+  Break(Option<Symbol>),
+  Continue(Option<Symbol>),
   Return(Box<RuntimeValue>),
+  /// a call in tail position, made by the caller of the function once its frame is gone
+  TailCall { callee: Box<RuntimeValue>, args: Vec<RuntimeValue>, span: Option<Span> },
+}
+
+/// what runs programs, both give the same results
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backend {
+  /// evaluates the syntax tree directly
+  #[default]
+  TreeWalker,
+  /// compiles to bytecode first, see [crate::io::vm]
+  Vm,
+}
+
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 512;
+/// half of the stack rust gives the threads it spawns, the rest is left to natives and the host
+pub const DEFAULT_MAX_STACK: usize = 1024 * 1024;
+
+/// where a program writes, the streams of the host process unless an embedder redirects them
+#[derive(Clone)]
+pub struct Output(Arc<Mutex<dyn Write + Send>>);
+
+impl Output {
+  pub fn new(sink: impl Write + Send + 'static) -> Self {
+    Output(Arc::new(Mutex::new(sink)))
+  }
+
+  pub fn write_line(&self, line: &str) {
+    let mut sink = self.0.lock().unwrap();
+    // a closed sink drops output, it doesn't fail the program
+    let _ = writeln!(sink, "{}", line).and_then(|_| sink.flush());
+  }
+}
+
+impl fmt::Debug for Output {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    write!(f, "Output")
+  }
+}
+
+/// interpreter wide state, shared by every [Context] of a program
+#[derive(Debug)]
+pub struct Runtime {
+  /// calls deeper than this raise a "stack overflow" instead of exhausting the host stack
+  pub max_call_depth: usize,
+  /// bytes of the host stack the calls of scripts may take, past it they raise a "stack overflow" too
+  /// measured from the outermost call on the thread, a host running scripts on a thread with a larger stack can raise it
+  pub max_stack: usize,
+  pub backend: Backend,
+  /// calls of script functions in progress, innermost last
+  pub stack: Vec<Frame>,
+  pub loader: Loader,
+  /// host functions `extern` declarations bind to
+  pub natives: Registry,
+  pub stdout: Output,
+  pub stderr: Output,
 }
 
+impl Default for Runtime {
+  fn default() -> Self {
+    Runtime {
+      max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+      max_stack: DEFAULT_MAX_STACK,
+      backend: Backend::default(),
+      stack: Vec::new(),
+      loader: Loader::default(),
+      natives: Registry::default(),
+      stdout: Output::new(std::io::stdout()),
+      stderr: Output::new(std::io::stderr()),
+    }
+  }
+}
+
+/// the globals of the program or of a module, looked up by name
+/// the variables of functions live in the frames of a [crate::io::env::Env]
 #[derive(Debug)]
 pub struct Context {
   pub parent: Option<RefContext>,
-  pub variables: HashMap<String, RuntimeValue>,
+  pub variables: HashMap<Name, RuntimeValue>,
+  pub runtime: RefRuntime,
+  /// module whose code runs in this context, [None] for the program itself
+  pub module: Option<String>,
+}
+
+/// how diagnostics refer to a module
+pub fn module_name(module: &Option<String>) -> &str {
+  module.as_deref().unwrap_or("main")
 }
\ No newline at end of file
@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use crate::io::builtins::new_vec;
+use crate::io::collections::{Key, Table};
+use crate::io::error::RuntimeError;
+use crate::io::gc;
+use crate::io::native::{FromValue, IntoValue};
+use crate::io::runtime::{RuntimeValue, TypeInfo};
+
+/// a copy of a [RuntimeValue] that can be sent to another thread and turned back into a value there
+///
+/// an interpreter and its values stay on the thread running it, this is how threads exchange data:
+/// copy a value with [SharedValue::new], send it, then hand it to the other interpreter with [IntoValue]
+/// functions and modules belong to the interpreter that made them and don't convert
+#[derive(Debug, Clone)]
+pub enum SharedValue {
+  Never,
+  Bool(bool),
+  Float(f64),
+  Int(i64),
+  String(String),
+  Range { start: i64, end: i64 },
+  Vec(Vec<SharedValue>),
+  /// in insertion order
+  Map(Vec<(Key, SharedValue)>),
+  Set(Vec<Key>),
+  Tuple(Vec<SharedValue>),
+  Object(HashMap<String, SharedValue>),
+  /// declarations are immutable, every copy refers to the same one
+  Type(Arc<TypeInfo>),
+  Struct { ty: Arc<TypeInfo>, fields: HashMap<String, SharedValue> },
+  Enum { ty: Arc<TypeInfo>, variant: String, values: Vec<SharedValue> },
+}
+
+impl SharedValue {
+  /// a deep copy of [value], vectors and maps shared by several holders are copied for each of them
+  pub fn new(value: &RuntimeValue) -> Result<SharedValue, RuntimeError> {
+    copy(value, &mut Vec::new())
+  }
+}
+
+/// [path] holds the collections being copied, one that contains itself can't be
+fn copy(value: &RuntimeValue, path: &mut Vec<*const ()>) -> Result<SharedValue, RuntimeError> {
+  let shared = match value {
+    RuntimeValue::Never => SharedValue::Never,
+    RuntimeValue::Bool(e) => SharedValue::Bool(*e),
+    RuntimeValue::Float(e) => SharedValue::Float(*e),
+    RuntimeValue::Int(e) => SharedValue::Int(*e),
+    RuntimeValue::String(e) => SharedValue::String(e.clone()),
+    RuntimeValue::Range { start, end } => SharedValue::Range { start: *start, end: *end },
+    RuntimeValue::Vec(e) => {
+      enter(Rc::as_ptr(e) as *const (), path)?;
+      let items = e.borrow().iter().map(|e| copy(e, path)).collect::<Result<_, _>>()?;
+      path.pop();
+      SharedValue::Vec(items)
+    }
+    RuntimeValue::Map(e) => {
+      enter(Rc::as_ptr(e) as *const (), path)?;
+      let entries = e.borrow().entries().map(|(k, v)| Ok((k.clone(), copy(v, path)?))).collect::<Result<_, RuntimeError>>()?;
+      path.pop();
+      SharedValue::Map(entries)
+    }
+    RuntimeValue::Set(e) => SharedValue::Set(e.borrow().keys().cloned().collect()),
+    RuntimeValue::Tuple(e) => SharedValue::Tuple(e.iter().map(|e| copy(e, path)).collect::<Result<_, _>>()?),
+    RuntimeValue::Object(e) => SharedValue::Object(copy_fields(e, path)?),
+    RuntimeValue::Type(e) => SharedValue::Type(e.clone()),
+    RuntimeValue::Struct { ty, fields } => SharedValue::Struct { ty: ty.clone(), fields: copy_fields(fields, path)? },
+    RuntimeValue::Enum { ty, variant, values } => SharedValue::Enum {
+      ty: ty.clone(),
+      variant: variant.clone(),
+      values: values.iter().map(|e| copy(e, path)).collect::<Result<_, _>>()?,
+    },
+    e => return Err(RuntimeError::type_error(format!("{} can't be shared with another thread", e))),
+  };
+  Ok(shared)
+}
+
+fn copy_fields(fields: &HashMap<String, RuntimeValue>, path: &mut Vec<*const ()>) -> Result<HashMap<String, SharedValue>, RuntimeError> {
+  fields.iter().map(|(k, v)| Ok((k.clone(), copy(v, path)?))).collect()
+}
+
+fn enter(collection: *const (), path: &mut Vec<*const ()>) -> Result<(), RuntimeError> {
+  if path.contains(&collection) {
+    return Err(RuntimeError::value("a collection containing itself can't be shared with another thread"));
+  }
+  path.push(collection);
+  Ok(())
+}
+
+/// a value of the interpreter on the receiving thread, collections are new and owned by it
+impl IntoValue for SharedValue {
+  const TYPE: &'static str = "shareable";
+
+  fn into_value(self) -> RuntimeValue {
+    match self {
+      SharedValue::Never => RuntimeValue::Never,
+      SharedValue::Bool(e) => RuntimeValue::Bool(e),
+      SharedValue::Float(e) => RuntimeValue::Float(e),
+      SharedValue::Int(e) => RuntimeValue::Int(e),
+      SharedValue::String(e) => RuntimeValue::String(e),
+      SharedValue::Range { start, end } => RuntimeValue::Range { start, end },
+      SharedValue::Vec(e) => new_vec(e.into_iter().map(IntoValue::into_value).collect()),
+      SharedValue::Map(e) => {
+        let mut table = Table::default();
+        for (k, v) in e {
+          table.insert(k, v.into_value());
+        }
+        RuntimeValue::Map(gc::alloc(RefCell::new(table)))
+      }
+      SharedValue::Set(e) => {
+        let mut table = Table::default();
+        for k in e {
+          table.insert(k, RuntimeValue::Never);
+        }
+        RuntimeValue::Set(gc::alloc(RefCell::new(table)))
+      }
+      SharedValue::Tuple(e) => RuntimeValue::Tuple(e.into_iter().map(IntoValue::into_value).collect()),
+      SharedValue::Object(e) => RuntimeValue::Object(e.into_iter().map(|(k, v)| (k, v.into_value())).collect()),
+      SharedValue::Type(e) => RuntimeValue::Type(e),
+      SharedValue::Struct { ty, fields } => RuntimeValue::Struct { ty, fields: fields.into_iter().map(|(k, v)| (k, v.into_value())).collect() },
+      SharedValue::Enum { ty, variant, values } => RuntimeValue::Enum { ty, variant, values: values.into_iter().map(IntoValue::into_value).collect() },
+    }
+  }
+}
+
+/// natives receive a copy they can send to another thread
+impl FromValue for SharedValue {
+  const TYPE: &'static str = "shareable";
+
+  fn from_value(value: RuntimeValue) -> Option<Self> {
+    SharedValue::new(&value).ok()
+  }
+}
@@ -0,0 +1,49 @@
+use std::cell::Cell;
+use crate::io::error::{ErrorKind, RuntimeError};
+
+thread_local! {
+  /// the address of the host stack the outermost entry of the thread started at, and how many bytes past it it may take
+  static BASE: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// marks where the interpreter starts using the host stack, the outermost one of the thread clears it on return
+pub(crate) struct StackBase(bool);
+
+impl StackBase {
+  /// [limit] bytes of the stack from here on, an enclosing base keeps its own
+  pub(crate) fn enter(limit: usize) -> Self {
+    StackBase(BASE.with(|e| e.get().is_none() && {
+      e.set(Some((address(), limit)));
+      true
+    }))
+  }
+}
+
+impl Drop for StackBase {
+  fn drop(&mut self) {
+    if self.0 {
+      BASE.with(|e| e.set(None));
+    }
+  }
+}
+
+fn address() -> usize {
+  let marker = 0u8;
+  std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// bytes of the host stack taken since the outermost [StackBase] of the thread, none outside of one
+pub(crate) fn used() -> usize {
+  BASE.with(|e| e.get()).map_or(0, |(base, _)| base.saturating_sub(address()))
+}
+
+/// checked on every recursive entry of the parser, resolver, compiler and evaluator,
+/// source nested deeper than the stack allows is an error instead of a crash of the host: `((((1))))`
+pub(crate) fn check_nesting() -> Result<(), RuntimeError> {
+  match BASE.with(|e| e.get()) {
+    Some((base, limit)) if base.saturating_sub(address()) > limit => {
+      Err(RuntimeError::new(ErrorKind::StackOverflow, format!("nested too deeply, exceeded {} bytes of host stack", limit)))
+    }
+    _ => Ok(()),
+  }
+}
@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::io::builtins::{call_method, iterate, unwrap};
+use crate::io::bytecode::{BindKind, Closure, Op, Proto};
+use crate::io::compiler::compile;
+use crate::io::env::Env;
+use crate::io::error::{EvalResult, Frame, RuntimeError};
+use crate::io::eval::{assign_index, binary_operation, call_at, check_stack, condition, equality, get_index, get_property, import_bindings, new_range, new_struct, new_type, resolve_extern};
+use crate::io::gc;
+use crate::io::intern::Name;
+use crate::io::expr::{Capture, Expr, Pattern, Symbol};
+use crate::io::lexer::Span;
+use crate::io::pattern::{destructure, Bindings};
+use crate::io::runtime::{Cell, RefContext, RefRuntime, RuntimeValue};
+
+/// a call of a [Closure] in progress
+struct CallFrame {
+  closure: Rc<Closure>,
+  ip: usize,
+  /// [None] until assigned
+  locals: Vec<Option<RuntimeValue>>,
+  cells: Vec<Cell>,
+  iterators: Vec<Box<dyn Iterator<Item=RuntimeValue>>>,
+  /// height of the operand stack when the frame was entered
+  base: usize,
+  /// where it was called from, [None] for a program, which isn't a call on [crate::io::runtime::Runtime::stack]
+  call: Option<Option<Span>>,
+}
+
+/// runs the bytecode of the [crate::io::compiler], the calls between closures don't grow the host stack
+struct Vm {
+  stack: Vec<RuntimeValue>,
+  frames: Vec<CallFrame>,
+  runtime: RefRuntime,
+}
+
+/// compiles and runs [program] in [ctx], like [crate::io::eval::eval] does
+pub fn run(program: &Expr, ctx: RefContext) -> EvalResult {
+  let proto = compile(program, ctx.clone());
+  let closure = gc::alloc(Closure { proto: Rc::new(proto), upvalues: Vec::new(), globals: ctx.clone() });
+  let mut vm = Vm::new(&ctx);
+  let locals = vec![None; closure.proto.locals.len()];
+  let cells = new_cells(closure.proto.cells.len());
+  vm.frames.push(CallFrame { closure, ip: 0, locals, cells, iterators: Vec::new(), base: 0, call: None });
+  vm.execute()
+}
+
+/// calls [closure] from the call site at [span]
+pub fn call(closure: Rc<Closure>, args: Vec<RuntimeValue>, span: Option<Span>) -> EvalResult {
+  let mut vm = Vm::new(&closure.globals);
+  vm.enter(closure, args, span)?;
+  vm.execute()
+}
+
+/// the value of a variable, a NameError like the tree-walker raises when it wasn't assigned yet
+fn assigned(value: Option<RuntimeValue>, name: Option<Name>) -> Result<RuntimeValue, RuntimeError> {
+  match (value, name) {
+    (Some(value), _) => Ok(value),
+    (None, Some(name)) => Err(RuntimeError::name(format!("undefined {:?}", name))),
+    (None, None) => Ok(RuntimeValue::Never),
+  }
+}
+
+fn new_cells(count: usize) -> Vec<Cell> {
+  (0..count).map(|_| gc::alloc(RefCell::new(None))).collect()
+}
+
+impl Vm {
+  fn new(ctx: &RefContext) -> Self {
+    let runtime = ctx.borrow().runtime.clone();
+    Vm { stack: Vec::new(), frames: Vec::new(), runtime }
+  }
+
+  fn enter(&mut self, closure: Rc<Closure>, args: Vec<RuntimeValue>, span: Option<Span>) -> Result<(), RuntimeError> {
+    let proto = &closure.proto;
+    let function = proto.name.clone().unwrap_or_else(|| Symbol::new("closure", Span::default()));
+    if args.len() != proto.arity {
+      return Err(RuntimeError::arity(function.name.as_str(), proto.arity, args.len()).at_opt(span));
+    }
+    {
+      let mut runtime = self.runtime.borrow_mut();
+      check_stack(&runtime, span)?;
+      runtime.stack.push(Frame { function, span, module: proto.module.clone() });
+    }
+
+    let mut locals: Vec<Option<RuntimeValue>> = args.into_iter().map(Some).collect();
+    locals.resize(proto.locals.len(), None);
+    let cells = new_cells(proto.cells.len());
+    self.frames.push(CallFrame { closure, ip: 0, locals, cells, iterators: Vec::new(), base: self.stack.len(), call: Some(span) });
+    Ok(())
+  }
+
+  /// leaves the innermost frame with [value], the result of the run once the first frame returns
+  fn leave(&mut self, value: RuntimeValue) -> Option<RuntimeValue> {
+    self.discard();
+    if self.frames.is_empty() {
+      return Some(value);
+    }
+    self.stack.push(value);
+    None
+  }
+
+  /// drops the innermost frame and what it left on the stack
+  fn discard(&mut self) {
+    let frame = self.frames.pop().unwrap();
+    if frame.call.is_some() {
+      self.runtime.borrow_mut().stack.pop();
+    }
+    self.stack.truncate(frame.base);
+  }
+
+  /// a [tail] call of a closure runs in place of the innermost frame, recursing through them takes no room
+  fn call_value(&mut self, callee: RuntimeValue, args: Vec<RuntimeValue>, span: Option<Span>, tail: bool) -> Result<(), RuntimeError> {
+    match callee {
+      RuntimeValue::Closure(closure) => {
+        if tail {
+          self.discard();
+        }
+        self.enter(closure, args, span)
+      }
+      callee => {
+        let globals = self.frames.last().unwrap().closure.globals.clone();
+        let value = call_at(callee, args, span, &mut Env::new(globals))?;
+        self.stack.push(value);
+        Ok(())
+      }
+    }
+  }
+
+  fn execute(&mut self) -> EvalResult {
+    loop {
+      match self.step() {
+        Ok(Some(value)) => return Ok(value),
+        Ok(None) => {}
+        Err(error) => return Err(self.unwind(error)),
+      }
+    }
+  }
+
+  /// drops every frame, an error is located at the failing instruction, then at the calls it leaves
+  fn unwind(&mut self, error: RuntimeError) -> RuntimeError {
+    let mut error = match self.frames.last() {
+      Some(frame) => error.at_opt(frame.closure.proto.chunk.spans[frame.ip - 1]),
+      None => error,
+    };
+    let mut runtime = self.runtime.borrow_mut();
+    // the innermost frame an error leaves records the whole stack
+    if error.trace.is_empty() {
+      error = error.with_trace(&runtime.stack);
+    }
+    while let Some(frame) = self.frames.pop() {
+      if let Some(span) = frame.call {
+        error = error.at_opt(span);
+        runtime.stack.pop();
+      }
+    }
+    self.stack.clear();
+    error
+  }
+
+  fn pop(&mut self) -> RuntimeValue {
+    self.stack.pop().unwrap()
+  }
+
+  fn pop_n(&mut self, n: usize) -> Vec<RuntimeValue> {
+    self.stack.split_off(self.stack.len() - n)
+  }
+
+  /// pushes the values of [names] last to first, the first ends on top
+  fn push_bindings(&mut self, names: &[Name], bindings: Bindings) {
+    for name in names.iter().rev() {
+      let value = bindings.iter().rev().find(|(e, _)| e.name == *name).map(|(_, e)| e.clone());
+      self.stack.push(value.unwrap_or(RuntimeValue::Never));
+    }
+  }
+
+  /// runs one instruction, [Some] once the program or the called function returns
+  fn step(&mut self) -> Result<Option<RuntimeValue>, RuntimeError> {
+    let frame = self.frames.last_mut().unwrap();
+    let proto: &Proto = &frame.closure.proto;
+    let chunk = &proto.chunk;
+    let op = chunk.code[frame.ip];
+    frame.ip += 1;
+
+    match op {
+      Op::Constant(e) => self.stack.push(chunk.constants[e].clone()),
+      Op::Never => self.stack.push(RuntimeValue::Never),
+      Op::Pop => {
+        self.stack.pop();
+      }
+      Op::Dup => self.stack.push(self.stack.last().unwrap().clone()),
+      Op::Dup2 => self.stack.extend_from_within(self.stack.len() - 2..),
+      Op::Sink(n) => {
+        let value = self.pop();
+        self.stack.insert(self.stack.len() - n, value);
+      }
+      Op::GetLocal(e) => {
+        let value = assigned(frame.locals[e].clone(), proto.locals[e])?;
+        self.stack.push(value)
+      }
+      Op::SetLocal(e) => frame.locals[e] = Some(self.stack.last().unwrap().clone()),
+      Op::GetCell(e) => {
+        let value = assigned(frame.cells[e].borrow().clone(), Some(proto.cells[e]))?;
+        self.stack.push(value)
+      }
+      Op::SetCell(e) => *frame.cells[e].borrow_mut() = Some(self.stack.last().unwrap().clone()),
+      Op::GetUpvalue(e) => {
+        let value = assigned(frame.closure.upvalues[e].borrow().clone(), Some(proto.upvalues[e]))?;
+        self.stack.push(value)
+      }
+      Op::SetUpvalue(e) => *frame.closure.upvalues[e].borrow_mut() = Some(self.stack.last().unwrap().clone()),
+      Op::GetGlobal(e) => {
+        let name = chunk.names[e];
+        let value = frame.closure.globals.borrow().get_variable(name);
+        match value {
+          Some(value) => self.stack.push(value),
+          None => return Err(RuntimeError::name(format!("undefined {:?}", name))),
+        }
+      }
+      Op::SetGlobal(e) => {
+        let value = self.stack.last().unwrap().clone();
+        frame.closure.globals.borrow_mut().assign_variable(chunk.names[e], value);
+      }
+      Op::DefineGlobal(e) => {
+        let value = self.stack.last().unwrap().clone();
+        frame.closure.globals.borrow_mut().let_variable(chunk.names[e], value);
+      }
+      Op::Binary(op) => {
+        let rhs = self.pop();
+        let lhs = self.pop();
+        self.stack.push(binary_operation(lhs, rhs, op)?);
+      }
+      Op::Eq | Op::NotEq => {
+        let r = self.pop();
+        let l = self.pop();
+        self.stack.push(equality(l, r, op == Op::NotEq)?);
+      }
+      Op::Range { inclusive } => {
+        let end = self.pop();
+        let start = self.pop();
+        self.stack.push(new_range(start, end, inclusive)?);
+      }
+      Op::Jump(e) => frame.ip = e,
+      Op::JumpIfFalse(e) => {
+        if !condition(self.stack.pop().unwrap())? {
+          frame.ip = e;
+        }
+      }
+      Op::IterStart => {
+        let items = iterate(self.stack.pop().unwrap())?;
+        frame.iterators.push(items);
+      }
+      Op::IterNext(e) => match frame.iterators.last_mut().unwrap().next() {
+        Some(value) => self.stack.push(value),
+        None => frame.ip = e,
+      },
+      Op::IterEnd => {
+        frame.iterators.pop();
+      }
+      Op::Call { argc, tail } => {
+        let span = chunk.spans[frame.ip - 1];
+        let args = self.pop_n(argc);
+        let callee = self.pop();
+        self.call_value(callee, args, span, tail)?;
+      }
+      Op::Invoke { method, argc, tail } => {
+        let span = chunk.spans[frame.ip - 1];
+        let method = chunk.symbols[method].clone();
+        let globals = frame.closure.globals.clone();
+        let args = self.pop_n(argc);
+        // receiver.method(args), unless the receiver is an object holding a function with that name
+        match self.pop() {
+          RuntimeValue::Object(props) if props.contains_key(method.name.as_str()) => self.call_value(props[method.name.as_str()].clone(), args, span, tail)?,
+          RuntimeValue::Module(module) => self.call_value(module.export(method.name).map_err(|e| e.at(method.span))?, args, span, tail)?,
+          receiver => {
+            let value = call_method(receiver, method.name.as_str(), args, globals).map_err(|e| e.at(method.span))?;
+            self.stack.push(value);
+          }
+        }
+      }
+      Op::Return => {
+        let value = self.pop();
+        return Ok(self.leave(value));
+      }
+      Op::Try => {
+        let value = self.pop();
+        match unwrap(value.clone()) {
+          Some(Ok(e)) => self.stack.push(e),
+          Some(Err(e)) => return Ok(self.leave(e)),
+          None => return Err(RuntimeError::type_error(format!("? expects a Result or Maybe, found {}", value))),
+        }
+      }
+      Op::Vec(n) => {
+        let items = self.pop_n(n);
+        self.stack.push(RuntimeValue::Vec(gc::alloc(RefCell::new(items))));
+      }
+      Op::Tuple(n) => {
+        let items = self.pop_n(n);
+        self.stack.push(RuntimeValue::Tuple(items));
+      }
+      Op::Object(e) => {
+        let keys = &chunk.shapes[e];
+        let values = self.stack.split_off(self.stack.len() - keys.len());
+        let props: HashMap<String, RuntimeValue> = keys.iter().cloned().zip(values).collect();
+        self.stack.push(RuntimeValue::Object(props));
+      }
+      Op::Struct(e) => {
+        let (identifier, fields) = &chunk.structs[e];
+        let values = self.stack.split_off(self.stack.len() - fields.len());
+        let ty = self.stack.pop().unwrap();
+        let value = new_struct(ty, identifier, fields.iter().cloned().zip(values).collect(), &proto.module)?;
+        self.stack.push(value);
+      }
+      Op::GetProperty(e) => {
+        let value = self.stack.pop().unwrap();
+        self.stack.push(get_property(value, chunk.names[e].as_str(), &proto.module)?);
+      }
+      Op::GetIndex => {
+        let key = self.stack.pop().unwrap();
+        let value = self.stack.pop().unwrap();
+        self.stack.push(get_index(value, key, &proto.module)?);
+      }
+      Op::SetIndex(shared) => {
+        let key = self.stack.pop().unwrap();
+        let container = self.stack.pop().unwrap();
+        let value = self.stack.pop().unwrap();
+        let updated = assign_index(container, key, value.clone(), &proto.module)?;
+        self.stack.push(value);
+        match updated {
+          Some(e) => self.stack.push(e),
+          None => frame.ip = shared,
+        }
+      }
+      Op::Unpack(n) => match self.stack.pop().unwrap() {
+        RuntimeValue::Tuple(values) if values.len() == n => self.stack.extend(values.into_iter().rev()),
+        value => return Err(RuntimeError::value(format!("can't assign {} to {} targets", value, n))),
+      },
+      Op::Closure(e) => {
+        let proto = chunk.protos[e].clone();
+        let upvalues = proto.captures.iter().map(|capture| match capture {
+          Capture::Cell(e) => frame.cells[*e].clone(),
+          Capture::Upvalue(e) => frame.closure.upvalues[*e].clone(),
+        }).collect();
+        let closure = Closure { proto, upvalues, globals: frame.closure.globals.clone() };
+        self.stack.push(RuntimeValue::Closure(gc::alloc(closure)));
+      }
+      Op::Match(e) | Op::Bind(e, _) => {
+        let site = &chunk.sites[e];
+        let value = self.stack.pop().unwrap();
+        let mut bindings = Bindings::new();
+        let matched = destructure(&site.pattern, value.clone(), &frame.closure.globals, &mut bindings)?;
+        if let Op::Bind(_, kind) = op {
+          if !matched {
+            return Err(mismatch(&site.pattern, value, kind));
+          }
+        }
+        if matched {
+          let names = site.names.clone();
+          self.push_bindings(&names, bindings);
+        }
+        if let Op::Match(_) = op {
+          self.stack.push(RuntimeValue::Bool(matched));
+        }
+      }
+      Op::Import(e) => {
+        let import = &chunk.imports[e];
+        let bindings = import_bindings(&import.path, &import.alias, &import.items, frame.closure.globals.clone())?;
+        self.stack.extend(bindings.into_iter().rev().map(|(_, e)| e));
+      }
+      Op::Extern(e) => {
+        let (identifier, ty) = &chunk.externs[e];
+        self.stack.push(resolve_extern(identifier, ty, &frame.closure.globals)?);
+      }
+      Op::Type(e) => {
+        let (identifier, def) = &chunk.types[e];
+        self.stack.push(new_type(identifier, def.clone(), proto.module.clone()));
+      }
+      Op::Raise(e) => return Err(chunk.errors[e].clone()),
+    }
+    Ok(None)
+  }
+}
+
+/// why [value] can't be bound to [pattern]
+fn mismatch(pattern: &Pattern, value: RuntimeValue, kind: BindKind) -> RuntimeError {
+  match (kind, pattern) {
+    (BindKind::Let, _) => RuntimeError::value(format!("{} doesn't match the declared pattern", value)),
+    (BindKind::Param, _) => RuntimeError::value(format!("argument {} doesn't match its parameter", value)),
+    (BindKind::Assign, Pattern::Variant { variant, .. }) => RuntimeError::value(format!("{} doesn't match {}", value, variant.name)).at(variant.span),
+    (BindKind::Assign, _) => RuntimeError::value(format!("can't destructure {} into fields", value)),
+  }
+}
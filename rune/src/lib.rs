@@ -1,3 +1,7 @@
 pub mod io;
 
-pub mod core;
\ No newline at end of file
+pub mod core;
+pub mod engine;
+
+pub use engine::{Engine, Value};
+pub use io::error::RuntimeError;
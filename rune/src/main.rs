@@ -1,69 +1,98 @@
-use std::collections::HashMap;
 use std::env;
-use std::fs;
 use std::io::{Write};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::process::{exit};
-use rune::io::eval::{eval};
-use rune::io::lexer::{tokenize};
-use rune::io::parser::{parse};
-use rune::io::runtime::{Context, RefContext, RuntimeValue};
+use std::thread;
+use rune::{Engine, RuntimeError, Value};
+use rune::io::builtins::{error, ok};
+use rune::io::module::DEFAULT_MODULE_ROOT;
+use rune::io::runtime::{Backend, ExternFn, RuntimeValue};
+
+/// the evaluator recurses on the host stack, scripts may take half of it, see [Engine::set_max_stack]
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+/// as many calls as the stack scripts may take holds at 32KB a call, about what a small function takes in a debug build,
+/// a release build takes a few KB, deeper frames run out of stack first and fail the same way
+const MAX_CALL_DEPTH: usize = STACK_SIZE / 2 / (32 * 1024);
 
 struct Query {
-  filename: String,
+  filename: Option<String>,
+  max_call_depth: usize,
+  /// extra module roots, searched before the defaults
+  module_paths: Vec<PathBuf>,
+  backend: Backend,
+  /// runs the file on every backend and reports where they differ
+  compare: bool,
 }
 
-fn parse_query(args: &[String]) -> Option<Query> {
-  if args.len() < 2 { return None; }
-  let filename = args[1].clone();
-  Some(Query {
-    filename,
-  })
+fn parse_query(args: &[String]) -> Query {
+  let mut query = Query { filename: None, max_call_depth: MAX_CALL_DEPTH, module_paths: Vec::new(), backend: Backend::default(), compare: false };
+  let mut args = args.iter().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--max-call-depth" => query.max_call_depth = args.next().and_then(|e| e.parse().ok()).unwrap_or(MAX_CALL_DEPTH),
+      "--module-path" => query.module_paths.extend(args.next().map(PathBuf::from)),
+      "--backend" => query.backend = match args.next().map(|e| e.as_str()) {
+        Some("vm") => Backend::Vm,
+        _ => Backend::TreeWalker,
+      },
+      "--compare" => query.compare = true,
+      _ => query.filename = Some(arg.clone()),
+    }
+  }
+  query
 }
 
-fn evaluate(source: &str, ctx: RefContext) {
-  let tokens = tokenize(source);
-  let parsed = parse(tokens);
-  match parsed {
-    Ok(program) => {
-      let result = eval(program, ctx);
-      println!("> {:?}", result);
-    }
-    Err(error) => {
-      println!("{}", error.message);
-    }
+fn report(result: Result<Value, RuntimeError>) {
+  match result {
+    Ok(result) => println!("> {:?}", result),
+    Err(error) => println!("{}", error),
   }
 }
 
+/// natives behind the `extern` declarations of modules/math
+fn register_math(engine: &mut Engine) -> Result<(), RuntimeError> {
+  engine.register("math", "sqrt", "(f64) -> f64", |v: f64| v.sqrt())?;
+  engine.register("math", "pow", "(f64, f64) -> f64", |base: f64, exp: f64| base.powf(exp))?;
+  engine.register("math", "parse", "(Utf8) -> Result<f64, Utf8>", |text: String| match text.trim().parse::<f64>() {
+    Ok(e) => ok(RuntimeValue::Float(e)),
+    Err(e) => error(RuntimeValue::String(e.to_string())),
+  })?;
+
+  // xorshift, the state lives in the closure
+  let seed = Mutex::new(0x2545_f491_4f6c_dd1d_u64);
+  engine.register("math", "random", "() -> f64", move || {
+    let mut state = seed.lock().unwrap();
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    (*state >> 11) as f64 / (1u64 << 53) as f64
+  })
+}
+
 fn main() {
-  println!();
-  println!("io.repl v.0.0.1");
+  let repl = thread::Builder::new()
+    .stack_size(STACK_SIZE)
+    .spawn(run)
+    .expect("unable to start the interpreter");
+  repl.join().unwrap();
+}
 
+fn run() {
   let args: Vec<String> = env::args().collect();
   let query = parse_query(&args);
+  if query.compare {
+    exit(compare(&query));
+  }
 
+  println!();
+  println!("io.repl v.0.0.1");
 
-  let mut context = Context {
-    parent: None,
-    variables: HashMap::new(),
-  };
-  context.let_variable("pi", RuntimeValue::Float(std::f64::consts::PI));
-  context.let_variable("true", RuntimeValue::Bool(true));
-  context.let_variable("false", RuntimeValue::Bool(false));
-  context.let_variable("println", RuntimeValue::ExternFn(|args, _| {
-    println!("[extern::print] > {:?}", args);
-    RuntimeValue::Never
-  }));
-  context.let_variable("status", RuntimeValue::ExternFn(|_, ctx| {
-    ctx.lock().unwrap().variables.iter().for_each(|e| println!("{:?}", e));
-    RuntimeValue::Never
-  }));
-
-  let ctx = Arc::new(Mutex::new(context));
+  let mut engine = new_engine(&query, query.backend);
 
-  if let Some(e) = query {
-    let contents = fs::read_to_string(e.filename).expect("unable to read filename");
-    evaluate(&contents, ctx.clone());
+  if let Some(filename) = &query.filename {
+    report(engine.eval_file(filename));
   }
 
   loop {
@@ -75,7 +104,78 @@ fn main() {
 
     match line.as_str() {
       "exit\n" => exit(0),
-      e => evaluate(e, ctx.clone()),
+      e => report(engine.eval_str(e)),
+    }
+  }
+}
+
+fn new_engine(query: &Query, backend: Backend) -> Engine {
+  let mut engine = Engine::default();
+  engine.set_backend(backend);
+  engine.set_max_stack(STACK_SIZE / 2);
+  engine.set_max_call_depth(query.max_call_depth);
+  // --module-path, then modules/ next to the script, then ./modules
+  let mut roots = query.module_paths.clone();
+  if let Some(dir) = query.filename.as_deref().and_then(|e| Path::new(e).parent()) {
+    roots.push(dir.join(DEFAULT_MODULE_ROOT));
+  }
+  roots.push(PathBuf::from(DEFAULT_MODULE_ROOT));
+  engine.set_module_roots(roots);
+
+  register_math(&mut engine).unwrap_or_else(|e| panic!("invalid native signature: {}", e));
+
+  engine.register_global("status", ExternFn::new(|_, ctx| {
+    ctx.borrow().variables.iter().for_each(|e| println!("{:?}", e));
+    Ok(RuntimeValue::Never)
+  }));
+  engine
+}
+
+/// collects what a program prints
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Capture {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    self.0.lock().unwrap().extend_from_slice(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    Ok(())
+  }
+}
+
+/// runs the file with the tree walker and the vm, the exit code is 1 when their output or result differ
+fn compare(query: &Query) -> i32 {
+  let Some(filename) = &query.filename else {
+    eprintln!("--compare needs a file");
+    return 2;
+  };
+  let outputs: Vec<String> = [Backend::TreeWalker, Backend::Vm].into_iter().map(|backend| {
+    let mut engine = new_engine(query, backend);
+    let output = Capture::default();
+    engine.set_stdout(output.clone());
+    engine.set_stderr(output.clone());
+    let result = match engine.eval_file(filename) {
+      Ok(e) => format!("> {}", e),
+      Err(e) => e.to_string(),
+    };
+    let output = String::from_utf8_lossy(&output.0.lock().unwrap()).into_owned();
+    format!("{}{}\n", output, result)
+  }).collect();
+
+  let (tree, vm) = (outputs[0].lines().collect::<Vec<_>>(), outputs[1].lines().collect::<Vec<_>>());
+  match (0..tree.len().max(vm.len())).find(|i| tree.get(*i) != vm.get(*i)) {
+    None => {
+      println!("{}: same output on both backends", filename);
+      0
+    }
+    Some(line) => {
+      println!("{}: backends differ at line {}", filename, line + 1);
+      println!("  tree: {}", tree.get(line).unwrap_or(&"<end>"));
+      println!("  vm:   {}", vm.get(line).unwrap_or(&"<end>"));
+      1
     }
   }
 }
@@ -0,0 +1,8 @@
+// a script function takes exactly as many arguments as it declares
+import core { catch_panic };
+
+sum: (a, b) => a + b;
+println(sum(1, 2));
+println(catch_panic(() => sum(1)).to_string());
+println(catch_panic(() => ((a) => a)(1, 2)).to_string());
+println(sum(1, 2, 3));
@@ -0,0 +1,109 @@
+// corners where the tree walker and the vm could disagree
+import core { catch_panic };
+
+// a closure capturing a parameter and a local, both updated after it was created
+accumulate: (start) {
+  total = start;
+  step = 1;
+  add = (n) {
+    total = total + n * step;
+    total
+  };
+  step = 10;
+  add
+}
+acc = accumulate(5);
+acc(1);
+println("captured", acc(2));
+
+// closures created in a loop share the variable of the function
+adders = [];
+for i in 0..3 {
+  adders.push((x) => x + i);
+}
+println("loop closures", adders[0](100), adders[2](100));
+
+// nested functions reaching two levels out
+outer: (a) {
+  middle: (b) {
+    inner: (c) => a + b + c;
+    inner(3)
+  }
+  middle(2)
+}
+println("nested", outer(1));
+
+// recursion through a local function
+countdown: (n) {
+  go: (k) {
+    if k == 0 { return "done" }
+    go(k - 1)
+  }
+  go(n)
+}
+println("local recursion", countdown(50));
+
+// a parameter shadows the global of the same name
+shadow = "global";
+echo: (shadow) => shadow;
+println("shadow", echo("param"), shadow);
+
+// break and continue out of nested for-in loops
+found = (0, 0);
+'search: for x in 1..10 {
+  for y in 1..10 {
+    if y > x { continue 'search }
+    if x * y == 42 {
+      found = (x, y);
+      break 'search
+    }
+  }
+}
+println("found", found.to_string());
+
+// the value of a loop is its last completed iteration
+last: () {
+  for n in [1, 2, 3] { n * 2 }
+}
+println("loop value", last());
+
+// if-let bindings only live in their branch
+value = 7;
+if Some(value) = Some(1) {
+  println("branch", value);
+}
+println("after", value);
+
+// write back into a struct held by a vec
+type Point: struct{ x: i32, y: i32 };
+points = [Point{ x: 1, y: 2 }];
+point = points[0];
+point.x = 5;
+println("copy", points[0].x, point.x);
+
+// destructuring assignments
+Point{x, y} = Point{ x: 3, y: 4 };
+Some(inner) = Some("unwrapped");
+println("destructured", x, y, inner);
+
+// ? leaves the function with the error
+half: (n) {
+  if n % 2 == 0 { return Ok(n / 2) }
+  Error("odd")
+}
+quarter: (n) => Ok(half(half(n)?)?);
+println("try", quarter(8).to_string(), quarter(6).to_string());
+
+// a variable no branch assigned is undefined, not never
+maybe: (n) = {
+  if n > 0 { assigned = n } else { };
+  assigned
+}
+println("assigned", maybe(1), catch_panic(() => maybe(0)).to_string());
+
+// a runtime error inside nested calls keeps its trace
+fail: (n) {
+  if n == 0 { return [1][3] }
+  fail(n - 1)
+}
+fail(2);
@@ -0,0 +1,24 @@
+// receiver..method(args) calls the method and yields the receiver
+items = Vec( 3, 5, 6, 7, 8, 1, 2)..sort();
+println(items.to_string());
+
+log = Vec()..push("a")..push("b")..reverse();
+println(log.to_string(), log..pop().len());
+
+// README groupBy, cascading on the group instead of matching
+group_by: (items: Vec<T>, key: (item: T) -> K) -> Map<K, Vec<T>> = {
+  items.fold(Map(), (acc, item) {
+    item_key = key(item);
+    if acc.contains(item_key) == false {
+      acc[item_key] = Vec();
+    }
+    acc[item_key]..push(item);
+    acc
+  })
+}
+println(group_by(["ant", "bee", "cat", "ape"], (e) => e[0]).to_string());
+
+// a `..` after a literal is a range
+last: () => 3;
+println((0..5).to_string(), (0..items.len()).to_string(), (0..last()).to_string());
+println(items..sort(1));
@@ -0,0 +1,30 @@
+// catch_panic turns a failure into an Error the program can handle
+import core { panic, catch_panic };
+
+withdraw: (balance, amount) = {
+  if amount > balance {
+    panic("insufficient funds");
+  }
+  balance - amount
+}
+
+attempt: (amount) => catch_panic(() => withdraw(10, amount));
+
+println(attempt(3).to_string(), attempt(30).to_string());
+
+// runtime errors are contained the same way
+println(catch_panic(() => 1 / 0).to_string());
+
+if Error(message) = catch_panic(() => [1, 2][5]) {
+  println("recovered", message);
+}
+
+// the calls abandoned by a caught panic don't show up in later backtraces
+nested: (depth) = {
+  if depth == 0 {
+    panic("deep");
+  }
+  nested(depth - 1)
+}
+println(catch_panic(() => nested(3)).unwrap_or("fallback"));
+withdraw(1, 2);
@@ -0,0 +1,27 @@
+// closures capture their defining context
+make_counter: () = {
+  count = 0;
+  () {
+    count = count + 1;
+    count
+  }
+}
+
+apply: (f, value) => f(value);
+
+counter = make_counter();
+counter();
+counter();
+println(counter(), apply((x) => x * 2, 21));
+
+// captured by reference, not by value
+prefix = "hello ";
+greeter = {
+  greet: (name: Utf8) -> Utf8 => prefix + name,
+};
+prefix = "hi ";
+println(greeter.greet("io"));
+
+if (counter() == 4) {
+  println("four");
+}
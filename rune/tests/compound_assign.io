@@ -0,0 +1,30 @@
+// a compound assignment evaluates the container and key of its target once
+calls = 0;
+index: () = {
+  calls += 1;
+  0
+}
+items = [10, 20];
+items[index()] += 5;
+items[index()]++;
+items[index()] *= 2;
+println(items.to_string(), calls);
+
+// a map entry and a struct field held by a vec
+counts = Map();
+counts["a"] = 1;
+counts["a"] += 2;
+type Point: struct{ x: i32, y: i32 };
+points = [Point{x: 1, y: 2}];
+points[index()].x += 10;
+points[0].y--;
+println(counts["a"], points[0].x, points[0].y, calls);
+
+// plain names
+n = 1;
+n += 2;
+n++;
+println(n);
+
+missing = [1];
+missing[3] += 1;
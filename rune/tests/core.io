@@ -0,0 +1,8 @@
+// modules/core as written loads
+import core { panic, Result, Maybe };
+
+// its Result and Maybe declarations are the builtin types
+println(Result.Error("lost") == Error("lost"), Maybe.Some(1) == Some(1), Maybe.None == None);
+
+// panic is the extern, it halts with the message
+panic("message");
@@ -0,0 +1,24 @@
+// vectors and maps are shared, they can hold themselves
+// one shows as [...] or {...} where it repeats and compares without recursing forever
+nested = [1];
+nested.push(nested);
+println(nested.to_string());
+a = [1];
+a.push(a);
+b = [1];
+b.push(b);
+c = [2];
+c.push(c);
+pair = [c, a];
+pair.sort();
+println(a == b, a == c, a < c, pair[0] == a);
+knot = Map();
+knot.insert("self", knot);
+other = Map();
+other.insert("self", other);
+println(knot.to_string(), knot == other);
+// shared without a cycle, it shows in full each time
+shared = [0];
+println([shared, shared].to_string());
+// println shows its arguments the same way
+println(nested, knot);
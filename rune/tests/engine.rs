@@ -0,0 +1,167 @@
+use std::path::Path;
+use std::thread;
+use rune::Engine;
+use rune::io::error::ErrorKind;
+use rune::io::intern::Name;
+use rune::io::runtime::{Backend, ExternFn};
+
+/// deep recursion on a thread with the default stack is an error, not a crash of the host
+#[test]
+fn deep_recursion_on_an_ordinary_thread() {
+  let scripts = [
+    "f: (n) => 1 + f(n + 1); f(0)",
+    "f: (n) = { for i in 0..<1 { if true { while true { x = [(1, {a: 1 + f(n + 1)})]; break } } } }; f(0)",
+  ];
+  for backend in [Backend::TreeWalker, Backend::Vm] {
+    for script in scripts {
+      let kind = thread::spawn(move || {
+        let mut engine = Engine::default();
+        engine.set_backend(backend);
+        engine.eval_str(script).map(|_| ()).map_err(|e| e.kind)
+      }).join().expect("the engine took its thread down");
+      assert_eq!(kind.err(), Some(ErrorKind::StackOverflow), "{:?}: {}", backend, script);
+    }
+  }
+}
+
+/// source nested deeper than the host stack allows is an error, not a crash of the host, however deep it is
+#[test]
+fn deep_nesting_on_an_ordinary_thread() {
+  let nest = |open: &str, inner: &str, close: &str, depth: usize| format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth));
+  let mut scripts = Vec::new();
+  for depth in [300, 5000, 100000] {
+    scripts.push((depth, nest("(", "1", ")", depth)));
+    scripts.push((depth, format!("1{}", " + 1".repeat(depth))));
+    scripts.push((depth, nest("{ ", "1", " }", depth)));
+    scripts.push((depth, nest("[", "1", "]", depth)));
+    scripts.push((depth, format!("x = [1]; x{}", ".len().to_string()".repeat(depth))));
+    scripts.push((depth, nest("if true { ", "1", " }", depth)));
+  }
+  for backend in [Backend::TreeWalker, Backend::Vm] {
+    let batch = scripts.clone();
+    let results = thread::spawn(move || {
+      batch.iter().map(|(_, script)| {
+        let mut engine = Engine::default();
+        engine.set_backend(backend);
+        engine.eval_str(script).map(|_| ()).map_err(|e| e.kind)
+      }).collect::<Vec<_>>()
+    }).join().expect("the engine took its thread down");
+    // the shallowest may run, depending on how much stack a level takes in this build
+    for ((depth, script), result) in scripts.iter().zip(results).filter(|((depth, _), _)| *depth > 1000) {
+      assert!(matches!(result, Err(ErrorKind::SyntaxError | ErrorKind::StackOverflow)), "{:?} at depth {}: {:.40}", backend, depth, script);
+    }
+  }
+}
+
+/// a signature has to name the types the closure converts its arguments and result to
+#[test]
+fn register_checks_the_signature() {
+  let mut engine = Engine::default();
+  assert!(engine.register("math", "pow", "(f64, f64) -> f64", |base: f64, exp: f64| base.powf(exp)).is_ok());
+  assert!(engine.register("math", "half", "(i64) -> i64", |n: i64| n / 2).is_ok());
+  for signature in ["(i64) -> f64", "(f64, f64) -> f64", "(f64)", "Utf8"] {
+    let error = engine.register("math", "sqrt", signature, |v: f64| v.sqrt()).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::TypeError, "{}", signature);
+  }
+}
+
+/// a host panic inside a native is an error saying where it happened, the panic hook stays quiet
+#[test]
+fn contained_panic_reports_its_location() {
+  let mut engine = Engine::default();
+  engine.register_global("explode", ExternFn::new(|_, _| panic!("boom")));
+  let error = engine.eval_str("explode()").unwrap_err();
+  assert_eq!(error.kind, ErrorKind::Panic);
+  assert!(error.message.contains("boom") && error.message.contains("engine.rs"), "{}", error.message);
+}
+
+/// a `break` or `continue` only leaves a loop of its own function, anything else fails before running
+#[test]
+fn jump_outside_of_a_loop() {
+  let scripts = [
+    "g: () = { break }; g()",
+    "continue",
+    "for i in 0..3 { f: () = { continue }; f() }",
+    "'outer: loop { while true { break 'inner } }",
+    "'outer: loop { g: () = { loop { continue 'outer } }; g() }",
+  ];
+  for backend in [Backend::TreeWalker, Backend::Vm] {
+    for script in scripts {
+      let mut engine = Engine::default();
+      engine.set_backend(backend);
+      let error = engine.eval_str(script).unwrap_err();
+      assert_eq!(error.kind, ErrorKind::SyntaxError, "{:?}: {}", backend, script);
+    }
+    let mut engine = Engine::default();
+    engine.set_backend(backend);
+    let value = engine.eval_str("n = 0; 'outer: loop { while true { n = n + 1; if n < 3 { continue 'outer } break 'outer } }; n").unwrap();
+    assert_eq!(value.to_string(), "3", "{:?}", backend);
+  }
+}
+
+#[test]
+fn assign_to_what_cant_be_assigned() {
+  let scripts = [
+    ("x = 1; 5 = x", "can't assign to a number at 1:8"),
+    ("x = 1; x + 1 += 2", "can't assign to an operation at 1:8"),
+    ("a, 3 = 1, 2", "can't assign to a number at 1:4"),
+    ("(a, Some(\"b\")) = (1, Some(2))", "can't assign to a string at 1:1"),
+    ("f: () => 1; f(1).g() = 2", "can't assign to a call at 1:13"),
+    ("x = {a: 1}; x.\"a\" = 2", "expected property, found String at 1:15"),
+  ];
+  for backend in [Backend::TreeWalker, Backend::Vm] {
+    for (script, message) in scripts {
+      let mut engine = Engine::default();
+      engine.set_backend(backend);
+      let error = engine.eval_str(script).unwrap_err();
+      assert_eq!(error.kind, ErrorKind::SyntaxError, "{:?}: {}", backend, script);
+      assert!(error.message.ends_with(message), "{:?}: {}: {}", backend, script, error.message);
+    }
+  }
+}
+
+#[test]
+fn parse_errors_name_the_token_and_its_position() {
+  let scripts = [
+    ("x = 1;\npub x = 2;", "only declarations can be `pub` at 2:1"),
+    ("let (a, =) = 1;", "expected pattern, found Equals at 1:9"),
+    ("type Point 5;", "expected type definition, found Number at 1:12"),
+    ("type Point = 5;", "expected type, found Number at 1:14"),
+    ("'outer: if true {}", "expected loop after label, found If at 1:9"),
+  ];
+  for (script, message) in scripts {
+    let error = Engine::default().eval_str(script).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::SyntaxError, "{}", script);
+    assert_eq!(error.message, message, "{}", script);
+  }
+}
+
+#[test]
+fn looking_up_a_missing_global_interns_nothing() {
+  let mut engine = Engine::default();
+  assert!(engine.get_global("never_declared_anywhere").is_none());
+  assert!(engine.call_function("never_called_anywhere", vec![]).is_err());
+  assert_eq!(Name::find("never_declared_anywhere"), None);
+  assert_eq!(Name::find("never_called_anywhere"), None);
+  engine.eval_str("declared_later = 1").unwrap();
+  assert_eq!(Name::find("declared_later").map(|e| e.as_str()), Some("declared_later"));
+}
+
+/// `panic` and `catch_panic` are declared by modules/core.io, the host ships it under a module root
+#[test]
+fn panic_needs_core_under_a_module_root() {
+  let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+  let script = "import core { panic, catch_panic }; catch_panic(() => panic(\"lost\"))";
+  for backend in [Backend::TreeWalker, Backend::Vm] {
+    let mut engine = Engine::default();
+    engine.set_backend(backend);
+    engine.set_module_roots(vec![root.join("examples")]);
+    let error = engine.eval_str(script).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ImportError, "{:?}", backend);
+
+    let mut engine = Engine::default();
+    engine.set_backend(backend);
+    engine.set_module_roots(vec![root.join("modules")]);
+    assert_eq!(engine.eval_str(script).unwrap().to_string(), "Error(\"lost\")", "{:?}", backend);
+  }
+}
@@ -0,0 +1,43 @@
+// calls keep their variables in frames, closures share the ones they capture
+
+// two closures capturing the same variable see each other's updates
+pair: () {
+  let count = 0;
+  let inc = () { count = count + 1; count };
+  let get = () => count;
+  (inc, get)
+}
+let (inc, get) = pair();
+inc();
+inc();
+let (other, peek) = pair();
+other();
+println("shared", get(), peek());
+
+// captured through a function that doesn't use it
+outer: (x) {
+  middle: () {
+    inner: () => x * 2;
+    inner
+  }
+  middle()
+}
+println("through", outer(21)());
+
+// a native calls back into the script while the caller's frame is live
+base = 10;
+scale: (items) {
+  factor = 3;
+  items.map((e) => e * factor + base)
+}
+println("reentrant", scale([1, 2, 3]));
+
+// a local function calling itself
+fact: (n) {
+  go: (k) {
+    if k <= 1 { return 1 }
+    k * go(k - 1)
+  }
+  go(n)
+}
+println("local recursion", fact(10));
@@ -0,0 +1,6 @@
+// runtime errors carry a kind, the position that failed and the calls they unwound through
+ratio: (a, b) => a / b;
+average: (items) => ratio(items.fold(0, (acc, e) => acc + e), items.len());
+
+println(average([1, 2, 3]), 7 / 2.0);
+println(average([]));
@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use rune::Engine;
+use rune::io::lexer::{tokenize, Token, TokenKind};
+use rune::io::parser::parse;
+
+/// every example of the repository parses, and the modules it imports from modules/ load
+/// modules that aren't shipped there, eg: `http`, and names the shipped ones don't declare yet, eg: core's `Future`,
+/// are left to the host running the example
+#[test]
+fn examples_parse_and_their_modules_load() {
+  let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+  let mut examples: Vec<PathBuf> = fs::read_dir(root.join("examples")).unwrap()
+    .map(|e| e.unwrap().path())
+    .filter(|e| e.extension().is_some_and(|e| e == "io"))
+    .collect();
+  examples.sort();
+  assert!(!examples.is_empty(), "no examples under {}", root.join("examples").display());
+
+  let mut failures = Vec::new();
+  for example in &examples {
+    let source = fs::read_to_string(example).unwrap();
+    if let Err(e) = parse(tokenize(&source)) {
+      failures.push(format!("{}: {}", example.display(), e.message));
+      continue;
+    }
+    for import in imports(tokenize(&source)) {
+      let mut engine = Engine::default();
+      engine.set_module_roots(vec![root.join("modules")]);
+      let module = import.iter().skip(1).take_while(|e| matches!(e.kind, TokenKind::Identifier | TokenKind::Dot)).map(|e| e.value.as_str()).collect::<String>();
+      if engine.runtime().borrow().loader.resolve(&module).is_err() {
+        continue;
+      }
+      if let Err(e) = engine.eval_str(&format!("import {};", module)) {
+        failures.push(format!("{}: {}", example.display(), e));
+      }
+    }
+  }
+  assert!(failures.is_empty(), "{} of {} examples failed\n\n{}", failures.len(), examples.len(), failures.join("\n\n"));
+}
+
+/// the tokens of every `import ...;` of a program
+fn imports(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+  let mut imports = Vec::new();
+  let mut tokens = tokens.into_iter();
+  while let Some(token) = tokens.next() {
+    if token.kind != TokenKind::Import {
+      continue;
+    }
+    let mut import = vec![token];
+    for token in tokens.by_ref() {
+      let end = matches!(token.kind, TokenKind::Semicolon | TokenKind::EOF);
+      import.push(token);
+      if end {
+        break;
+      }
+    }
+    imports.push(import);
+  }
+  imports
+}
@@ -0,0 +1,4 @@
+// a module can't declare a function under the name of one of its externs
+import overload;
+
+println(overload.twice(2));
@@ -0,0 +1,5 @@
+// natives are registered by the host and imported like declarations of a module
+import math { sqrt };
+import math as m;
+
+println(sqrt(16.0), m.sqrt(2));
@@ -0,0 +1,18 @@
+// README-style function declarations
+square: (x: i32) -> i32 => x * x;
+
+sum: (a, b: i32) -> i32 = {
+  a + b
+}
+
+scale<T: Mul + Copy>: (mut value: T, factor: T) -> T {
+  value * factor
+}
+
+main: () = {
+  println(square(7), sum(2, 3), scale(1.5, 2));
+  // a function prints as its name
+  println(square, (x) => x);
+}
+
+main()
@@ -0,0 +1,49 @@
+// reference cycles are collected, memory stays flat in long loops
+
+// a local function refers to itself through the frame it was declared in
+count: (n) {
+  go: (k) {
+    if k == 0 { return 0 }
+    1 + go(k - 1)
+  }
+  go(n)
+}
+
+// a closure captures the variable holding it
+counter: () {
+  let tick = 0;
+  let next = () { tick = tick + 1; next };
+  next
+}
+
+// a vector and a map holding themselves
+knot: () {
+  let items = [];
+  items.push(items);
+  let table = Map();
+  table.insert("self", table);
+  items
+}
+
+churn: (n) {
+  for i = 0; i < n; i++ {
+    count(3);
+    counter()();
+    knot();
+  }
+  n
+}
+
+gc();
+before = heap().live;
+churn(30000);
+// collections ran on their own while the loop allocated
+println("collected on the way", heap().collections > 1);
+gc();
+println("flat", heap().live - before);
+
+// a cycle something still refers to survives, only the map knot dropped is freed
+kept = knot();
+println("freed", gc(), "kept", kept.len());
+kept = 0;
+println("freed once dropped", gc());
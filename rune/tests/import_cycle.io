@@ -0,0 +1,2 @@
+// importing a module that is still loading is an error
+import cycle.a;
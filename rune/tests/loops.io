@@ -0,0 +1,45 @@
+// while
+n = 0;
+while n < 5 {
+  n += 1;
+}
+println("while", n);
+
+// c-style for with continue
+total = 0;
+for i = 0; i < 10; i++ {
+  if i % 2 == 0 {
+    continue
+  }
+  total += i;
+}
+println("odd sum", total);
+
+// for in over the characters of a string
+letters = "";
+for c in "loop" {
+  letters = c + letters;
+}
+println("reversed", letters);
+
+// labeled break and continue
+pairs = 0;
+'outer: for x = 0; x < 5; x++ {
+  for y = 0; y < 5; y++ {
+    if y > x {
+      continue 'outer
+    }
+    if x == 4 {
+      break 'outer
+    }
+    pairs += 1;
+  }
+}
+println("pairs", pairs);
+
+count = 0;
+loop {
+  count++;
+  if count >= 3 { break }
+}
+println("loop", count);
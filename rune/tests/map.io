@@ -0,0 +1,32 @@
+// Map and Set keep insertion order and accept any hashable key
+ages = Map();
+ages.insert("ana", 31);
+ages["bob"] = 27;
+ages[7] = "seven";
+ages[true] = "yes";
+println(ages.to_string(), ages.len(), ages["bob"], ages.get("bob"), ages.get("zed"));
+
+ages.insert("ana", 32);
+println(ages.remove(7), ages.contains("ana"), ages.contains(7));
+println(ages.keys().to_string(), ages.values().to_string(), ages.entries().to_string());
+
+// README groupBy, get is Some(group) or None
+group_by: (items: Vec<T>, key: (item: T) -> K) -> Map<K, Vec<T>> = {
+  items.fold(Map(), (acc, item) {
+    item_key = key(item);
+    if let Some(group) = acc.get(item_key) {
+      group.push(item);
+    } else {
+      acc[item_key] = [item];
+    }
+    acc
+  })
+}
+println(group_by([1, 2, 3, 4, 5, 6, 7], (n) => n % 3).to_string());
+
+seen = Set(3, 1, 3, 2);
+println(seen.to_string(), seen.insert(4), seen.insert(1), seen.remove(3), seen.contains(2));
+for n in seen {
+  println(n);
+}
+println(ages[1.5]);
@@ -0,0 +1,21 @@
+// modules are looked up under modules/ next to the script
+module main;
+
+import geometry;
+import geometry as geo;
+import shapes { Circle };
+import shapes as s { Square };
+
+println(geometry.area(geometry.circle(2)), geo.area(Square(3)), Circle(1).name);
+// every import shares the module loaded the first time
+println(s.loaded, geometry == geo);
+println(s.Circle(2).area());
+
+// only `pub` declarations and fields are visible outside their module
+import geometry { Point, origin, tag };
+p = origin();
+p.x = 3;
+let Point{x, y} = p;
+println(x, y, tag(p));
+// the first error stops the script, see private_import.io, private_member.io and private_field_init.io for the other private accesses
+println(p.tag);
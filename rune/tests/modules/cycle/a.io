@@ -0,0 +1,3 @@
+module cycle.a;
+
+import cycle.b;
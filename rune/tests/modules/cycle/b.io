@@ -0,0 +1,3 @@
+module cycle.b;
+
+import cycle.a;
@@ -0,0 +1,18 @@
+module geometry;
+
+import shapes { Circle, Square as Box };
+
+pub area: (shape) => shape.area();
+
+pub circle: (r) => Circle(r);
+pub square: (side) => Box(side);
+
+pub type Point: struct{
+  pub x, y: i32;
+  tag: Utf8;
+};
+
+pub origin: () => Point{x: 0, y: 0, tag: "origin"};
+pub tag: (p: Point) => p.tag;
+
+secret: () => 42;
@@ -0,0 +1,5 @@
+module overload;
+
+pub extern twice : (value: i64) -> i64;
+
+pub twice : (value) => value * 2;
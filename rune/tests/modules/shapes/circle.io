@@ -0,0 +1,6 @@
+module shapes;
+
+pub Circle: (r) => {
+  area: () => 3 * r * r,
+  name: "circle",
+};
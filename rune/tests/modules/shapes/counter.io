@@ -0,0 +1 @@
+pub let loaded = 0;
@@ -0,0 +1,8 @@
+module shapes;
+
+pub Square: (side) => {
+  area: () => side * side,
+  name: "square",
+};
+
+loaded = loaded + 1;
@@ -0,0 +1,4 @@
+// a native called with too few arguments is an arity error
+import math { pow };
+
+println(pow(2));
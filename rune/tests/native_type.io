@@ -0,0 +1,4 @@
+// a native called with an argument of the wrong type is a type error
+import math { sqrt };
+
+println(sqrt("nine"));
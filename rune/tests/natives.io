@@ -0,0 +1,10 @@
+// typed natives convert their arguments and results
+import math { pow, parse, random };
+
+println(pow(2, 10), parse(" 2.5 ").unwrap_or(0.0));
+
+let r = random();
+println(r >= 0.0, r < 1.0, r != random());
+
+// text that isn't a number is an Error the script handles
+println(parse("two").to_string());
@@ -0,0 +1,14 @@
+// panic halts the program with the calls in progress
+import core { panic };
+
+withdraw: (balance, amount) = {
+  if amount > balance {
+    panic("insufficient funds");
+  }
+  balance - amount
+}
+
+checkout: (balance, prices) => prices.fold(balance, (left, price) => withdraw(left, price));
+
+println(checkout(10, [2, 3]));
+println(checkout(10, [4, 5, 6]));
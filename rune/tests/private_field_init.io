@@ -0,0 +1,3 @@
+// a struct of another module can't be built with its private fields
+import geometry { Point };
+println(Point{x: 1, y: 2, tag: "mine"});
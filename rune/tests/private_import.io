@@ -0,0 +1,2 @@
+// private names can't be imported
+import geometry { secret };
@@ -0,0 +1,3 @@
+// private declarations can't be reached through the module either
+import geometry;
+println(geometry.secret);
@@ -0,0 +1,38 @@
+// `..` and `..=` include the end, `..<` excludes it
+import core { catch_panic };
+
+sum = 0;
+for i in 1..10 {
+  sum += i;
+}
+println("1..10", sum);
+
+digits = 0..<10;
+println(digits, digits.len(), digits.contains(9), digits.contains(10));
+
+name = "rune language";
+println(name[0..<4], name[5..=12], name[0]);
+
+n = 4;
+squares = "";
+for i in 0..<n {
+  squares = squares + i * i + " ";
+}
+println(squares);
+
+// a call ends a range like any other expression
+size: () => 3;
+ends = "";
+for i in 0..size() {
+  ends = ends + i;
+}
+for i in 0..<size() {
+  ends = ends + i;
+}
+println(ends, (0..size()).len(), (1..<size()).to_string());
+
+// bounds at the edge of the integers overflow like arithmetic does
+println(catch_panic(() => 0..=9223372036854775807).to_string());
+low = 0 - 2;
+println(catch_panic(() => (low..<9223372036854775807).len()).to_string());
+println(name[10..<20]);
@@ -0,0 +1,22 @@
+// function declarations are hoisted to the top of their block
+check: (n: i32) = {
+  // is_even is called before it is declared
+  result = is_even(n);
+
+  is_even: (n: i32) -> bool = {
+    if n == 0 { true } else { is_odd(n - 1) }
+  }
+
+  is_odd: (n: i32) -> bool = {
+    if n == 0 { false } else { is_even(n - 1) }
+  }
+
+  result
+}
+
+println(check(10), check(7));
+
+// unbounded recursion raises a runtime error instead of crashing the host
+// the addition keeps the call out of tail position, a tail call would loop forever instead
+forever: (n: i32) => 1 + forever(n + 1);
+println(forever(0));
@@ -0,0 +1,41 @@
+// variables are resolved to frame slots before the program runs
+
+// a function can call one declared after it
+even: (n) {
+  if n == 0 { return true }
+  odd(n - 1)
+}
+odd: (n) {
+  if n == 0 { return false }
+  even(n - 1)
+}
+println("mutual", even(10), odd(7));
+
+// locals of nested functions and closures, two levels out
+counter: (start) {
+  count = start;
+  step: (by) {
+    bump = () { count = count + by; count };
+    bump()
+  }
+  step(1);
+  step(10)
+}
+println("nested", counter(5));
+
+// a local shadows the global of the same name
+name = "global";
+greet: () {
+  let name = "local";
+  name
+}
+println("shadow", greet(), name);
+
+// if-let bindings get their own slots
+total = 0;
+for item in [Some(1), None, Some(3)] {
+  if Some(total) = item {
+    println("branch", total);
+  }
+}
+println("after", total);
@@ -0,0 +1,8 @@
+// a local used before its declaration fails before anything runs, even when a global has its name
+println("unreachable");
+later = 1;
+late: () {
+  value = later + 1;
+  let later = 2;
+  value
+}
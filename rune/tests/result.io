@@ -0,0 +1,89 @@
+import core { Result, Maybe as Option };
+
+parse_digit: (text) = {
+  digits = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+  for i in 0..<digits.len() {
+    if digits[i] == text {
+      return Ok(i);
+    }
+  }
+  Error("not a digit: " + text)
+}
+
+// ? hands the first Error back to the caller
+sum_digits: (a, b) = {
+  x = parse_digit(a)?;
+  Ok(x + parse_digit(b)?)
+}
+
+find: (items, wanted) = {
+  for item in items {
+    if item == wanted {
+      return Some(item);
+    }
+  }
+  None
+}
+
+first_even_doubled: (items) = {
+  found = find(items, 4)?;
+  Some(found * 2)
+}
+
+show: (values) => values.map((e) => e.to_string()).join(", ");
+
+println(show([sum_digits("4", "5"), sum_digits("4", "x"), sum_digits("y", "5")]));
+println(show([first_even_doubled([1, 4, 5]), first_even_doubled([1, 3])]));
+
+if Error(e) = sum_digits("a", "1") {
+  println("failed", e);
+}
+if Ok(n) = sum_digits("2", "3") {
+  println("sum", n);
+} else {
+  println("unreachable");
+}
+if let Some(x) = find([1, 2], 3) {
+  println("unreachable", x);
+} else {
+  println("not found");
+}
+if None = find([], 1) {
+  println("empty");
+}
+
+// combinators
+println(show([parse_digit("7").map((n) => n * 10), parse_digit("z").map((n) => n * 10)]));
+println(show([parse_digit("3").and_then((n) => parse_digit("" + (n + 1))), parse_digit("9").and_then((n) => Error("too big"))]));
+println(parse_digit("q").unwrap_or(0), find([8], 8).unwrap_or(0), find([], 8).unwrap_or(9));
+println(show([find([2], 2).ok_or("missing"), find([], 2).ok_or("missing")]));
+println(Ok(1).is_ok(), Error(1).is_error(), None.is_none(), Some(1).is_some());
+println(Result.Ok(1) == Ok(1), Option.None == None);
+
+// ? in a loop header or condition leaves the function the same way
+count_while: (flags) = {
+  n = 0;
+  while flags[n]? {
+    n = n + 1;
+  }
+  Ok(n)
+}
+sum_all: (items) = {
+  total = 0;
+  for x in items? {
+    total = total + x;
+  }
+  if Ok(total > 9)? {
+    return Error("unreachable");
+  }
+  Ok(total)
+}
+println(show([count_while([Ok(true), Ok(false)]), count_while([Ok(true), Error("bad flag")])]));
+println(show([sum_all(Ok([1, 2])), sum_all(Error("no items"))]));
+
+// ? only applies to a Result or Maybe
+bad: () = {
+  x = 5?;
+  x
+}
+println(bad());
@@ -0,0 +1,61 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// every script of tests/ prints what tests/expected/<name>.txt holds, on both backends
+/// they run through the interpreter from the repository root, where `core` and `math` are found under modules/
+/// BLESS=1 writes the output of the tree walker as the expected one
+#[test]
+fn scripts_print_the_expected_output() {
+  let tests = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+  let mut scripts: Vec<PathBuf> = fs::read_dir(&tests).unwrap()
+    .map(|e| e.unwrap().path())
+    .filter(|e| e.extension().is_some_and(|e| e == "io"))
+    .collect();
+  scripts.sort();
+  assert!(!scripts.is_empty(), "no scripts under {}", tests.display());
+
+  let failures: Vec<String> = thread::scope(|scope| {
+    let runs: Vec<_> = scripts.iter().map(|script| scope.spawn(|| check(script))).collect();
+    runs.into_iter().filter_map(|e| e.join().unwrap().err()).collect()
+  });
+  assert!(failures.is_empty(), "{} of {} scripts failed\n\n{}", failures.len(), scripts.len(), failures.join("\n\n"));
+}
+
+fn check(script: &Path) -> Result<(), String> {
+  let name = script.file_stem().unwrap().to_string_lossy();
+  let expected_path = script.parent().unwrap().join("expected").join(format!("{}.txt", name));
+  let tree = run(script, "tree")?;
+  if std::env::var_os("BLESS").is_some() {
+    fs::write(&expected_path, &tree).map_err(|e| format!("{}: {}", expected_path.display(), e))?;
+  }
+  let expected = fs::read_to_string(&expected_path).map_err(|e| format!("{}: {}", expected_path.display(), e))?;
+  for (backend, output) in [("tree", tree), ("vm", run(script, "vm")?)] {
+    if output != expected {
+      return Err(format!("{} on the {} backend\n--- expected\n{}--- found\n{}", name, backend, expected, output));
+    }
+  }
+  Ok(())
+}
+
+/// what the interpreter prints for [script], without the banner and prompt of the repl it ends in
+fn run(script: &Path, backend: &str) -> Result<String, String> {
+  let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+  let mut child = Command::new(env!("CARGO_BIN_EXE_rune"))
+    .current_dir(root)
+    .args(["--backend", backend])
+    .arg(script.strip_prefix(root).unwrap_or(script))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("unable to run {}: {}", script.display(), e))?;
+  child.stdin.take().unwrap().write_all(b"exit\n").unwrap();
+  let output = child.wait_with_output().unwrap();
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let stdout = stdout.strip_prefix("\nio.repl v.0.0.1\n").unwrap_or(&stdout);
+  let stdout = stdout.strip_suffix("$ ").unwrap_or(stdout);
+  Ok(format!("{}{}", stdout, String::from_utf8_lossy(&output.stderr)))
+}
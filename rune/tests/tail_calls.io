@@ -0,0 +1,42 @@
+// calls in tail position run in the frame of the caller, recursing through them takes constant stack
+
+// self recursion, the accumulator carries the result
+sum: (n, total) {
+  if n == 0 { return total }
+  sum(n - 1, total + n)
+}
+println("self", sum(1000000, 0));
+
+// mutual recursion
+is_even: (n) {
+  if n == 0 { true } else { is_odd(n - 1) }
+}
+is_odd: (n) {
+  if n == 0 { false } else { is_even(n - 1) }
+}
+println("mutual", is_even(1000000), is_odd(1000001));
+
+// returned from inside a loop
+countdown: (n) {
+  while true {
+    if n == 0 { break }
+    return countdown(n - 1)
+  }
+  "done"
+}
+println("from a loop", countdown(1000000));
+
+// through a local closure
+steps: (n) {
+  let go = (k, count) {
+    if k == 1 { count } else if k % 2 == 0 { go(k / 2, count + 1) } else { go(3 * k + 1, count + 1) }
+  };
+  go(n, 0)
+}
+println("closure", steps(27));
+
+// a call whose value is used afterwards still nests
+depth: (n) {
+  if n == 0 { 0 } else { 1 + depth(n - 1) }
+}
+println("not a tail call", depth(100));
@@ -0,0 +1,11 @@
+// a native called in tail position runs in place, the function calling it stays in the trace
+import core { panic };
+
+refuse: (reason) => panic(reason);
+
+checked: (amount) {
+  if amount < 0 { refuse("negative amount") } else { amount }
+}
+
+println(checked(3));
+println(checked(0 - 1));
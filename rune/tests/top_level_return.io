@@ -0,0 +1,4 @@
+// the program isn't a function, a `return` outside of one fails before anything runs
+println("unreachable");
+return 5;
+println("after");
@@ -0,0 +1,9 @@
+// like `return`, a `?` outside of a function fails before anything runs
+first: (items) = {
+  // inside a function it returns the None
+  item = items.get(0)?;
+  Some(item)
+}
+println(first([]));
+x = None?;
+println(x);
@@ -0,0 +1,68 @@
+// tuples, type declarations and destructuring
+pair = (1, "one");
+println(pair.to_string(), pair.0, pair[1], (7,).to_string(), ().to_string());
+
+// README swap, the right hand side is evaluated before anything is assigned
+a = 1;
+b = 2;
+a, b = b, a;
+println(a, b);
+
+sort<T: Ord>: (mut items: Vec<T>) {
+  for i = 0; i < items.len(); i+=1 {
+    max = i;
+    for j = i+1; j < items.len(); j+=1 {
+      if(items[max] < items[j]) {
+        max = j;
+      }
+    }
+    items[i], items[max] = items[max], items[i]
+  }
+}
+numbers = [3, 1, 4, 1, 5, 9, 2, 6];
+sort(numbers);
+println(numbers.to_string());
+
+let (x, (y, _)) = (10, (20, 30));
+println(x, y);
+
+type Vec3: struct{ x, y: i32; z: i32 };
+type Things: enum{
+  Car;
+  Paint(Color);
+  Part: struct{part: Utf8, count: u32};
+};
+
+origin = Vec3{x: 0, y: 0, z: 0};
+up = Vec3{x: 0, y: 1, z: 0};
+println(up.to_string(), up == origin, up.y);
+up.z = 5;
+let Vec3{x, y: height} = up;
+println(x, height, up.to_string());
+
+length: (Vec3{x, y, z}) -> i32 => x * x + y * y + z * z;
+println(length(up));
+
+swap: ((a, b): (i32, i32)) => (b, a);
+println(swap((1, 2)).to_string());
+
+paint = Things.Paint("red");
+let Things.Paint(color) = paint;
+println(paint.to_string(), color, Things.Car.to_string(), paint == Things.Paint("red"));
+Paint(color) = Things.Paint("blue");
+println(color);
+
+nested = (Vec3{x: 1, y: 2, z: 3}, 4);
+nested.0.x = 100;
+println(nested.to_string());
+
+totals = Map();
+totals[(1, 2)] = "pair key";
+println(totals[(1, 2)]);
+ages = Map();
+ages["ana"] = 31;
+for entry in ages.entries() {
+  let (name, age) = entry;
+  println(name, age);
+}
+let Things.Car = paint;
@@ -0,0 +1,6 @@
+// text beyond ascii, in strings, comments and names
+/* résumé — ünïcödé */
+greeting = "café, naïve, 日本語"; // 👋
+println(greeting.to_string(), greeting.len());
+für = "ß";
+println(für + "!");
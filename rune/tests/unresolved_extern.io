@@ -0,0 +1,4 @@
+// externs are resolved before the program runs, nothing is registered as main.missing
+println("never printed");
+
+extern missing: (a: i32) -> i32;
@@ -0,0 +1,30 @@
+// Vec is shared by reference, mutating methods are seen by every holder
+items = Vec(3, 5, 6, 7, 8, 1, 2);
+items.push(4);
+items.sort();
+println(items.to_string(), items.len(), items[0], items[1..<3].to_string());
+
+numbers = [1, 2, 3];
+doubled = numbers.map((n) => n * 2);
+evens = [1, 2, 3, 4, 5, 6].filter((n) => n % 2 == 0);
+sum = numbers.fold(0, (acc, n) => acc + n);
+println(doubled.to_string(), evens.to_string(), sum);
+
+numbers.insert(0, 0);
+removed = numbers.remove(3);
+numbers.reverse();
+println(numbers.join(", "), removed, numbers.pop(), Vec().pop(), numbers.contains(2), numbers.contains(9));
+
+append: (mut target: Vec<i32>, value: i32) = {
+  target.push(value);
+}
+append(numbers, 42);
+numbers[0] = 7;
+println(numbers.to_string(), [1, [2, 3]] == [1, [2, 3]]);
+
+total = 0;
+for n in numbers {
+  total += n;
+}
+println(total);
+println(numbers[10]);